@@ -1,40 +1,367 @@
-use reqwest::{self, Url, IntoUrl, Method};
+use reqwest::{Url, Method};
 use select::document::Document;
 use select::node::Node;
 use select::predicate::{Attr, Name, Class, And};
+use selectors::Selectors;
+use cassette::Cassette;
+use source::{DocSource, HttpSource, CassetteSource};
+use release_notes::{ReleaseNote, parse_release_notes};
+use rate_limits::{RateLimits, parse_rate_limits_page};
+use model_cache::{ModelCache, content_hash, selectors_hash};
 use errors::*;
-use errors::ErrorKind::SpecParseError;
+use errors::ErrorKind::{SpecParseError, FailureBudgetExceeded};
 
-use std::io::Read;
+use serde_json::{self, Value};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
+use std::path::PathBuf;
 
 const SPEC_BASE_URL : &'static str = "https://start.exactonline.nl/docs/";
 const SPEC_OVERVIEW : &'static str = "HlpRestAPIResources.aspx";
-const SPEC_DETAIL : &'static str = "HlpRestAPIResourcesDetails.aspx";
+const SPEC_RELEASE_NOTES : &'static str = "HlpRestAPIReleaseNotes.aspx";
+const SPEC_RATE_LIMITS : &'static str = "HlpRestAPIRateLimits.aspx";
 
-fn fetch_document<T: IntoUrl>(url: T) -> Result<Document> {
-    let mut response = reqwest::get(url)?;
-    let mut body = String::new();
-    response.read_to_string(&mut body)?;
-    Ok(Document::from(body.as_str()))
+/// Settings for downloading the documentation pages
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct FetchOptions {
+    /// Value for the `Cookie` header, e.g. a logged-in session copied from the browser,
+    /// for documentation pages that are only available after logging in
+    pub cookies: Option<String>,
+    /// Value for the `Accept-Language` header, e.g. `en-US` or `nl-NL`, so all pages of a run
+    /// are in the same language
+    pub language: Option<String>,
+    /// Record pages to, or replay them from, a directory instead of only fetching them live
+    pub cassette: Option<Cassette>,
+    /// When to stop crawling because the site is failing
+    pub failure_budget: FailureBudget,
+    pub tls: TlsOptions,
 }
 
-pub fn fetch_endpoint_urls() -> Result<Vec<Url>> {
-    let overview_url = Url::parse(&(SPEC_BASE_URL.to_owned() + SPEC_OVERVIEW))?;
-    let document = fetch_document(overview_url)?;
-    let mut urls: Vec<Url> = document
+/// TLS settings for fetching live, e.g. behind a proxy intercepting TLS with a private CA
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TlsOptions {
+    /// DER encoded certificates to trust in addition to the system roots
+    pub root_certificates: Vec<PathBuf>,
+    /// Accept certificates issued for other hosts. Only for proxies that can't issue proper ones.
+    pub danger_disable_hostname_verification: bool,
+}
+
+/// How many failed pages a crawl tolerates before giving up
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FailureBudget {
+    /// Give up after this many pages in a row failed
+    pub max_consecutive: Option<u32>,
+    /// Give up when more than this fraction of the pages failed
+    pub max_error_rate: Option<f32>,
+    /// Pages to fetch before the error rate is taken into account
+    pub min_attempts: u32,
+}
+
+impl Default for FailureBudget {
+    fn default() -> FailureBudget {
+        FailureBudget {
+            max_consecutive: None,
+            max_error_rate: None,
+            min_attempts: 20,
+        }
+    }
+}
+
+impl FailureBudget {
+    pub fn tracker(&self) -> FailureTracker {
+        FailureTracker { budget: self.clone(), attempts: 0, failures: 0, consecutive: 0 }
+    }
+}
+
+/// Counts the outcomes of a crawl against a `FailureBudget`
+#[derive(Clone, Debug)]
+pub struct FailureTracker {
+    budget: FailureBudget,
+    attempts: u32,
+    failures: u32,
+    consecutive: u32,
+}
+
+impl FailureTracker {
+    /// Records the outcome of fetching a page, failing once the budget is spent
+    pub fn record(&mut self, success: bool) -> Result<()> {
+        self.attempts += 1;
+        if success {
+            self.consecutive = 0;
+            return Ok(());
+        }
+        self.failures += 1;
+        self.consecutive += 1;
+        let too_many_consecutive = self.budget.max_consecutive.map_or(false, |max| self.consecutive >= max);
+        let too_high_rate = self.attempts >= self.budget.min_attempts && self.budget.max_error_rate
+            .map_or(false, |max| self.failures as f32 / self.attempts as f32 > max);
+        if too_many_consecutive || too_high_rate {
+            bail!(FailureBudgetExceeded(self.failures, self.attempts));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn it_stops_when_the_failure_budget_is_spent() {
+    let mut tracker = FailureBudget { max_consecutive: Some(3), ..FailureBudget::default() }.tracker();
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(true).is_ok());
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(false).is_err());
+
+    let mut tracker = FailureBudget { max_error_rate: Some(0.5), min_attempts: 4, ..FailureBudget::default() }.tracker();
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(false).is_ok());
+    assert!(tracker.record(false).is_err());
+
+    let mut tracker = FailureBudget::default().tracker();
+    for _ in 0..100 {
+        assert!(tracker.record(false).is_ok());
+    }
+}
+
+/// Downloads and parses the documentation pages
+pub struct Scraper<S: DocSource = Box<DocSource>> {
+    source: S,
+    options: FetchOptions,
+    selectors: Selectors,
+}
+
+impl Scraper {
+    /// Scrapes the live site, through the cassette if one is configured
+    pub fn new(options: FetchOptions, selectors: Selectors) -> Result<Scraper> {
+        let http = HttpSource::new(&options)?;
+        let source: Box<DocSource> = match options.cassette {
+            Some(ref cassette) => Box::new(CassetteSource::new(cassette.clone(), http)),
+            None => Box::new(http),
+        };
+        Ok(Scraper {
+            source: source,
+            options: options,
+            selectors: selectors,
+        })
+    }
+}
+
+impl<S: DocSource> Scraper<S> {
+    /// Scrapes the pages of the given source
+    pub fn with_source(source: S, selectors: Selectors) -> Scraper<S> {
+        Scraper {
+            source: source,
+            options: FetchOptions::default(),
+            selectors: selectors,
+        }
+    }
+
+    pub fn options(&self) -> &FetchOptions {
+        &self.options
+    }
+
+    pub fn selectors(&self) -> &Selectors {
+        &self.selectors
+    }
+
+    fn fetch_document(&self, url: Url) -> Result<Document> {
+        let body = self.fetch_page(url)?;
+        Ok(Document::from(body.as_str()))
+    }
+
+    fn fetch_page(&self, url: Url) -> Result<String> {
+        self.source.fetch_page(&url)
+    }
+
+    /// Collects the links to the endpoints from the overview, following its continuation pages
+    pub fn fetch_endpoint_index(&self) -> Result<Vec<EndpointLink>> {
+        let overview_url = Url::parse(&(SPEC_BASE_URL.to_owned() + SPEC_OVERVIEW))?;
+        let mut pending = VecDeque::new();
+        pending.push_back(overview_url);
+        let mut visited = HashSet::new();
+        let mut links = Vec::new();
+        while let Some(url) = pending.pop_front() {
+            if !visited.insert(url.to_string()) {
+                continue;
+            }
+            let document = self.fetch_document(url)?;
+            links.extend(parse_endpoint_index(&document, &self.selectors));
+            pending.extend(parse_overview_continuations(&document, &self.selectors));
+        }
+        dedup_links(&mut links);
+        Ok(links)
+    }
+
+    pub fn fetch_endpoint_urls(&self) -> Result<Vec<Url>> {
+        Ok(self.fetch_endpoint_index()?.into_iter().map(|link| link.url).collect())
+    }
+
+    pub fn fetch_endpoint_details(&self, url: Url) -> Result<EndpointDetails> {
+        let document = self.fetch_document(url.clone())?;
+        parse_endpoint_details(url, &document, &self.selectors)
+    }
+
+    /// Fetches the REST API release notes, listing added and removed endpoints and properties
+    pub fn fetch_release_notes(&self) -> Result<Vec<ReleaseNote>> {
+        let url = Url::parse(&(SPEC_BASE_URL.to_owned() + SPEC_RELEASE_NOTES))?;
+        let document = self.fetch_document(url)?;
+        Ok(parse_release_notes(&document))
+    }
+
+    /// Fetches the documented per-minute and daily call limits
+    pub fn fetch_rate_limits(&self) -> Result<RateLimits> {
+        let url = Url::parse(&(SPEC_BASE_URL.to_owned() + SPEC_RATE_LIMITS))?;
+        let document = self.fetch_document(url)?;
+        Ok(parse_rate_limits_page(&document))
+    }
+
+    /// Re-fetches at most `budget` of the pages that were recorded longest ago into the cassette,
+    /// returning the urls that were refreshed
+    pub fn refresh(&self, budget: usize) -> Result<Vec<Url>> {
+        let cassette = self.options.cassette.as_ref()
+            .ok_or("refreshing requires a cassette to record to")?;
+        let live = HttpSource::new(&self.options)?;
+        let mut refreshed = Vec::new();
+        for entry in cassette.stalest(budget)? {
+            let body = live.fetch_page(&entry.url)?;
+            cassette.store(&entry.url, &body)?;
+            refreshed.push(entry.url);
+        }
+        Ok(refreshed)
+    }
+
+    /// Fetches the details of an endpoint, including what is known about it from the overview
+    pub fn fetch_endpoint(&self, link: &EndpointLink) -> Result<EndpointDetails> {
+        let mut details = self.fetch_endpoint_details(link.url.clone())?;
+        details.service = link.service.clone();
+        Ok(details)
+    }
+
+    /// Like `fetch_endpoint`, but reuses the details in `cache` when the page did not change
+    pub fn fetch_endpoint_incremental(&self, link: &EndpointLink, cache: &mut ModelCache) -> Result<EndpointDetails> {
+        let body = self.fetch_page(link.url.clone())?;
+        let hash = content_hash(&body);
+        let selectors = selectors_hash(&self.selectors);
+        let mut details = match cache.lookup(&link.url, hash, selectors) {
+            Some(details) => details,
+            None => {
+                let document = Document::from(body.as_str());
+                let details = parse_endpoint_details(link.url.clone(), &document, &self.selectors)?;
+                cache.store(&link.url, hash, selectors, &details)?;
+                details
+            }
+        };
+        details.service = link.service.clone();
+        Ok(details)
+    }
+}
+
+/// An endpoint as listed on the overview page
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndpointLink {
+    pub url: Url,
+    /// The service area the overview lists the endpoint under, e.g. `Manufacturing`
+    pub service: Option<String>,
+}
+
+/// The service column of the overview table row containing `link`
+fn overview_service(link: Node) -> Option<String> {
+    let mut node = link.parent();
+    while let Some(n) = node {
+        if n.name() == Some("tr") {
+            return n.find(Name("td")).next()
+                .map(|td| td.text().trim().to_owned())
+                .and_then(|service| if service.is_empty() { None } else { Some(service) });
+        }
+        node = n.parent();
+    }
+    None
+}
+
+fn parse_endpoint_index(document: &Document, selectors: &Selectors) -> Vec<EndpointLink> {
+    let mut links: Vec<EndpointLink> = document
         .find(And(Name("a"), Attr("href", ())))
         .filter_map(|node| {
             let href = node.attr("href").unwrap();
-            if href.starts_with(SPEC_DETAIL) {
+            if href.starts_with(&selectors.detail_link_prefix) {
                 Url::parse(&(SPEC_BASE_URL.to_owned() + href)).ok()
+                    .map(|url| EndpointLink { url: canonical_detail_url(&url).1, service: overview_service(node) })
             }
             else {
                 None
             }
         }).collect();
-    urls.dedup();
-    Ok(urls)
+    dedup_links(&mut links);
+    links
+}
+
+/// Links to other pages or sections of the overview, e.g. `HlpRestAPIResources.aspx?page=2`
+fn parse_overview_continuations(document: &Document, selectors: &Selectors) -> Vec<Url> {
+    document.find(And(Name("a"), Attr("href", ())))
+        .filter_map(|node| node.attr("href"))
+        .filter(|href| href.starts_with(&selectors.overview_continuation_prefix))
+        .filter_map(|href| Url::parse(&(SPEC_BASE_URL.to_owned() + href)).ok())
+        .collect()
+}
+
+/// The same resource may be linked from several places, keeps the first link to it
+fn dedup_links(links: &mut Vec<EndpointLink>) {
+    let mut seen = HashSet::new();
+    links.retain(|link| seen.insert(canonical_detail_url(&link.url).0));
+}
+
+/// The documentation url of a resource without incidental variations like extra query parameters
+/// or a fragment, along with the case insensitive resource name identifying it
+fn canonical_detail_url(url: &Url) -> (String, Url) {
+    let name = url.query_pairs()
+        .find(|&(ref key, _)| key.to_lowercase() == "name")
+        .map(|(_, value)| value.into_owned());
+    match name {
+        Some(name) => {
+            let mut canonical = url.clone();
+            canonical.set_fragment(None);
+            canonical.query_pairs_mut().clear().append_pair("name", &name);
+            (name.to_lowercase(), canonical)
+        },
+        None => (url.as_str().to_lowercase(), url.clone()),
+    }
+}
+
+#[test]
+fn it_canonicalizes_detail_urls() {
+    let url = Url::parse("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?Name=CRMAccounts&tab=1#top").unwrap();
+    let (key, canonical) = canonical_detail_url(&url);
+    assert_eq!(key, "crmaccounts");
+    assert_eq!(canonical.as_str(), "https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=CRMAccounts");
+    let other = Url::parse("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=crmaccounts").unwrap();
+    assert_eq!(canonical_detail_url(&other).0, key);
+}
+
+pub fn fetch_endpoint_index() -> Result<Vec<EndpointLink>> {
+    Scraper::new(FetchOptions::default(), Selectors::default())?.fetch_endpoint_index()
+}
+
+pub fn fetch_release_notes() -> Result<Vec<ReleaseNote>> {
+    Scraper::new(FetchOptions::default(), Selectors::default())?.fetch_release_notes()
+}
+
+pub fn fetch_rate_limits() -> Result<RateLimits> {
+    Scraper::new(FetchOptions::default(), Selectors::default())?.fetch_rate_limits()
+}
+
+pub fn fetch_endpoint_urls() -> Result<Vec<Url>> {
+    Scraper::new(FetchOptions::default(), Selectors::default())?.fetch_endpoint_urls()
+}
+
+#[test]
+fn it_groups_endpoints_by_service() {
+    let links = fetch_endpoint_index().expect("Successfully fetched endpoints");
+    let grouped = links.iter().filter(|l| l.service.is_some()).count();
+    assert!(grouped * 2 > links.len(), "Most endpoints should be listed under a service");
 }
 
 #[test]
@@ -51,27 +378,261 @@ fn it_fetches_endpoint_urls() {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct EndpointDetails {
     pub name: String,
     pub uri: String,
+    /// The introductory paragraph of the documentation page
+    pub description: Option<String>,
+    /// The documentation page these details were scraped from
+    #[serde(with = "::serde_with::url_string")]
+    pub doc_url: Url,
+    /// Whether the uri contains a `{division}` segment that callers must fill in
+    pub requires_division: bool,
+    pub kind: EndpointKind,
+    /// The service area from the overview page, only known when fetched through an `EndpointLink`
+    pub service: Option<String>,
+    /// The maximum number of records returned per page
+    pub max_page_size: u32,
+    /// Parameters of function imports, empty for other kinds of endpoints
+    pub function_parameters: Vec<FunctionParameter>,
+    /// Whether the endpoint returns a single object rather than a collection
+    pub singleton: bool,
+    /// Notes restricting a single method, e.g. "POST only allowed for draft invoices",
+    /// keyed by the method name
+    pub method_remarks: BTreeMap<String, String>,
+    /// Whether Exact marks the endpoint as beta, i.e. subject to change
+    pub beta: bool,
+    /// Whether Exact marks the endpoint as deprecated
+    pub deprecated: bool,
+    /// The endpoint the docs point to instead, for deprecated endpoints
+    pub replaced_by: Option<String>,
+    /// The Exact Online packages of which at least one is needed to call the endpoint,
+    /// empty when available in every subscription
+    pub required_packages: Vec<String>,
+    /// The OAuth scopes a token needs to call the endpoint
+    pub scopes: Vec<String>,
+    /// The OData query options the endpoint accepts on GET
+    pub query_options: Vec<QueryOption>,
     pub properties: Vec<Property>,
+    #[serde(with = "::serde_with::error_messages")]
     pub failed_properties: Vec<Error>,
+    #[serde(with = "::serde_with::methods")]
     pub methods: Vec<Method>,
+    /// Methods listed in the docs that aren't GET, POST, PUT or DELETE
+    pub unknown_methods: Vec<String>,
+    /// Sample payloads from the docs, keyed by the method they are sent or returned with
+    #[serde(default)]
+    pub examples: BTreeMap<String, Value>,
 }
 
-#[derive(Clone, Debug)]
+/// The flavours of resources listed on the overview page
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndpointKind {
+    /// A regular resource
+    Standard,
+    /// A `bulk/` variant of a resource, which returns larger pages
+    Bulk,
+    /// A `sync/` resource, which returns records changed since a given `Timestamp`
+    Sync,
+    /// An OData function import or action, invoked with parameters rather than addressed as a collection
+    Function,
+}
+
+impl EndpointKind {
+    /// The page size Exact uses for this kind of endpoint unless documented otherwise
+    pub fn default_page_size(&self) -> u32 {
+        match *self {
+            EndpointKind::Standard | EndpointKind::Function => 60,
+            EndpointKind::Bulk | EndpointKind::Sync => 1000,
+        }
+    }
+
+    pub fn from_uri(uri: &str) -> EndpointKind {
+        let segments: Vec<String> = uri.split('/').map(|s| s.trim().to_lowercase()).collect();
+        if segments.iter().any(|s| s == "bulk") {
+            EndpointKind::Bulk
+        } else if segments.iter().any(|s| s == "sync") {
+            EndpointKind::Sync
+        } else if uri.contains('(') || uri.contains('?') {
+            EndpointKind::Function
+        } else {
+            EndpointKind::Standard
+        }
+    }
+}
+
+/// The OData system query options an endpoint may support
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum QueryOption {
+    Filter,
+    Select,
+    OrderBy,
+    Top,
+    Skip,
+    Expand,
+    InlineCount,
+}
+
+impl QueryOption {
+    /// Parses names as written in the docs, e.g. `$filter`
+    pub fn from_name(name: &str) -> Option<QueryOption> {
+        match name.trim().trim_left_matches('$').to_lowercase().as_str() {
+            "filter" => Some(QueryOption::Filter),
+            "select" => Some(QueryOption::Select),
+            "orderby" => Some(QueryOption::OrderBy),
+            "top" => Some(QueryOption::Top),
+            "skip" => Some(QueryOption::Skip),
+            "expand" => Some(QueryOption::Expand),
+            "inlinecount" => Some(QueryOption::InlineCount),
+            _ => None,
+        }
+    }
+
+    /// The options listed in the docs, each once, or the defaults when the docs list none
+    pub fn from_names<'a, I: Iterator<Item=&'a str>>(names: I, kind: EndpointKind) -> Vec<QueryOption> {
+        let mut options: Vec<QueryOption> = names.filter_map(QueryOption::from_name).collect();
+        options.sort();
+        options.dedup();
+        if options.is_empty() { QueryOption::defaults(kind) } else { options }
+    }
+
+    /// The options assumed when the docs don't list any
+    pub fn defaults(kind: EndpointKind) -> Vec<QueryOption> {
+        match kind {
+            EndpointKind::Function => Vec::new(),
+            _ => vec![QueryOption::Filter, QueryOption::Select, QueryOption::OrderBy, QueryOption::Top,
+                QueryOption::Skip, QueryOption::Expand, QueryOption::InlineCount],
+        }
+    }
+}
+
+#[test]
+fn it_parses_query_options() {
+    assert_eq!(QueryOption::from_name("$filter"), Some(QueryOption::Filter));
+    assert_eq!(QueryOption::from_name(" $orderby"), Some(QueryOption::OrderBy));
+    assert_eq!(QueryOption::from_name("$InlineCount"), Some(QueryOption::InlineCount));
+    assert_eq!(QueryOption::from_name("$format"), None);
+    // Pages listing an option again further down
+    assert_eq!(QueryOption::from_names(vec!["$top", "$filter", "$top"].into_iter(), EndpointKind::Standard),
+        vec![QueryOption::Filter, QueryOption::Top]);
+    assert_eq!(QueryOption::from_names(vec!["$format"].into_iter(), EndpointKind::Standard),
+        QueryOption::defaults(EndpointKind::Standard));
+}
+
+/// The property sync endpoints are keyed and ordered by
+pub const SYNC_TIMESTAMP: &'static str = "Timestamp";
+
+#[test]
+fn it_detects_endpoint_kinds() {
+    assert_eq!(EndpointKind::from_uri("/api/v1/{division}/crm/Accounts"), EndpointKind::Standard);
+    assert_eq!(EndpointKind::from_uri("/api/v1/{division}/bulk/CRM/Accounts"), EndpointKind::Bulk);
+    assert_eq!(EndpointKind::from_uri("/api/v1/{division}/sync/CRM/Accounts"), EndpointKind::Sync);
+    assert_eq!(EndpointKind::from_uri("/api/v1/{division}/read/financial/Revenue?year={year}"), EndpointKind::Function);
+}
+
+/// A parameter of a function import, taken from its documented uri template
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FunctionParameter {
+    pub name: String,
+    pub edm_type: EdmType,
+}
+
+impl FunctionParameter {
+    /// Parses the parameters out of uris like `.../Function(id=guid'{id}',year={year})`
+    /// or `.../Function?code='{code}'&year={year}`
+    pub fn from_uri(uri: &str) -> Vec<FunctionParameter> {
+        let start = match uri.find(|c| c == '(' || c == '?') {
+            Some(i) => i + 1,
+            None => return Vec::new(),
+        };
+        uri[start..].trim_right_matches(')')
+            .split(|c| c == ',' || c == '&')
+            .filter_map(|assignment| {
+                let mut parts = assignment.splitn(2, '=');
+                let name = parts.next()?.trim();
+                let value = parts.next()?.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                let edm_type = if value.starts_with("guid'") {
+                    EdmType::Guid
+                } else if value.starts_with("datetime'") {
+                    EdmType::DateTime
+                } else if value.starts_with('\'') {
+                    EdmType::String
+                } else {
+                    EdmType::Int32
+                };
+                Some(FunctionParameter { name: name.to_owned(), edm_type: edm_type })
+            }).collect()
+    }
+}
+
+/// The uri of a function without its parameter template
+pub fn function_path(uri: &str) -> &str {
+    uri.split(|c| c == '(' || c == '?').next().unwrap_or(uri)
+}
+
+#[test]
+fn it_parses_function_parameters() {
+    let params = FunctionParameter::from_uri("/api/v1/{division}/read/Report(id=guid'{id}',year={year},code='{code}')");
+    assert_eq!(params, vec![
+        FunctionParameter { name: "id".to_owned(), edm_type: EdmType::Guid },
+        FunctionParameter { name: "year".to_owned(), edm_type: EdmType::Int32 },
+        FunctionParameter { name: "code".to_owned(), edm_type: EdmType::String },
+    ]);
+    let params = FunctionParameter::from_uri("/api/v1/{division}/read/Revenue?year={year}");
+    assert_eq!(params, vec![FunctionParameter { name: "year".to_owned(), edm_type: EdmType::Int32 }]);
+    assert_eq!(function_path("/api/v1/{division}/read/Revenue?year={year}"), "/api/v1/{division}/read/Revenue");
+    assert!(FunctionParameter::from_uri("/api/v1/{division}/crm/Accounts").is_empty());
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Property {
     pub name: String,
     pub edm_type: EdmType,
     pub description: Option<String>,
     pub key: bool,
+    #[serde(with = "::serde_with::methods")]
     pub methods: Vec<Method>,
+    /// The documented default value, as written in the docs
+    pub default: Option<String>,
+    /// Values the property is limited to, when the description enumerates them
+    pub allowed_values: Vec<AllowedValue>,
+    /// Whether the value may be null. Taken from the docs when they say, otherwise
+    /// every property but the keys is assumed to be nullable.
+    pub nullable: bool,
+    /// Whether the property must be given when creating an entity
+    pub mandatory: bool,
+    /// Maximum number of characters of a string, when documented
+    pub max_length: Option<u32>,
+    /// Total number of digits of a number, when documented
+    pub precision: Option<u32>,
+    /// Number of digits after the decimal point, when documented
+    pub scale: Option<u32>,
+    /// Whether the property refers to other entities, its type then names their entity set
+    #[serde(default)]
+    pub navigation: bool,
+}
+
+/// One of the values of an enumeration like "1 = Open, 2 = Processed"
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AllowedValue {
+    pub value: String,
+    pub description: String,
 }
 
 impl<'a> TryFrom<Node<'a>> for Property {
     type Error = Error;
     fn try_from(n: Node<'a>) -> Result<Property> {
+        Property::from_row(n, &Selectors::default())
+    }
+}
+
+impl Property {
+    /// Parses a row of the reference table
+    pub fn from_row(n: Node, selectors: &Selectors) -> Result<Property> {
         let input = n.find(Name("input")).next()
             .ok_or(SpecParseError(format!("could not find name and type of Property: {:?}", n)))?;
         let description = n.children().nth(n.children().count() - 2)
@@ -83,33 +644,133 @@ impl<'a> TryFrom<Node<'a>> for Property {
             Some(description)
         };
         let mut methods = Vec::new();
-        if n.find(Class("showget")).count() > 0 {
+        if n.find(Class(selectors.get_class.as_str())).count() > 0 {
             methods.push(Method::Get);
         }
-        if n.find(Class("showpost")).count() > 0 {
+        if n.find(Class(selectors.post_class.as_str())).count() > 0 {
             methods.push(Method::Post);
         }
-        if n.find(Class("showput")).count() > 0 {
+        if n.find(Class(selectors.put_class.as_str())).count() > 0 {
             methods.push(Method::Put);
         }
-        if n.find(Class("showdelete")).count() > 0 {
+        if n.find(Class(selectors.delete_class.as_str())).count() > 0 {
             methods.push(Method::Delete);
         }
+        let default = input.attr(&selectors.property_default_attr)
+            .map(|d| d.trim().to_owned())
+            .or_else(|| description.as_ref().and_then(|d| parse_default(d)));
+        let allowed_values = description.as_ref().map(|d| parse_allowed_values(d)).unwrap_or_default();
+        let key = input.attr(&selectors.property_key_attr) == Some("True");
+        let nullable = match input.attr(&selectors.property_nullable_attr) {
+            Some(nullable) => nullable == "True",
+            None => !key,
+        };
+        let navigation = input.attr(&selectors.property_navigation_attr) == Some("True");
+        let type_name = input.attr(&selectors.property_type_attr)
+            .ok_or(SpecParseError("could not find property type".to_owned()))?;
+        let edm_type = if navigation {
+            EdmType::entity_set(type_name)
+        } else {
+            type_name.try_into().chain_err(|| format!("While parsing property {:?}", input.attr("name")))?
+        };
         Ok(Property {
             name: input.attr("name")
                 .ok_or(SpecParseError("could not find property name".to_owned()))?.to_owned(),
-            edm_type: input.attr("data-type")
-                .ok_or(SpecParseError("could not find property type".to_owned()))?.try_into()
-                .chain_err(|| format!("While parsing property {:?}", input.attr("name")))?,
+            edm_type: edm_type,
             description: description,
-            key: input.attr("data-key") == Some("True"),
+            key: key,
             methods: methods,
+            default: default,
+            allowed_values: allowed_values,
+            nullable: nullable,
+            mandatory: input.attr(&selectors.property_mandatory_attr) == Some("True"),
+            max_length: input.attr(&selectors.property_max_length_attr).and_then(|l| l.trim().parse().ok()),
+            precision: input.attr(&selectors.property_precision_attr).and_then(|p| p.trim().parse().ok()),
+            scale: input.attr(&selectors.property_scale_attr).and_then(|s| s.trim().parse().ok()),
+            navigation: navigation,
         })
     }
 }
 
+/// Finds a default value in descriptions like "Status of the item. Default: 1"
+/// or "The default value is Open."
+fn parse_default(description: &str) -> Option<String> {
+    let markers = ["default value is", "default value:", "defaults to", "default:", "default ="];
+    for marker in markers.iter() {
+        if let Some(i) = find_ignore_ascii_case(description, marker) {
+            let rest = description[i + marker.len()..].trim_left();
+            let value: String = rest.chars()
+                .take_while(|&c| c != ',' && c != ';' && c != ')' && c != '\n')
+                .collect();
+            let value = value.trim().trim_right_matches('.').trim_matches(|c| c == '\'' || c == '"');
+            if !value.is_empty() {
+                return Some(value.to_owned());
+            }
+        }
+    }
+    None
+}
+
+/// Byte index of the first occurrence of the ASCII `needle` in `text`, ignoring ASCII case. Unlike
+/// searching the lowercased text, the index is one into `text`, which lowercasing can change the
+/// length of, like that of `İ`.
+fn find_ignore_ascii_case(text: &str, needle: &str) -> Option<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| text.as_bytes()[i..].get(..needle.len()).map_or(false, |b| b.eq_ignore_ascii_case(needle.as_bytes())))
+}
+
+/// Finds enumerations like "Status: 1 = Open, 2 = Processed" or "C=Credit; D=Debit" in descriptions.
+/// A single `x = y` is more likely prose than an enumeration, so at least two values are needed.
+fn parse_allowed_values(description: &str) -> Vec<AllowedValue> {
+    let values: Vec<AllowedValue> = description.split(|c| c == ',' || c == ';' || c == '\n')
+        .filter_map(|item| {
+            let mut sides = item.splitn(2, '=');
+            let value = sides.next()?.split_whitespace().last()?
+                .trim_matches(|c| c == ':' || c == '(' || c == '\'' || c == '"');
+            let label = sides.next()?.split(". ").next()?
+                .trim().trim_right_matches(|c| c == '.' || c == ')').trim();
+            let is_code = value.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            if value.is_empty() || label.is_empty() || !is_code {
+                return None;
+            }
+            Some(AllowedValue { value: value.to_owned(), description: label.to_owned() })
+        }).collect();
+    if values.len() < 2 {
+        Vec::new()
+    } else {
+        values
+    }
+}
+
+#[test]
+fn it_parses_allowed_values() {
+    let values = parse_allowed_values("Status: 1 = Open, 2 = Processed. Default 1");
+    assert_eq!(values, vec![
+        AllowedValue { value: "1".to_owned(), description: "Open".to_owned() },
+        AllowedValue { value: "2".to_owned(), description: "Processed".to_owned() },
+    ]);
+    let values = parse_allowed_values("Type of entry (C=Credit; D=Debit)");
+    assert_eq!(values, vec![
+        AllowedValue { value: "C".to_owned(), description: "Credit".to_owned() },
+        AllowedValue { value: "D".to_owned(), description: "Debit".to_owned() },
+    ]);
+    assert!(parse_allowed_values("Amount = quantity * price").is_empty());
+    assert!(parse_allowed_values("Code of the item").is_empty());
+}
+
+#[test]
+fn it_parses_defaults_from_descriptions() {
+    assert_eq!(parse_default("Status of the item. Default: 1"), Some("1".to_owned()));
+    assert_eq!(parse_default("The default value is Open."), Some("Open".to_owned()));
+    assert_eq!(parse_default("Currency (defaults to 'EUR')"), Some("EUR".to_owned()));
+    assert_eq!(parse_default("Code of the item"), None);
+    assert_eq!(parse_default("İİİİ ẞẞ Kelvin: K. Default: Open"), Some("Open".to_owned()));
+    assert_eq!(parse_default("Größe in KELVIN, DEFAULT VALUE IS 273"), Some("273".to_owned()));
+}
+
 // As defined in http://www.odata.org/documentation/odata-version-2-0/overview/#AbstractTypeSystem
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum EdmType {
     Null,
     Binary,
@@ -126,13 +787,23 @@ pub enum EdmType {
     SByte,
     String,
     Time,
-    DateTimeOffset
+    DateTimeOffset,
+    /// `Collection(...)` of another type
+    Collection(Box<EdmType>),
+    /// A named complex type, e.g. `Exact.Web.Api.Models.Address`, or the entity set
+    /// a navigation property refers to, e.g. `SalesInvoiceLines`
+    Complex(String),
 }
 
 impl<T: AsRef<str>> TryFrom<T> for EdmType {
     type Error = Error;
     fn try_from(s: T) -> Result<EdmType> {
-        match s.as_ref() {
+        let name = s.as_ref().trim();
+        if name.starts_with("Collection(") && name.ends_with(')') {
+            let element = &name["Collection(".len()..name.len() - 1];
+            return Ok(EdmType::Collection(Box::new(EdmType::try_from(element)?)));
+        }
+        match name {
             "Edm.Null" => Ok(EdmType::Null),
             "Edm.Binary" => Ok(EdmType::Binary),
             "Edm.Boolean" => Ok(EdmType::Boolean),
@@ -149,44 +820,416 @@ impl<T: AsRef<str>> TryFrom<T> for EdmType {
             "Edm.String" => Ok(EdmType::String),
             "Edm.Time" => Ok(EdmType::Time),
             "Edm.DateTimeOffset" => Ok(EdmType::DateTimeOffset),
-            _ => Err(SpecParseError(format!("Unknown type: {}", s.as_ref())).into())
+            _ if !name.starts_with("Edm.") && name.contains('.') => Ok(EdmType::Complex(name.to_owned())),
+            _ => Err(SpecParseError(format!("Unknown type: {}", name)).into())
         }
     }
 } 
 
+impl EdmType {
+    /// The type of a navigation property referring to the entity set, or a collection of it
+    pub fn entity_set(name: &str) -> EdmType {
+        let name = name.trim();
+        if name.starts_with("Collection(") && name.ends_with(')') {
+            return EdmType::Collection(Box::new(EdmType::entity_set(&name["Collection(".len()..name.len() - 1])));
+        }
+        EdmType::Complex(name.to_owned())
+    }
+
+    /// The name of the type as used in the documentation, e.g. `Edm.String`
+    pub fn name(&self) -> String {
+        let name = match *self {
+            EdmType::Null => "Edm.Null",
+            EdmType::Binary => "Edm.Binary",
+            EdmType::Boolean => "Edm.Boolean",
+            EdmType::Byte => "Edm.Byte",
+            EdmType::DateTime => "Edm.DateTime",
+            EdmType::Decimal => "Edm.Decimal",
+            EdmType::Double => "Edm.Double",
+            EdmType::Single => "Edm.Single",
+            EdmType::Guid => "Edm.Guid",
+            EdmType::Int16 => "Edm.Int16",
+            EdmType::Int32 => "Edm.Int32",
+            EdmType::Int64 => "Edm.Int64",
+            EdmType::SByte => "Edm.SByte",
+            EdmType::String => "Edm.String",
+            EdmType::Time => "Edm.Time",
+            EdmType::DateTimeOffset => "Edm.DateTimeOffset",
+            EdmType::Collection(ref element) => return format!("Collection({})", element.name()),
+            EdmType::Complex(ref name) => return name.clone(),
+        };
+        name.to_owned()
+    }
+}
+
+#[test]
+fn it_parses_collection_types() {
+    assert_eq!(EdmType::try_from("Collection(Edm.String)").unwrap(),
+        EdmType::Collection(Box::new(EdmType::String)));
+    assert_eq!(EdmType::try_from("Collection(Exact.Web.Api.Models.Address)").unwrap(),
+        EdmType::Collection(Box::new(EdmType::Complex("Exact.Web.Api.Models.Address".to_owned()))));
+    assert_eq!(EdmType::try_from("Collection(Edm.Int32)").unwrap().name(), "Collection(Edm.Int32)");
+    // Entity sets are only types of navigation properties
+    assert!(EdmType::try_from("SalesInvoiceLines").is_err());
+    assert_eq!(EdmType::entity_set("SalesInvoiceLines"), EdmType::Complex("SalesInvoiceLines".to_owned()));
+    assert!(EdmType::try_from("Edm.Unknown").is_err());
+    assert!(EdmType::try_from("not a type").is_err());
+    assert!(EdmType::try_from("Collection(Edm.Unknown)").is_err());
+}
 
-pub fn fetch_endpoint_details<T: IntoUrl>(url: T) -> Result<EndpointDetails> {
-    let document = fetch_document(url)?;
-    let name = document.find(Attr("id", "endpoint")).next()
+
+pub fn fetch_endpoint_details(url: Url) -> Result<EndpointDetails> {
+    Scraper::new(FetchOptions::default(), Selectors::default())?.fetch_endpoint_details(url)
+}
+
+fn parse_endpoint_details(url: Url, document: &Document, selectors: &Selectors) -> Result<EndpointDetails> {
+    let name = document.find(Attr("id", selectors.endpoint_id.as_str())).next()
         .ok_or(SpecParseError("name of endpoint not found".to_owned()))?
         .text();
-    let uri = document.find(Attr("id", "serviceUri")).next()
+    let uri = normalize_uri(&document.find(Attr("id", selectors.service_uri_id.as_str())).next()
         .ok_or(SpecParseError("uri of endpoint not found".to_owned()))?
-        .text();
+        .text());
+    let description = document.find(Attr("id", selectors.description_id.as_str())).next()
+        .map(|n| n.text().split_whitespace().collect::<Vec<_>>().join(" "))
+        .and_then(|d| if d.is_empty() { None } else { Some(d) });
     let (properties, failed_properties) = document
-        .find(Attr("id", "referencetable")).next()
+        .find(Attr("id", selectors.reference_table_id.as_str())).next()
         .ok_or(SpecParseError(format!("Endpoint {} - referencetable not found", name)))?
         .find(Name("tbody")).next()
         .ok_or(SpecParseError(format!("Endpoint {} - table body not found", name)))?
         // Skip the first row (header)
         .children().skip(1).filter(|c| c.name() == Some("tr"))
-        .map(Property::try_from)
+        .map(|row| Property::from_row(row, selectors))
         .partition(|r| r.is_ok());
     let properties : Vec<Result<Property>> = properties;
-    let properties: Vec<Property> = properties.into_iter().map(|p| p.unwrap()).collect();
+    let mut properties: Vec<Property> = properties.into_iter().map(|p| p.unwrap()).collect();
     let failed_properties: Vec<Error> = failed_properties.into_iter().map(|p| p.err().unwrap()).collect();
-    let methods = document.find(Attr("name", "supportedmethods"))
-        .filter_map(|n| {
-            match n.attr("value") {
-                Some("GET") => Some(Method::Get),
-                Some("POST") => Some(Method::Post),
-                Some("PUT") => Some(Method::Put),
-                Some("DELETE") => Some(Method::Delete),
-                Some(m) => panic!("Unrecognized method in Endpoint {}: {}", name, m),
-                None => None
+    let mut methods = Vec::new();
+    let mut unknown_methods = Vec::new();
+    for n in document.find(Attr("name", selectors.supported_methods_name.as_str())) {
+        match n.attr("value") {
+            Some("GET") => methods.push(Method::Get),
+            Some("POST") => methods.push(Method::Post),
+            Some("PUT") => methods.push(Method::Put),
+            Some("DELETE") => methods.push(Method::Delete),
+            Some(m) => unknown_methods.push(m.to_owned()),
+            None => {}
+        }
+    }
+    let requires_division = requires_division(&uri);
+    let kind = EndpointKind::from_uri(&uri);
+    if kind == EndpointKind::Sync {
+        // Sync endpoints are keyed by an ever increasing Int64 timestamp, whatever the docs mark as key
+        for p in properties.iter_mut() {
+            p.key = p.name == SYNC_TIMESTAMP;
+            if p.key {
+                p.edm_type = EdmType::Int64;
             }
-        }).collect();
-    Ok(EndpointDetails {name, uri, properties, failed_properties, methods})
+        }
+    }
+    let body_text = document.find(Name("body")).next().map(|body| body.text()).unwrap_or_default();
+    let max_page_size = parse_page_size(&body_text).unwrap_or(kind.default_page_size());
+    let remarks: Vec<String> = document.find(Class(selectors.remarks_class.as_str()))
+        .map(|n| n.text())
+        .collect();
+    let method_remarks = parse_method_remarks(&remarks.join("\n"));
+    let examples = parse_examples(document, selectors);
+    let beta = document.find(Class(selectors.beta_class.as_str())).next().is_some()
+        || name.to_lowercase().contains("(beta)");
+    let deprecation = document.find(Class(selectors.deprecated_class.as_str())).next().map(|n| n.text());
+    let deprecated = deprecation.is_some() || name.to_lowercase().contains("(deprecated)");
+    let replaced_by = deprecation.as_ref().and_then(|text| parse_replacement(text));
+    let required_packages = match document.find(Attr("id", selectors.packages_id.as_str())).next() {
+        Some(list) => split_packages(&list.text()),
+        None => parse_required_packages(&body_text),
+    };
+    let scopes = document.find(Attr("id", selectors.scope_id.as_str())).next()
+        .map(|n| split_scopes(&n.text()))
+        .unwrap_or_default();
+    let function_parameters = FunctionParameter::from_uri(&uri);
+    let singleton = is_singleton(&name, &uri);
+    let query_options = QueryOption::from_names(document
+        .find(Attr("name", selectors.query_options_name.as_str()))
+        .filter_map(|n| n.attr("value")), kind);
+    Ok(EndpointDetails {name, uri, description, doc_url: url, requires_division, kind, service: None, max_page_size,
+        function_parameters, singleton, method_remarks, beta, deprecated, replaced_by, required_packages, scopes, query_options, properties, failed_properties, methods,
+        unknown_methods, examples})
+}
+
+/// The first sample of every method that is valid JSON, samples abbreviated with `...` and the
+/// like are left out. Pages showing several samples of a method start with the simplest one.
+fn parse_examples(document: &Document, selectors: &Selectors) -> BTreeMap<String, Value> {
+    let mut examples = BTreeMap::new();
+    for node in document.find(Class(selectors.example_class.as_str())) {
+        let method = node.attr(selectors.example_method_attr.as_str()).unwrap_or("GET").trim().to_uppercase();
+        if let Ok(sample) = serde_json::from_str(node.text().trim()) {
+            examples.entry(method).or_insert(sample);
+        }
+    }
+    examples
+}
+
+#[test]
+fn it_parses_examples() {
+    let document = Document::from(r#"<body>
+        <pre class="example">{"d": {"results": [{"Code": "P1"}]}}</pre>
+        <pre class="example" data-method="post">{"Code": "P2"}</pre>
+        <pre class="example" data-method="PUT">{"Code": ...}</pre>
+        <pre class="example" data-method="POST">{"Code": "P3", "Description": "Second sample"}</pre>
+    </body>"#);
+    let examples = parse_examples(&document, &Selectors::default());
+    assert_eq!(examples.get("GET"), Some(&json!({ "d": { "results": [{ "Code": "P1" }] } })));
+    assert_eq!(examples.get("POST"), Some(&json!({ "Code": "P2" })));
+    assert_eq!(examples.get("PUT"), None);
+}
+
+/// Finds a documented page size in phrases like "returns 1000 records per page" or "page size of 1000"
+fn parse_page_size(text: &str) -> Option<u32> {
+    let words: Vec<String> = text.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .collect();
+    for (i, word) in words.iter().enumerate() {
+        if let Ok(size) = word.parse::<u32>() {
+            let followed_by_records = words.get(i + 1).map_or(false, |w| w.starts_with("record"));
+            let page_follows = words.iter().skip(i + 2).take(3).any(|w| w == "page");
+            let size_of_precedes = i >= 3 && words[i - 3] == "page" && words[i - 2] == "size" && words[i - 1] == "of";
+            if (followed_by_records && page_follows) || size_of_precedes {
+                return Some(size);
+            }
+        }
+    }
+    None
+}
+
+#[test]
+fn it_parses_page_sizes() {
+    assert_eq!(parse_page_size("This endpoint returns 1000 records per page."), Some(1000));
+    assert_eq!(parse_page_size("It uses a page size of 60."), Some(60));
+    assert_eq!(parse_page_size("Returns 60 records. Nothing about pages"), None);
+    assert_eq!(parse_page_size("No numbers here"), None);
+}
+
+/// Splits a listing like "Accounting, Manufacturing" into package names
+fn split_packages(list: &str) -> Vec<String> {
+    list.split(|c| c == ',' || c == ';' || c == '\n')
+        .flat_map(|p| p.split(" or "))
+        .map(|p| p.trim().trim_right_matches('.').trim())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_owned())
+        .collect()
+}
+
+/// Finds the endpoint to use instead in notices like "Deprecated, use SalesInvoices instead"
+/// or "This endpoint is replaced by CRMAccounts."
+fn parse_replacement(notice: &str) -> Option<String> {
+    let words: Vec<&str> = notice.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '/'))
+        .collect();
+    for (i, word) in words.iter().enumerate() {
+        let lower = word.to_lowercase();
+        let follows = lower == "use" || (lower == "by" && i > 0 && words[i - 1].to_lowercase() == "replaced");
+        if !follows {
+            continue;
+        }
+        let mut candidates = words[i + 1..].iter().take(2);
+        let candidate = match candidates.next() {
+            Some(c) if c.to_lowercase() == "the" => candidates.next(),
+            other => other,
+        };
+        match candidate {
+            Some(c) if is_endpoint_name(c) => return Some(c.to_string()),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether the word looks like the name of an endpoint, like `SalesInvoices`, rather than a
+/// word of the sentence, like the `this` of "do not use this endpoint"
+fn is_endpoint_name(word: &str) -> bool {
+    word.chars().next().map_or(false, |c| c.is_ascii_uppercase()) && word.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[test]
+fn it_parses_replacements() {
+    assert_eq!(parse_replacement("Deprecated, use SalesInvoices instead."), Some("SalesInvoices".to_owned()));
+    assert_eq!(parse_replacement("This endpoint is replaced by CRMAccounts."), Some("CRMAccounts".to_owned()));
+    assert_eq!(parse_replacement("Please use the SalesOrders endpoint."), Some("SalesOrders".to_owned()));
+    assert_eq!(parse_replacement("This endpoint is deprecated."), None);
+    assert_eq!(parse_replacement("Do not use this endpoint."), None);
+    assert_eq!(parse_replacement("Deprecated, use with care."), None);
+}
+
+/// Splits a listing like "crm.accounts.read, crm.accounts.write" into sorted scope names
+fn split_scopes(list: &str) -> Vec<String> {
+    let scopes: BTreeSet<&str> = list.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    scopes.into_iter().map(|s| s.to_owned()).collect()
+}
+
+/// Finds package requirements in phrases like "This endpoint requires the Manufacturing package."
+/// or "Only available with the following packages: Accounting, Wholesale."
+fn parse_required_packages(text: &str) -> Vec<String> {
+    if let Some(i) = find_ignore_ascii_case(text, "following packages:") {
+        let rest = &text[i + "following packages:".len()..];
+        let list = rest.split(|c| c == '.' || c == '\n').next().unwrap_or("");
+        return split_packages(list);
+    }
+    if let Some(i) = find_ignore_ascii_case(text, "requires the ") {
+        let rest = &text[i + "requires the ".len()..];
+        if let Some(end) = find_ignore_ascii_case(rest, " package") {
+            if !rest[..end].contains('.') {
+                return split_packages(&rest[..end]);
+            }
+        }
+    }
+    Vec::new()
+}
+
+#[test]
+fn it_splits_scopes() {
+    assert_eq!(split_scopes("crm.accounts.read, crm.accounts.write\n"),
+        vec!["crm.accounts.read".to_owned(), "crm.accounts.write".to_owned()]);
+    assert_eq!(split_scopes("crm.accounts.write, crm.accounts.read, crm.accounts.write"),
+        vec!["crm.accounts.read".to_owned(), "crm.accounts.write".to_owned()]);
+    assert!(split_scopes("  ").is_empty());
+}
+
+#[test]
+fn it_parses_required_packages() {
+    assert_eq!(parse_required_packages("This endpoint requires the Manufacturing package."),
+        vec!["Manufacturing".to_owned()]);
+    assert_eq!(parse_required_packages("Only available with the following packages: Accounting, Wholesale.\nMore"),
+        vec!["Accounting".to_owned(), "Wholesale".to_owned()]);
+    assert_eq!(parse_required_packages("Requires the Project or Professional Services package"),
+        vec!["Project".to_owned(), "Professional Services".to_owned()]);
+    assert!(parse_required_packages("This endpoint requires the division. Pick a package.").is_empty());
+    assert!(parse_required_packages("Returns 60 records per page").is_empty());
+    assert_eq!(parse_required_packages("İİ Alleen met de following packages: Groothandel, Productie."),
+        vec!["Groothandel".to_owned(), "Productie".to_owned()]);
+    assert_eq!(parse_required_packages("Straße İ requires the Größe package"), vec!["Größe".to_owned()]);
+}
+
+impl EndpointDetails {
+    /// The properties that together identify an entity, in documentation order
+    pub fn keys(&self) -> Vec<&Property> {
+        self.properties.iter().filter(|p| p.key).collect()
+    }
+
+    pub fn has_composite_key(&self) -> bool {
+        self.keys().len() > 1
+    }
+
+    /// The properties referring to other entities, which `$expand` can include inline
+    pub fn navigation_properties(&self) -> Vec<&Property> {
+        self.properties.iter().filter(|p| p.navigation).collect()
+    }
+
+    /// The property holding file contents, for endpoints exchanging files like `DocumentAttachments`.
+    /// Entities of other endpoints may have binary columns too, those aren't files.
+    pub fn file_property(&self) -> Option<&Property> {
+        if !ATTACHMENT_ENDPOINTS.contains(&self.name.as_str()) {
+            return None;
+        }
+        self.binary_property()
+    }
+
+    /// The first binary property besides the keys
+    pub fn binary_property(&self) -> Option<&Property> {
+        self.properties.iter().find(|p| !p.key && p.edm_type == EdmType::Binary)
+    }
+}
+
+/// Endpoints known to exchange the contents of files
+const ATTACHMENT_ENDPOINTS: &'static [&'static str] = &["DocumentsDocumentAttachments", "MailboxMailMessageAttachments"];
+
+/// Endpoints known to return a single object that the heuristic below misses
+const SINGLETON_ENDPOINTS: &'static [&'static str] = &["SystemSystemMe"];
+
+/// Whether the endpoint returns one object instead of a `d.results` collection.
+/// Resources under `/current/` describe the calling user and are singletons.
+fn is_singleton(name: &str, uri: &str) -> bool {
+    SINGLETON_ENDPOINTS.contains(&name) || uri.split('/').any(|segment| segment.trim() == "current")
+}
+
+#[test]
+fn it_detects_singletons() {
+    assert!(is_singleton("SystemSystemMe", "/api/v1/current/Me"));
+    assert!(is_singleton("Whatever", "/api/v1/current/Me"));
+    assert!(!is_singleton("ProjectProjects", "/api/v1/{division}/project/Projects"));
+}
+
+/// Assigns each sentence of the remarks to the methods it mentions
+fn parse_method_remarks(remarks: &str) -> BTreeMap<String, String> {
+    let mut by_method: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for sentence in remarks.split(|c| c == '.' || c == '\n').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let words: Vec<&str> = sentence.split(|c: char| !c.is_alphanumeric()).collect();
+        for method in &["GET", "POST", "PUT", "DELETE"] {
+            if words.contains(method) {
+                by_method.entry(method.to_string()).or_insert_with(Vec::new).push(format!("{}.", sentence));
+            }
+        }
+    }
+    by_method.into_iter().map(|(method, sentences)| (method, sentences.join(" "))).collect()
+}
+
+#[test]
+fn it_assigns_remarks_to_methods() {
+    let remarks = parse_method_remarks("POST only allowed for draft invoices. \
+        PUT and DELETE are not possible once processed.\nPrices include VAT.");
+    assert_eq!(remarks.get("POST"), Some(&"POST only allowed for draft invoices.".to_owned()));
+    assert_eq!(remarks.get("DELETE"), Some(&"PUT and DELETE are not possible once processed.".to_owned()));
+    assert_eq!(remarks.get("GET"), None);
+}
+
+/// Canonicalizes a scraped service uri: no whitespace, no scheme or host,
+/// a single leading slash and no trailing or duplicate slashes
+pub fn normalize_uri(uri: &str) -> String {
+    let uri: String = uri.split_whitespace().collect();
+    let path = match uri.find("://") {
+        Some(scheme_end) => match uri[scheme_end + 3..].find('/') {
+            Some(host_end) => uri[scheme_end + 3 + host_end..].to_owned(),
+            None => "/".to_owned(),
+        },
+        None => uri,
+    };
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    format!("/{}", segments.join("/"))
+}
+
+/// Whether the uri follows the `/api/v1/{division}/...` pattern, or one of the division-less
+/// variants like `/api/v1/current/Me`, with a resource after the prefix
+pub fn is_expected_uri(uri: &str) -> bool {
+    let segments: Vec<&str> = uri.split('/').skip(1).collect();
+    segments.len() >= 4 && segments[0] == "api" && segments[1] == "v1"
+        && !segments[2].is_empty() && segments.iter().all(|s| !s.is_empty())
+}
+
+#[test]
+fn it_normalizes_uris() {
+    assert_eq!(normalize_uri(" /api/v1/{division}/project/Projects \n"), "/api/v1/{division}/project/Projects");
+    assert_eq!(normalize_uri("https://start.exactonline.nl/api/v1/{division}/crm/Accounts/"), "/api/v1/{division}/crm/Accounts");
+    assert_eq!(normalize_uri("api/v1//current/Me"), "/api/v1/current/Me");
+    assert!(is_expected_uri("/api/v1/{division}/project/Projects"));
+    assert!(is_expected_uri("/api/v1/current/Me"));
+    assert!(!is_expected_uri("/docs/v1/current/Me"));
+    assert!(!is_expected_uri("/api/v1/Me"));
+}
+
+/// Whether the given service uri is scoped to a division,
+/// e.g. `/api/v1/{division}/project/Projects` but not `/api/v1/current/Me`.
+fn requires_division(uri: &str) -> bool {
+    uri.split('/').any(|segment| segment.trim() == "{division}")
+}
+
+#[test]
+fn it_detects_division_segments() {
+    assert!(requires_division("/api/v1/{division}/project/Projects"));
+    assert!(requires_division("/api/v1/{division}/system/Divisions"));
+    assert!(!requires_division("/api/v1/current/Me"));
+    assert!(!requires_division("/api/v1/system/Users"));
 }
 
 #[test]
@@ -204,6 +1247,7 @@ fn it_fetches_endpoints_details() {
                 assert!(!details.name.is_empty(), "Could not find name of endpoint with details {:?}", details);
                 assert!(!details.uri.is_empty(), "Could not find uri of endpoint with details {:?}", details);
                 assert!(!details.methods.is_empty(), "Could not find methods of endpoint with details: {:?}", details);
+                assert_eq!(details.doc_url, url);
 
                 for err in details.failed_properties {
                     failed_properties.push((details.name.clone(), err));
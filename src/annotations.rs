@@ -0,0 +1,77 @@
+use errors::*;
+use errors::ErrorKind::AnnotationTargetMissing;
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Extra members for objects in the serialized spec, for everything the `openapi`
+/// types have no field for (vendor extensions, `readOnly`, ...).
+/// Objects are addressed by their JSON pointer, see `pointer`.
+#[derive(Clone, Debug, Default)]
+pub struct Annotations {
+    members: BTreeMap<String, Map<String, Value>>,
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations::default()
+    }
+
+    /// Adds `key: value` to the object at `pointer`, replacing an earlier annotation with the same key
+    pub fn insert<K: Into<String>>(&mut self, pointer: String, key: K, value: Value) {
+        self.members.entry(pointer).or_insert_with(Map::new).insert(key.into(), value);
+    }
+
+    pub fn get(&self, pointer: &str, key: &str) -> Option<&Value> {
+        self.members.get(pointer).and_then(|m| m.get(key))
+    }
+
+    /// Drops the annotations of objects missing from the serialized spec
+    pub fn retain_targets(&mut self, document: &Value) {
+        let missing: Vec<String> = self.members.keys()
+            .filter(|pointer| document.pointer(pointer).map_or(true, |target| !target.is_object()))
+            .cloned()
+            .collect();
+        for pointer in missing {
+            self.members.remove(&pointer);
+        }
+    }
+
+    /// Moves the annotations of each object to the pointer `target` gives, dropping them when it
+    /// gives `None`
+    pub fn retarget<F: Fn(&str) -> Option<String>>(&mut self, target: F) {
+        let members = ::std::mem::replace(&mut self.members, BTreeMap::new());
+        for (pointer, members) in members {
+            if let Some(pointer) = target(&pointer) {
+                self.members.entry(pointer).or_insert_with(Map::new).extend(members);
+            }
+        }
+    }
+
+    /// Merges all annotations into the serialized spec
+    pub fn apply(&self, document: &mut Value) -> Result<()> {
+        for (pointer, members) in &self.members {
+            let target = document.pointer_mut(pointer)
+                .and_then(|v| v.as_object_mut())
+                .ok_or(AnnotationTargetMissing(pointer.clone()))?;
+            for (key, value) in members {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a JSON pointer (RFC 6901) from unescaped segments
+pub fn pointer<S: AsRef<str>>(segments: &[S]) -> String {
+    segments.iter()
+        .map(|s| format!("/{}", s.as_ref().replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+#[test]
+fn it_escapes_pointers() {
+    assert_eq!(pointer(&["paths", "/api/v1/{division}/crm/Accounts", "get"]),
+        "/paths/~1api~1v1~1{division}~1crm~1Accounts/get");
+    assert_eq!(pointer::<&str>(&[]), "");
+}
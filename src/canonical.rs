@@ -0,0 +1,144 @@
+//! The canonical form generated specs are written in, so they can be committed and diffed
+
+use std::mem;
+
+use serde_json::{Map, Value};
+
+/// Order of parameters by their `in`, the ones the path needs first
+const LOCATIONS: &'static [&'static str] = &["path", "query", "header", "formData", "cookie", "body"];
+
+/// Puts a Swagger 2.0 or OpenAPI 3.0 document in canonical form. Generated specs are always in
+/// this form, and the same endpoints and options give the same document byte for byte:
+///
+/// - members of objects are ordered by key, whatever the features of `serde_json`
+/// - parameters of operations and path items are ordered by location (path, query, header, form
+///   data, body), then by name, references by the parameter they refer to
+/// - `required` lists of schemas are sorted, without duplicates
+/// - the tags of the document are ordered by name
+///
+/// Examples, defaults, enums and the other values the document describes keep their order.
+///
+/// The form is part of the API of this crate, so changing it is a breaking change.
+pub fn canonicalize(document: &mut Value) {
+    let shared = shared_parameters(document);
+    let value = mem::replace(document, Value::Null);
+    *document = canonical_value(value, &mut Vec::new(), &shared);
+}
+
+/// The reusable parameters by reference, to order references like what they refer to
+fn shared_parameters(document: &Value) -> Map<String, Value> {
+    let mut shared = Map::new();
+    for &(pointer, prefix) in &[("/parameters", "#/parameters/"), ("/components/parameters", "#/components/parameters/")] {
+        if let Some(parameters) = document.pointer(pointer).and_then(|p| p.as_object()) {
+            for (name, parameter) in parameters {
+                shared.insert(format!("{}{}", prefix, name), parameter.clone());
+            }
+        }
+    }
+    shared
+}
+
+/// The value in canonical form, `path` holding the keys leading to it from the root
+fn canonical_value(value: Value, path: &mut Vec<String>, shared: &Map<String, Value>) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut members: Vec<(String, Value)> = object.into_iter().collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(members.into_iter().map(|(key, value)| {
+                path.push(key);
+                let value = canonical_value(value, path, shared);
+                let key = path.pop().expect("Key pushed");
+                (key, value)
+            }).collect())
+        },
+        Value::Array(items) => {
+            let mut items: Vec<Value> = items.into_iter().map(|item| canonical_value(item, path, shared)).collect();
+            if is_required_list(path) && items.iter().all(|i| i.is_string()) {
+                items.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+                items.dedup();
+            } else if is_parameter_list(path) && items.iter().all(|i| i.is_object()) {
+                items.sort_by_key(|p| parameter_order(p, shared));
+            } else if path.len() == 1 && path[0] == "tags" && items.iter().all(|i| i.is_object()) {
+                items.sort_by_key(|t| t.get("name").and_then(|n| n.as_str()).map(|n| n.to_owned()));
+            }
+            Value::Array(items)
+        },
+        other => other,
+    }
+}
+
+/// Members holding values rather than schemas, whose arrays keep their order
+const VALUES: &'static [&'static str] = &["example", "examples", "x-example", "default", "enum"];
+
+/// Whether the path leads to the `required` list of a schema, rather than to a property named
+/// `required` or to a value the document describes
+fn is_required_list(path: &[String]) -> bool {
+    match path.split_last() {
+        Some((last, parents)) if last == "required" => {
+            parents.last().map(|parent| parent.as_str()) != Some("properties")
+                && !parents.iter().any(|key| VALUES.contains(&key.as_str()))
+        },
+        _ => false,
+    }
+}
+
+/// Whether the path leads to the parameters of a path item or of an operation
+fn is_parameter_list(path: &[String]) -> bool {
+    match path.len() {
+        3 | 4 => path[0] == "paths" && path[path.len() - 1] == "parameters",
+        _ => false,
+    }
+}
+
+/// The position of the location of the parameter, and its name
+fn parameter_order(parameter: &Value, shared: &Map<String, Value>) -> (usize, String) {
+    let reference = parameter.get("$ref").and_then(|r| r.as_str());
+    let parameter = match reference {
+        Some(reference) => match shared.get(reference) {
+            Some(target) => target,
+            // Unresolvable references go last, in a fixed order nonetheless
+            None => return (LOCATIONS.len(), reference.to_owned()),
+        },
+        None => parameter,
+    };
+    let location = parameter.get("in").and_then(|l| l.as_str()).unwrap_or("");
+    let rank = LOCATIONS.iter().position(|l| *l == location).unwrap_or(LOCATIONS.len());
+    let name = parameter.get("name").and_then(|n| n.as_str()).unwrap_or("");
+    (rank, name.to_owned())
+}
+
+#[test]
+fn it_canonicalizes_specs() {
+    let mut spec = json!({
+        "tags": [{ "name": "Project" }, { "name": "CRM" }],
+        "parameters": { "filter": { "name": "$filter", "in": "query" } },
+        "paths": { "/a/{id}": { "put": {
+            "tags": ["Project"],
+            "parameters": [
+                { "name": "body", "in": "body", "schema": { "required": ["Code", "Amount", "Code"] } },
+                { "$ref": "#/parameters/filter" },
+                { "name": "Prefer", "in": "header" },
+                { "name": "id", "in": "path" },
+                { "name": "$expand", "in": "query" }
+            ],
+            "responses": { "200": { "schema": {
+                "properties": { "required": { "type": "string" }, "tags": { "type": "array", "enum": [["b", "a"]] } },
+                "example": { "required": ["Code", "Amount"], "parameters": [{ "name": "b" }, { "name": "a" }] }
+            } } }
+        } } }
+    });
+    canonicalize(&mut spec);
+    assert_eq!(spec["tags"], json!([{ "name": "CRM" }, { "name": "Project" }]));
+    assert_eq!(spec["paths"]["/a/{id}"]["put"]["parameters"], json!([
+        { "name": "id", "in": "path" },
+        { "name": "$expand", "in": "query" },
+        { "$ref": "#/parameters/filter" },
+        { "name": "Prefer", "in": "header" },
+        { "name": "body", "in": "body", "schema": { "required": ["Amount", "Code"] } }
+    ]));
+    let schema = &spec["paths"]["/a/{id}"]["put"]["responses"]["200"]["schema"];
+    assert_eq!(schema["example"], json!({ "parameters": [{ "name": "b" }, { "name": "a" }], "required": ["Code", "Amount"] }));
+    assert_eq!(schema["properties"]["tags"]["enum"], json!([["b", "a"]]));
+    let serialized = ::serde_json::to_string(&spec).expect("Serialized spec");
+    assert!(serialized.starts_with(r#"{"parameters":"#));
+}
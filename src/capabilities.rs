@@ -0,0 +1,109 @@
+use acquisition::{EndpointDetails, EndpointKind, QueryOption, SYNC_TIMESTAMP};
+use options::SpecOptions;
+
+use reqwest::Method;
+
+/// Compact summary of what an endpoint supports, for tools that don't want to parse the spec
+#[derive(Clone, Debug, Serialize)]
+pub struct EndpointCapabilities {
+    pub name: String,
+    pub uri: String,
+    pub service: Option<String>,
+    pub methods: Vec<String>,
+    pub keys: Vec<KeyCapability>,
+    /// Properties that can be used in `$filter`
+    pub filterable: Vec<String>,
+    pub pagination: Pagination,
+    /// OAuth scopes required to call the endpoint
+    pub scopes: Vec<String>,
+    /// Exact Online packages of which the customer needs at least one, empty if any subscription will do
+    pub required_packages: Vec<String>,
+    /// Marked as beta by Exact
+    pub preview: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyCapability {
+    pub name: String,
+    pub edm_type: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum Pagination {
+    /// Follow the `__next` links, each page holds at most `page_size` records
+    NextLink { page_size: u32 },
+    /// Filter on the given property being greater than the last value received
+    Timestamp { property: String, page_size: u32 },
+    /// A single result, e.g. of a function
+    Unpaged,
+}
+
+impl Pagination {
+    fn for_endpoint(endpoint: &EndpointDetails, options: &SpecOptions) -> Pagination {
+        let page_size = options.max_page_size(endpoint);
+        match endpoint.kind {
+            EndpointKind::Standard | EndpointKind::Bulk => Pagination::NextLink { page_size: page_size },
+            EndpointKind::Sync => Pagination::Timestamp {
+                property: SYNC_TIMESTAMP.to_owned(),
+                page_size: page_size,
+            },
+            EndpointKind::Function => Pagination::Unpaged,
+        }
+    }
+}
+
+impl EndpointCapabilities {
+    pub fn new(endpoint: &EndpointDetails, options: &SpecOptions) -> EndpointCapabilities {
+        EndpointCapabilities {
+            name: endpoint.name.clone(),
+            uri: endpoint.uri.clone(),
+            service: endpoint.service.clone(),
+            methods: endpoint.methods.iter().map(|m| m.to_string()).collect(),
+            keys: endpoint.keys().into_iter()
+                .map(|p| KeyCapability { name: p.name.clone(), edm_type: p.edm_type.name() })
+                .collect(),
+            filterable: endpoint.properties.iter()
+                .filter(|p| p.methods.contains(&Method::Get))
+                .filter(|_| endpoint.query_options.contains(&QueryOption::Filter))
+                .map(|p| p.name.clone())
+                .collect(),
+            pagination: Pagination::for_endpoint(endpoint, options),
+            scopes: endpoint.scopes.clone(),
+            required_packages: endpoint.required_packages.clone(),
+            preview: endpoint.beta,
+        }
+    }
+}
+
+/// Summarizes the endpoints that would be included in a spec generated with `options`
+pub fn build_capabilities(endpoints: &[EndpointDetails], options: &SpecOptions) -> Vec<EndpointCapabilities> {
+    endpoints.iter()
+        .filter(|e| options.includes_endpoint(e))
+        .map(|e| EndpointCapabilities::new(e, options))
+        .collect()
+}
+
+#[test]
+fn it_summarizes_restricted_endpoints() {
+    let mut endpoint = ::transform::sample_endpoint();
+    endpoint.methods = vec![Method::Get];
+    endpoint.query_options = vec![QueryOption::Select, QueryOption::Top];
+    let mut options = SpecOptions::default();
+    options.page_size_overrides.insert(endpoint.name.clone(), 1000);
+    let capabilities = EndpointCapabilities::new(&endpoint, &options);
+    assert_eq!(capabilities.methods, vec!["GET"]);
+    assert_eq!(capabilities.keys.iter().map(|k| (&k.name[..], &k.edm_type[..])).collect::<Vec<_>>(), vec![("ID", "Edm.Guid")]);
+    // Without `$filter` nothing is filterable
+    assert!(capabilities.filterable.is_empty());
+    match capabilities.pagination {
+        Pagination::NextLink { page_size } => assert_eq!(page_size, 1000),
+        other => panic!("Expected next links, got {:?}", other),
+    }
+
+    // Properties that are only written can't be filtered on
+    endpoint.query_options.push(QueryOption::Filter);
+    endpoint.properties[1].methods = vec![Method::Post];
+    let capabilities = EndpointCapabilities::new(&endpoint, &options);
+    assert_eq!(capabilities.filterable, vec!["ID", "Created"]);
+}
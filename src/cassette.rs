@@ -0,0 +1,124 @@
+use errors::*;
+use errors::ErrorKind::CassetteMiss;
+
+use reqwest::Url;
+use serde_json;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Maps the recorded files to the urls they were fetched from
+const INDEX_FILE: &'static str = "index.json";
+
+/// Whether documentation pages are recorded to or replayed from a cassette
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CassetteMode {
+    /// Fetch pages live and store them in the cassette
+    Record,
+    /// Serve pages from the cassette, never touching the network
+    Replay,
+}
+
+/// A directory of recorded documentation pages, one file per url
+#[derive(Clone, Debug, Deserialize)]
+pub struct Cassette {
+    pub dir: PathBuf,
+    pub mode: CassetteMode,
+}
+
+impl Cassette {
+    pub fn new<P: Into<PathBuf>>(dir: P, mode: CassetteMode) -> Cassette {
+        Cassette { dir: dir.into(), mode: mode }
+    }
+
+    /// The file a page is recorded in
+    pub fn path(&self, url: &Url) -> PathBuf {
+        let name: String = url.as_str().chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+        self.dir.join(name + ".html")
+    }
+
+    pub fn load(&self, url: &Url) -> Result<String> {
+        let path = self.path(url);
+        if !path.exists() {
+            bail!(CassetteMiss(url.to_string()));
+        }
+        let mut body = String::new();
+        File::open(path)?.read_to_string(&mut body)?;
+        Ok(body)
+    }
+
+    pub fn store(&self, url: &Url, body: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(url);
+        File::create(&path)?.write_all(body.as_bytes())?;
+
+        let mut index = self.read_index()?;
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if index.insert(file_name, url.to_string()).is_none() {
+            serde_json::to_writer_pretty(File::create(self.dir.join(INDEX_FILE))?, &index)?;
+        }
+        Ok(())
+    }
+
+    fn read_index(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.dir.join(INDEX_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// All recorded pages
+    pub fn entries(&self) -> Result<Vec<CassetteEntry>> {
+        let mut entries = Vec::new();
+        for (file_name, url) in self.read_index()? {
+            let path = self.dir.join(file_name);
+            if !path.exists() {
+                continue;
+            }
+            entries.push(CassetteEntry {
+                url: Url::parse(&url)?,
+                recorded: fs::metadata(path)?.modified()?,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// The `count` pages that were recorded longest ago
+    pub fn stalest(&self, count: usize) -> Result<Vec<CassetteEntry>> {
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|e| e.recorded);
+        entries.truncate(count);
+        Ok(entries)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CassetteEntry {
+    pub url: Url,
+    pub recorded: SystemTime,
+}
+
+#[test]
+fn it_replays_recorded_pages() {
+    use std::env;
+
+    let dir = env::temp_dir().join(format!("exact-openapi-gen-cassette-test-{}", ::std::process::id()));
+    let cassette = Cassette::new(dir.clone(), CassetteMode::Replay);
+    let url = Url::parse("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=CRMAccounts").unwrap();
+    cassette.store(&url, "<html></html>").expect("Stored page");
+    assert_eq!(cassette.load(&url).expect("Replayed page"), "<html></html>");
+
+    let missing = Url::parse("https://start.exactonline.nl/docs/Missing.aspx").unwrap();
+    assert!(cassette.load(&missing).is_err());
+
+    let entries = cassette.stalest(10).expect("Listed pages");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].url, url);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,70 @@
+//! Markdown changelogs of the differences between two specs, for the release notes
+
+use diff::{SpecChange, SpecDiff};
+
+/// The change as a line of a changelog
+fn describe(change: &SpecChange) -> String {
+    match *change {
+        SpecChange::AddedPath { ref path } => format!("Added endpoint `{}`", path),
+        SpecChange::RemovedPath { ref path } => format!("Removed endpoint `{}`", path),
+        SpecChange::AddedOperation { ref path, ref method } =>
+            format!("Added `{}` to `{}`", method.to_uppercase(), path),
+        SpecChange::RemovedOperation { ref path, ref method } =>
+            format!("Removed `{}` from `{}`", method.to_uppercase(), path),
+        SpecChange::AddedProperty { ref entity, ref property } =>
+            format!("Added field `{}` to {}", property, entity),
+        SpecChange::RemovedProperty { ref entity, ref property } =>
+            format!("Removed field `{}` from {}", property, entity),
+        SpecChange::ChangedType { ref entity, ref property, ref old, ref new } =>
+            format!("Changed the type of field `{}` of {} from `{}` to `{}`", property, entity, old, new),
+        SpecChange::NewlyRequired { ref entity, ref property } =>
+            format!("Field `{}` of {} is now required when creating", property, entity),
+        SpecChange::NoLongerRequired { ref entity, ref property } =>
+            format!("Field `{}` of {} is no longer required when creating", property, entity),
+    }
+}
+
+/// Renders the differences as a Markdown section headed `title`, breaking changes first
+pub fn render_changelog(diff: &SpecDiff, title: &str) -> String {
+    let mut markdown = format!("# {}\n\n", title);
+    if diff.is_empty() {
+        markdown.push_str("No changes.\n");
+        return markdown;
+    }
+    let sections = [("Breaking changes", diff.breaking()), ("Other changes", diff.non_breaking())];
+    for &(heading, ref changes) in sections.iter().filter(|&&(_, ref changes)| !changes.is_empty()) {
+        markdown.push_str(&format!("## {}\n\n", heading));
+        for change in changes {
+            markdown.push_str(&format!("- {}\n", describe(change)));
+        }
+        markdown.push('\n');
+    }
+    markdown.pop();
+    markdown
+}
+
+#[test]
+fn it_renders_changelogs() {
+    let diff = SpecDiff { changes: vec![
+        SpecChange::RemovedPath { path: "/api/v1/{division}/project/Old".to_owned() },
+        SpecChange::AddedProperty { entity: "ManufacturingOperations".to_owned(), property: "Notes".to_owned() },
+        SpecChange::ChangedType {
+            entity: "ManufacturingOperations".to_owned(),
+            property: "Code".to_owned(),
+            old: "integer (int32)".to_owned(),
+            new: "string".to_owned(),
+        },
+    ] };
+    assert_eq!(render_changelog(&diff, "Release 291"), "# Release 291
+
+## Breaking changes
+
+- Removed endpoint `/api/v1/{division}/project/Old`
+- Changed the type of field `Code` of ManufacturingOperations from `integer (int32)` to `string`
+
+## Other changes
+
+- Added field `Notes` to ManufacturingOperations
+");
+    assert_eq!(render_changelog(&SpecDiff::default(), "Release 292"), "# Release 292\n\nNo changes.\n");
+}
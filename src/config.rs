@@ -0,0 +1,132 @@
+use options::SpecOptions;
+use lint::LintOptions;
+use acquisition::FetchOptions;
+use selectors::Selectors;
+use errors::*;
+
+use std::fs::File;
+use std::path::Path;
+use serde_json;
+
+/// Configuration for a generator run, read from a JSON file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Names of the endpoints to include in the spec
+    pub endpoints: Vec<String>,
+    /// File the spec is written to
+    pub output: String,
+    /// File the endpoint capabilities summary is written to, if any
+    pub capabilities_output: Option<String>,
+    /// File the JSON run report, listing the warnings of the run, is written to, if any
+    pub report_output: Option<String>,
+    /// File caching parsed endpoints between runs, so only changed pages are parsed again and
+    /// only outputs with changed endpoints are generated again
+    pub model_cache: Option<String>,
+    pub fetch: FetchOptions,
+    /// JSON file overriding the selectors used to scrape the documentation
+    pub selectors_file: Option<String>,
+    pub spec: SpecOptions,
+    /// Severities of the style rules checked on every spec
+    pub lint: LintOptions,
+    /// JSON or YAML files with hand-maintained corrections, laid over every generated spec in order
+    pub overlays: Vec<String>,
+    /// JSON Merge Patch (RFC 7386) files applied to every generated spec in order, after the overlays
+    pub merge_patches: Vec<String>,
+    /// Several specs to generate from a single crawl. When empty, a single spec is written
+    /// to `output` containing `endpoints`.
+    pub outputs: Vec<OutputConfig>,
+}
+
+/// A spec to generate, covering a selection of the endpoints
+#[derive(Clone, Debug, Deserialize)]
+pub struct OutputConfig {
+    /// File to write to, `.yaml`/`.yml` files are written as YAML, anything else as JSON
+    pub file: String,
+    /// Names of the endpoints to include
+    pub endpoints: Vec<String>,
+    /// Generation settings for this output, defaults to the top-level `spec`
+    pub spec: Option<SpecOptions>,
+    /// Write the paths of every tag to a file of their own next to `file`, which then only
+    /// refers to them
+    #[serde(default)]
+    pub split_by_tag: bool,
+}
+
+impl OutputConfig {
+    pub fn is_yaml(&self) -> bool {
+        self.file.ends_with(".yaml") || self.file.ends_with(".yml")
+    }
+
+    /// Extension of the files split off by tag, matching that of `file`
+    pub fn extension(&self) -> &'static str {
+        if self.is_yaml() { "yaml" } else { "json" }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            endpoints: vec![
+                "SystemSystemMe",
+                "ProjectProjects",
+                "ManufacturingOperations",
+                "ManufacturingShopOrderRoutingStepPlans",
+                "PayrollEmploymentContracts",
+                "ManufacturingTimeTransactions",
+            ].into_iter().map(String::from).collect(),
+            output: "api.json".to_owned(),
+            capabilities_output: None,
+            report_output: None,
+            model_cache: None,
+            fetch: FetchOptions::default(),
+            selectors_file: None,
+            spec: SpecOptions::default(),
+            lint: LintOptions::default(),
+            overlays: Vec::new(),
+            merge_patches: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// The specs to generate, including the one described by the top-level fields if no outputs are set
+    pub fn outputs(&self) -> Vec<OutputConfig> {
+        if self.outputs.is_empty() {
+            vec![OutputConfig {
+                file: self.output.clone(),
+                endpoints: self.endpoints.clone(),
+                spec: None,
+                split_by_tag: false,
+            }]
+        } else {
+            self.outputs.clone()
+        }
+    }
+
+    /// Names of all endpoints needed by any of the outputs
+    pub fn selected_endpoints(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.outputs().into_iter().flat_map(|o| o.endpoints).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn selectors(&self) -> Result<Selectors> {
+        match self.selectors_file {
+            Some(ref path) => Selectors::from_path(path),
+            None => Ok(Selectors::default()),
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Config> {
+        let file = File::open(path.as_ref())?;
+        let config: Config = serde_json::from_reader(file)
+            .chain_err(|| format!("While reading config {}", path.as_ref().display()))?;
+        for options in Some(&config.spec).into_iter().chain(config.outputs.iter().filter_map(|o| o.spec.as_ref())) {
+            options.definition_naming.check()?;
+        }
+        Ok(config)
+    }
+}
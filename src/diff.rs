@@ -0,0 +1,316 @@
+//! Differences between two generated specs, to track changes Exact makes to its API
+
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use validation::OPERATION_METHODS;
+
+/// Suffixes of the per-method definitions generated for an endpoint, longest first, with the
+/// view of the entity they hold: as returned, or as sent with POST or PUT
+const DEFINITION_SUFFIXES: &'static [(&'static str, &'static str)] = &[
+    ("SingleResponse", ""), ("PostResponse", ""), ("Response", ""), ("Post", "Post"), ("Put", "Put"),
+];
+
+/// Members of the OData envelope, which are not properties of the entity
+const ENVELOPE_MEMBERS: &'static [&'static str] = &["__next", "__count", "__metadata"];
+
+/// A single difference between two specs
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SpecChange {
+    AddedPath { path: String },
+    RemovedPath { path: String },
+    AddedOperation { path: String, method: String },
+    RemovedOperation { path: String, method: String },
+    AddedProperty { entity: String, property: String },
+    RemovedProperty { entity: String, property: String },
+    /// The type of the property, like `string (guid)` or `array of Line`, changed
+    ChangedType { entity: String, property: String, old: String, new: String },
+    /// The property must now be given when creating the entity
+    NewlyRequired { entity: String, property: String },
+    /// The property may now be left out when creating the entity
+    NoLongerRequired { entity: String, property: String },
+}
+
+impl SpecChange {
+    /// Whether clients written against the old spec can fail against the new one
+    pub fn is_breaking(&self) -> bool {
+        match *self {
+            SpecChange::RemovedPath { .. } |
+            SpecChange::RemovedOperation { .. } |
+            SpecChange::RemovedProperty { .. } |
+            SpecChange::ChangedType { .. } |
+            SpecChange::NewlyRequired { .. } => true,
+            SpecChange::AddedPath { .. } |
+            SpecChange::AddedOperation { .. } |
+            SpecChange::AddedProperty { .. } |
+            SpecChange::NoLongerRequired { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for SpecChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpecChange::AddedPath { ref path } => write!(f, "added path {}", path),
+            SpecChange::RemovedPath { ref path } => write!(f, "removed path {}", path),
+            SpecChange::AddedOperation { ref path, ref method } =>
+                write!(f, "added {} {}", method.to_uppercase(), path),
+            SpecChange::RemovedOperation { ref path, ref method } =>
+                write!(f, "removed {} {}", method.to_uppercase(), path),
+            SpecChange::AddedProperty { ref entity, ref property } =>
+                write!(f, "added property {} to {}", property, entity),
+            SpecChange::RemovedProperty { ref entity, ref property } =>
+                write!(f, "removed property {} from {}", property, entity),
+            SpecChange::ChangedType { ref entity, ref property, ref old, ref new } =>
+                write!(f, "changed type of {}>{} from {} to {}", entity, property, old, new),
+            SpecChange::NewlyRequired { ref entity, ref property } =>
+                write!(f, "{}>{} is now required on create", entity, property),
+            SpecChange::NoLongerRequired { ref entity, ref property } =>
+                write!(f, "{}>{} is no longer required on create", entity, property),
+        }
+    }
+}
+
+/// The differences between two specs, paths first and entities after
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SpecDiff {
+    pub changes: Vec<SpecChange>,
+}
+
+impl SpecDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn breaking(&self) -> Vec<&SpecChange> {
+        self.changes.iter().filter(|c| c.is_breaking()).collect()
+    }
+
+    pub fn non_breaking(&self) -> Vec<&SpecChange> {
+        self.changes.iter().filter(|c| !c.is_breaking()).collect()
+    }
+}
+
+/// Compares the paths, operations and entity properties of two Swagger 2.0 or OpenAPI 3.0 documents
+pub fn diff_specs(old: &Value, new: &Value) -> SpecDiff {
+    let mut changes = Vec::new();
+    let old_paths = operations(old);
+    let new_paths = operations(new);
+    for (path, old_methods) in &old_paths {
+        match new_paths.get(path) {
+            None => changes.push(SpecChange::RemovedPath { path: path.clone() }),
+            Some(new_methods) => {
+                for method in old_methods.difference(new_methods) {
+                    changes.push(SpecChange::RemovedOperation { path: path.clone(), method: method.clone() });
+                }
+                for method in new_methods.difference(old_methods) {
+                    changes.push(SpecChange::AddedOperation { path: path.clone(), method: method.clone() });
+                }
+            },
+        }
+    }
+    for path in new_paths.keys().filter(|p| !old_paths.contains_key(*p)) {
+        changes.push(SpecChange::AddedPath { path: path.clone() });
+    }
+
+    let old_entities = entities(old);
+    let new_entities = entities(new);
+    for (name, old_entity) in &old_entities {
+        // Added and removed endpoints already show as paths
+        let new_entity = match new_entities.get(name) {
+            Some(entity) => entity,
+            None => continue,
+        };
+        let entity = || name.clone();
+        for (property, old_type) in &old_entity.properties {
+            match new_entity.properties.get(property) {
+                None => changes.push(SpecChange::RemovedProperty { entity: entity(), property: property.clone() }),
+                Some(new_type) if new_type != old_type => changes.push(SpecChange::ChangedType {
+                    entity: entity(),
+                    property: property.clone(),
+                    old: old_type.clone(),
+                    new: new_type.clone(),
+                }),
+                Some(_) => {},
+            }
+        }
+        for property in new_entity.properties.keys().filter(|p| !old_entity.properties.contains_key(*p)) {
+            changes.push(SpecChange::AddedProperty { entity: entity(), property: property.clone() });
+        }
+        for property in new_entity.required.difference(&old_entity.required) {
+            changes.push(SpecChange::NewlyRequired { entity: entity(), property: property.clone() });
+        }
+        for property in old_entity.required.difference(&new_entity.required) {
+            if new_entity.properties.contains_key(property) {
+                changes.push(SpecChange::NoLongerRequired { entity: entity(), property: property.clone() });
+            }
+        }
+    }
+    SpecDiff { changes: changes }
+}
+
+/// The methods of every path
+fn operations(spec: &Value) -> BTreeMap<String, BTreeSet<String>> {
+    spec.get("paths").and_then(|p| p.as_object()).map(|paths| {
+        paths.iter().map(|(path, item)| {
+            let methods = OPERATION_METHODS.iter()
+                .filter(|m| item.get(**m).is_some())
+                .map(|m| m.to_string())
+                .collect();
+            (path.clone(), methods)
+        }).collect()
+    }).unwrap_or_default()
+}
+
+/// The properties of a view of an endpoint's entity, gathered from the definitions holding it
+#[derive(Debug, Default)]
+struct Entity {
+    /// Description of the type of every property
+    properties: BTreeMap<String, String>,
+    /// Properties required when creating the entity
+    required: BTreeSet<String>,
+}
+
+/// The entities of the spec by endpoint name, as returned, and by the names of the definitions
+/// sent with POST and PUT, so changes only affecting one method are found as well
+fn entities(spec: &Value) -> BTreeMap<String, Entity> {
+    let definitions = spec.pointer("/definitions").or_else(|| spec.pointer("/components/schemas"))
+        .and_then(|d| d.as_object()).cloned().unwrap_or_default();
+    let mut entities: BTreeMap<String, Entity> = BTreeMap::new();
+    for (name, definition) in &definitions {
+        let suffix = DEFINITION_SUFFIXES.iter().find(|&&(s, _)| name.ends_with(s) && name.len() > s.len());
+        let view = match suffix {
+            Some(&(suffix, view)) => format!("{}{}", &name[..name.len() - suffix.len()], view),
+            None => name.clone(),
+        };
+        // With composed definitions, the base definition named after the endpoint joins in as well
+        let entity = entities.entry(view).or_insert_with(Entity::default);
+        let (properties, required) = entity_schema(definition, &definitions, 0);
+        for (property, schema) in properties {
+            entity.properties.entry(property).or_insert_with(|| describe_type(&schema));
+        }
+        if suffix.map_or(false, |&(_, view)| view == "Post") {
+            entity.required.extend(required);
+        }
+    }
+    entities
+}
+
+/// The properties and required properties of the entity inside the envelope of `schema`
+fn entity_schema(schema: &Value, definitions: &Map<String, Value>, depth: usize) -> (Map<String, Value>, Vec<String>) {
+    // Guards against definitions referring to themselves
+    if depth > 8 {
+        return (Map::new(), Vec::new());
+    }
+    if let Some(target) = schema.get("$ref").and_then(|r| r.as_str()) {
+        let name = target.rsplit('/').next().unwrap_or_default();
+        return match definitions.get(name) {
+            Some(definition) => entity_schema(definition, definitions, depth + 1),
+            None => (Map::new(), Vec::new()),
+        };
+    }
+    if let Some(parts) = schema.get("allOf").and_then(|a| a.as_array()) {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for part in parts {
+            let (p, r) = entity_schema(part, definitions, depth + 1);
+            properties.extend(p);
+            required.extend(r);
+        }
+        return (properties, required);
+    }
+    if let Some(items) = schema.get("items") {
+        return entity_schema(items, definitions, depth + 1);
+    }
+    let properties = schema.get("properties").and_then(|p| p.as_object()).cloned().unwrap_or_default();
+    for envelope in &["d", "results"] {
+        if let Some(inner) = properties.get(*envelope) {
+            return entity_schema(inner, definitions, depth + 1);
+        }
+    }
+    let required = schema.get("required").and_then(|r| r.as_array()).map(|r| {
+        r.iter().filter_map(|p| p.as_str().map(|p| p.to_owned())).collect()
+    }).unwrap_or_default();
+    let properties = properties.into_iter().filter(|&(ref name, _)| !ENVELOPE_MEMBERS.contains(&name.as_str())).collect();
+    (properties, required)
+}
+
+/// A short description of the type of the schema, like `string (guid)` or `array of Line`
+fn describe_type(schema: &Value) -> String {
+    if let Some(target) = schema.get("$ref").and_then(|r| r.as_str()) {
+        return target.rsplit('/').next().unwrap_or_default().to_owned();
+    }
+    let schema_type = schema.get("type").and_then(|t| t.as_str()).unwrap_or("any");
+    match (schema_type, schema.get("format").and_then(|f| f.as_str())) {
+        ("array", _) => format!("array of {}", schema.get("items").map_or("any".to_owned(), describe_type)),
+        (schema_type, Some(format)) => format!("{} ({})", schema_type, format),
+        (schema_type, None) => schema_type.to_owned(),
+    }
+}
+
+#[test]
+fn it_diffs_specs() {
+    let old = json!({
+        "paths": {
+            "/project/Projects": { "get": {}, "post": {} },
+            "/project/Old": { "get": {} }
+        },
+        "definitions": {
+            "ProjectProjectsResponse": { "properties": { "d": { "properties": {
+                "results": { "type": "array", "items": { "properties": {
+                    "ID": { "type": "string", "format": "guid" },
+                    "Code": { "type": "string" },
+                    "Budget": { "type": "number", "format": "double" }
+                } } },
+                "__next": { "type": "string" }
+            } } } },
+            "ProjectProjectsPost": { "required": ["Code"], "properties": {
+                "Code": { "type": "string" },
+                "Description": { "type": "string" }
+            } },
+            "ProjectProjectsPut": { "properties": {
+                "Code": { "type": "string" },
+                "Description": { "type": "string" }
+            } }
+        }
+    });
+    let new = json!({
+        "paths": {
+            "/project/Projects": { "get": {}, "put": {} },
+            "/project/New": { "get": {} }
+        },
+        "definitions": {
+            "ProjectProjectsResponse": { "properties": { "d": { "properties": {
+                "results": { "type": "array", "items": { "properties": {
+                    "ID": { "type": "string", "format": "guid" },
+                    "Code": { "type": "string" },
+                    "Budget": { "type": "string" },
+                    "Manager": { "type": "string", "format": "guid" }
+                } } }
+            } } } },
+            "ProjectProjectsPost": { "required": ["Description"], "properties": {
+                "Code": { "type": "string" },
+                "Description": { "type": "string" }
+            } },
+            "ProjectProjectsPut": { "properties": {
+                "Description": { "type": "string" }
+            } }
+        }
+    });
+    let diff = diff_specs(&old, &new);
+    let changes: Vec<String> = diff.changes.iter().map(|c| c.to_string()).collect();
+    assert_eq!(changes, vec![
+        "removed path /project/Old",
+        "removed POST /project/Projects",
+        "added PUT /project/Projects",
+        "added path /project/New",
+        "changed type of ProjectProjects>Budget from number (double) to string",
+        "added property Manager to ProjectProjects",
+        "ProjectProjectsPost>Description is now required on create",
+        "ProjectProjectsPost>Code is no longer required on create",
+        "removed property Code from ProjectProjectsPut",
+    ]);
+    assert_eq!(diff.breaking().len(), 5);
+    assert!(diff_specs(&new, &new).is_empty());
+}
@@ -0,0 +1,230 @@
+use acquisition::Scraper;
+use cassette::{Cassette, CassetteMode};
+use config::Config;
+use errors::Error;
+use selectors::Selectors;
+
+use std::fmt;
+use std::fs::{self, File};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
+/// The outcome of a single diagnostic
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// What was found, and for problems, what to do about it
+    pub message: String,
+}
+
+impl Check {
+    fn new<S: Into<String>>(name: &'static str, status: CheckStatus, message: S) -> Check {
+        Check { name: name, status: status, message: message.into() }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.status {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warning",
+            CheckStatus::Failed => "FAILED",
+        };
+        write!(f, "[{}] {}: {}", status, self.name, self.message)
+    }
+}
+
+/// Loads the config at `config_path`, if there is one, and runs the diagnostics with it.
+/// Config and selectors files that can't be loaded are reported like the other problems.
+/// `cookies` override the session cookies of the config.
+pub fn diagnose(config_path: &Path, cookies: Option<String>) -> Vec<Check> {
+    let mut checks = Vec::new();
+    let (check, config) = check_config(config_path);
+    checks.push(check);
+    let mut config = match config {
+        Some(config) => config,
+        None => return checks,
+    };
+    if cookies.is_some() {
+        config.fetch.cookies = cookies;
+    }
+    let (check, selectors) = check_selectors(&config);
+    checks.push(check);
+    let selectors = match selectors {
+        Some(selectors) => selectors,
+        None => return checks,
+    };
+    match Scraper::new(config.fetch.clone(), selectors) {
+        Ok(scraper) => checks.extend(run_diagnostics(&scraper)),
+        Err(e) => checks.push(Check::new("http client", CheckStatus::Failed, format!(
+            "Could not set up the HTTP client ({}). Check the proxy and TLS settings.", describe(&e)))),
+    }
+    checks
+}
+
+/// Checks everything generation depends on: the docs site, the cassette directory and
+/// whether the selectors still match the documentation layout
+pub fn run_diagnostics(scraper: &Scraper) -> Vec<Check> {
+    let mut checks = Vec::new();
+    checks.push(check_cassette(scraper.options().cassette.as_ref()));
+
+    let links = match scraper.fetch_endpoint_index() {
+        Ok(links) => {
+            checks.push(Check::new("overview", CheckStatus::Ok, "Fetched the resource overview"));
+            links
+        },
+        Err(e) => {
+            checks.push(Check::new("overview", CheckStatus::Failed, format!(
+                "Could not fetch the resource overview ({}). Check network access, proxy and TLS settings, \
+                and whether the docs require session cookies.", e)));
+            return checks;
+        }
+    };
+    if links.is_empty() {
+        checks.push(Check::new("overview selectors", CheckStatus::Failed, format!(
+            "The overview contains no links starting with '{}'. The documentation layout may have changed, \
+            update `detail_link_prefix` in the selectors file.", scraper.selectors().detail_link_prefix)));
+        return checks;
+    }
+    checks.push(Check::new("overview selectors", CheckStatus::Ok, format!("Found {} endpoints", links.len())));
+
+    match scraper.fetch_endpoint(&links[0]) {
+        Ok(details) => {
+            if details.properties.is_empty() {
+                checks.push(Check::new("detail selectors", CheckStatus::Failed, format!(
+                    "No properties found for {}. Verify `reference_table_id` and `property_type_attr` \
+                    in the selectors file against {}.", details.name, details.doc_url)));
+            } else if !details.failed_properties.is_empty() {
+                checks.push(Check::new("detail selectors", CheckStatus::Warning, format!(
+                    "{} of the properties of {} could not be parsed, first error: {}",
+                    details.failed_properties.len(), details.name, details.failed_properties[0])));
+            } else if details.methods.is_empty() {
+                checks.push(Check::new("detail selectors", CheckStatus::Failed, format!(
+                    "No supported methods found for {}. Verify `supported_methods_name` in the selectors file.",
+                    details.name)));
+            } else {
+                checks.push(Check::new("detail selectors", CheckStatus::Ok, format!(
+                    "Parsed {} with {} properties", details.name, details.properties.len())));
+            }
+        },
+        Err(e) => checks.push(Check::new("detail selectors", CheckStatus::Failed, format!(
+            "Could not parse {} ({}). Verify `endpoint_id` and `service_uri_id` in the selectors file.",
+            links[0].url, e))),
+    }
+    checks
+}
+
+/// The error with its causes, as the config and selectors files are read with context
+fn describe(error: &Error) -> String {
+    let messages: Vec<String> = error.iter().map(|e| e.to_string()).collect();
+    messages.join(": ")
+}
+
+fn check_config(path: &Path) -> (Check, Option<Config>) {
+    if !path.exists() {
+        let check = Check::new("config", CheckStatus::Ok, format!("{} not found, using the defaults", path.display()));
+        return (check, Some(Config::default()));
+    }
+    match Config::from_path(path) {
+        Ok(config) => (Check::new("config", CheckStatus::Ok, format!("Loaded {}", path.display())), Some(config)),
+        Err(e) => (Check::new("config", CheckStatus::Failed, format!(
+            "Could not load the config ({}). Fix the file, or remove it to use the defaults.", describe(&e))), None),
+    }
+}
+
+fn check_selectors(config: &Config) -> (Check, Option<Selectors>) {
+    match config.selectors() {
+        Ok(selectors) => {
+            let message = match config.selectors_file {
+                Some(ref path) => format!("Loaded {}", path),
+                None => "Not configured, using the built-in selectors".to_owned(),
+            };
+            (Check::new("selectors", CheckStatus::Ok, message), Some(selectors))
+        },
+        Err(e) => (Check::new("selectors", CheckStatus::Failed, format!(
+            "Could not load the selectors file ({}). Fix it, or remove `selectors_file` from the config.",
+            describe(&e))), None),
+    }
+}
+
+fn check_cassette(cassette: Option<&Cassette>) -> Check {
+    let cassette = match cassette {
+        Some(cassette) => cassette,
+        None => return Check::new("cassette", CheckStatus::Ok, "Not configured, all pages are fetched live"),
+    };
+    match cassette.mode {
+        CassetteMode::Replay => match fs::read_dir(&cassette.dir) {
+            Ok(entries) => {
+                let count = entries.count();
+                if count == 0 {
+                    Check::new("cassette", CheckStatus::Failed, format!(
+                        "{} is empty, record it first", cassette.dir.display()))
+                } else {
+                    Check::new("cassette", CheckStatus::Ok, format!(
+                        "Replaying {} pages from {}", count, cassette.dir.display()))
+                }
+            },
+            Err(e) => Check::new("cassette", CheckStatus::Failed, format!(
+                "Cannot read {} ({}), record it first", cassette.dir.display(), e)),
+        },
+        CassetteMode::Record => {
+            let probe = cassette.dir.join(".doctor");
+            let writable = fs::create_dir_all(&cassette.dir)
+                .and_then(|_| File::create(&probe))
+                .and_then(|_| fs::remove_file(&probe));
+            match writable {
+                Ok(_) => Check::new("cassette", CheckStatus::Ok, format!("Recording to {}", cassette.dir.display())),
+                Err(e) => Check::new("cassette", CheckStatus::Failed, format!(
+                    "Cannot write to {} ({}), fix its permissions or choose another directory",
+                    cassette.dir.display(), e)),
+            }
+        }
+    }
+}
+
+#[test]
+fn it_checks_the_config() {
+    use std::env;
+    use std::io::Write;
+
+    let dir = env::temp_dir().join(format!("exact-openapi-gen-doctor-config-{}", ::std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (check, config) = check_config(&dir.join("missing.json"));
+    assert_eq!(check.status, CheckStatus::Ok);
+    assert!(config.is_some());
+
+    let path = dir.join("broken.json");
+    File::create(&path).unwrap().write_all(b"{ \"output\": ").unwrap();
+    let checks = diagnose(&path, None);
+    assert_eq!(checks.len(), 1);
+    assert_eq!(checks[0].status, CheckStatus::Failed);
+    assert!(checks[0].message.contains("broken.json"));
+
+    File::create(&path).unwrap().write_all(b"{ \"selectors_file\": \"missing-selectors.json\" }").unwrap();
+    let checks = diagnose(&path, None);
+    assert_eq!(checks.iter().map(|c| (c.name, c.status)).collect::<Vec<_>>(),
+        vec![("config", CheckStatus::Ok), ("selectors", CheckStatus::Failed)]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn it_checks_the_cassette() {
+    use std::env;
+
+    assert_eq!(check_cassette(None).status, CheckStatus::Ok);
+    let dir = env::temp_dir().join(format!("exact-openapi-gen-doctor-cassette-{}", ::std::process::id()));
+    assert_eq!(check_cassette(Some(&Cassette::new(dir.clone(), CassetteMode::Replay))).status, CheckStatus::Failed);
+    assert_eq!(check_cassette(Some(&Cassette::new(dir.clone(), CassetteMode::Record))).status, CheckStatus::Ok);
+    // Recording created the directory, but there is nothing to replay yet
+    let replay = check_cassette(Some(&Cassette::new(dir.clone(), CassetteMode::Replay)));
+    assert_eq!(replay.status, CheckStatus::Failed);
+    assert!(replay.message.contains("is empty"));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,91 @@
+//! Replacing the references of a generated document by what they refer to
+
+use report::collect_refs;
+
+use serde_json::{Map, Value};
+
+/// Where Swagger 2.0 and OpenAPI 3.0 keep the reusable parts a reference can point at
+const REUSABLE_POINTERS: &'static [&'static str] = &[
+    "/definitions", "/parameters", "/responses",
+    "/components/schemas", "/components/parameters", "/components/responses",
+];
+
+/// Replaces every local `$ref` by a copy of its target, for tools that can't resolve references.
+/// The reusable parts are dropped afterwards, unless a reference to them is left: references
+/// that resolve to nothing, and those a schema makes to itself, are kept as they are.
+pub fn inline_refs(document: &Value) -> Value {
+    let mut inlined = inline(document, document, &mut Vec::new());
+    let mut remaining = Vec::new();
+    collect_refs(&inlined, &mut remaining);
+    for pointer in REUSABLE_POINTERS {
+        let referred = remaining.iter().any(|r| r.starts_with(&format!("#{}/", pointer)));
+        if referred {
+            continue;
+        }
+        let (parent, name) = pointer.split_at(pointer.rfind('/').expect("Absolute pointer"));
+        if let Some(parent) = inlined.pointer_mut(parent).and_then(|p| p.as_object_mut()) {
+            parent.remove(&name[1..]);
+        }
+    }
+    inlined
+}
+
+/// Copy of `value` with its references inlined, `expanding` lists the targets being inlined
+fn inline(value: &Value, document: &Value, expanding: &mut Vec<String>) -> Value {
+    match *value {
+        Value::Object(ref members) => {
+            if let Some(target) = members.get("$ref").and_then(|r| r.as_str()) {
+                let resolved = if target.starts_with('#') && !expanding.iter().any(|t| t == target) {
+                    document.pointer(&target[1..])
+                } else {
+                    None
+                };
+                if let Some(resolved) = resolved {
+                    expanding.push(target.to_owned());
+                    let mut inlined = inline(resolved, document, expanding);
+                    expanding.pop();
+                    // Keep what was said next to the reference, like a description
+                    if let Value::Object(ref mut inlined) = inlined {
+                        for (key, member) in members.iter().filter(|&(key, _)| key != "$ref") {
+                            inlined.insert(key.clone(), inline(member, document, expanding));
+                        }
+                    }
+                    return inlined;
+                }
+            }
+            let members: Map<String, Value> = members.iter()
+                .map(|(key, member)| (key.clone(), inline(member, document, expanding)))
+                .collect();
+            Value::Object(members)
+        },
+        Value::Array(ref items) => Value::Array(items.iter().map(|item| inline(item, document, expanding)).collect()),
+        _ => value.clone(),
+    }
+}
+
+#[test]
+fn it_inlines_refs() {
+    let spec = json!({
+        "swagger": "2.0",
+        "paths": { "/a": {
+            "parameters": [{ "$ref": "#/parameters/Division" }],
+            "get": { "responses": { "200": { "schema": { "$ref": "#/definitions/AResponse" } } } }
+        } },
+        "definitions": {
+            "A": { "type": "object", "properties": { "Parent": { "$ref": "#/definitions/A" } } },
+            "AResponse": { "type": "array", "items": { "$ref": "#/definitions/A" } }
+        },
+        "parameters": { "Division": { "name": "division", "in": "path", "type": "integer" } },
+        "securityDefinitions": { "ApiKey": { "type": "apiKey" } }
+    });
+    let inlined = inline_refs(&spec);
+    assert_eq!(inlined["paths"]["/a"]["parameters"][0],
+        json!({ "name": "division", "in": "path", "type": "integer" }));
+    assert_eq!(inlined["paths"]["/a"]["get"]["responses"]["200"]["schema"], json!({
+        "type": "array",
+        "items": { "type": "object", "properties": { "Parent": { "$ref": "#/definitions/A" } } }
+    }));
+    assert!(inlined.get("parameters").is_none());
+    assert!(inlined.get("definitions").is_some(), "A still refers to itself");
+    assert!(inlined.get("securityDefinitions").is_some());
+}
@@ -0,0 +1,359 @@
+//! Validation of JSON documents against a JSON Schema of draft 4, the draft the official Swagger 2.0
+//! and OpenAPI 3.0 schemas are written in. `format` is taken as an annotation and not checked.
+
+use annotations::pointer;
+use errors::*;
+use validation::Violation;
+
+use regex::{self, Regex};
+use serde_json::{self, Map, Value};
+use std::collections::BTreeMap;
+
+/// A schema together with the schemas its remote `$ref`s point at
+pub struct JsonSchema {
+    /// The schemas by their `id` without fragment, the root schema by the empty string
+    documents: BTreeMap<String, Value>,
+    /// Every `pattern` and `patternProperties` key of the schemas, compiled
+    patterns: BTreeMap<String, Regex>,
+}
+
+impl JsonSchema {
+    /// Parses the `schema`, `remotes` are the schemas it refers to by their `id`
+    pub fn new(schema: &str, remotes: &[&str]) -> Result<JsonSchema> {
+        let mut documents = BTreeMap::new();
+        documents.insert(String::new(), serde_json::from_str(schema)?);
+        for remote in remotes {
+            let remote: Value = serde_json::from_str(remote)?;
+            let id = remote.get("id").and_then(|i| i.as_str()).unwrap_or_default().trim_right_matches('#').to_owned();
+            documents.insert(id, remote);
+        }
+        let mut patterns = BTreeMap::new();
+        for document in documents.values() {
+            collect_patterns(document, &mut patterns)?;
+        }
+        Ok(JsonSchema { documents: documents, patterns: patterns })
+    }
+
+    /// Everything about `instance` the schema doesn't allow, located by JSON pointers into `instance`
+    pub fn validate(&self, instance: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check("", &self.documents[""], instance, &mut Vec::new(), &mut violations);
+        violations
+    }
+
+    /// What `instance` breaks of `schema`, found in the document with id `base`
+    fn violations(&self, base: &str, schema: &Value, instance: &Value, location: &[String]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(base, schema, instance, &mut location.to_vec(), &mut violations);
+        violations
+    }
+
+    /// The document with the id and the schema a `$ref` points at, relative to the document `base`
+    fn resolve<'a>(&'a self, base: &'a str, reference: &'a str) -> Option<(&'a str, &'a Value)> {
+        let (id, fragment) = match reference.find('#') {
+            Some(i) => (&reference[..i], &reference[i + 1..]),
+            None => (reference, ""),
+        };
+        let id = if id.is_empty() { base } else { id };
+        self.documents.get(id).and_then(|document| document.pointer(fragment)).map(|schema| (id, schema))
+    }
+
+    fn check(&self, base: &str, schema: &Value, instance: &Value, location: &mut Vec<String>,
+            violations: &mut Vec<Violation>) {
+        // Draft 4 ignores the other members of a schema with a `$ref`
+        if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+            match self.resolve(base, reference) {
+                Some((base, schema)) => self.check(base, schema, instance, location, violations),
+                None => violate(violations, location, format!("is checked against missing schema '{}'", reference)),
+            }
+            return;
+        }
+
+        if let Some(types) = schema.get("type") {
+            let allowed: Vec<&str> = match *types {
+                Value::String(ref t) => vec![t.as_str()],
+                Value::Array(ref ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.iter().any(|t| has_type(instance, t)) {
+                violate(violations, location, format!("must be of type {}", allowed.join(" or ")));
+            }
+        }
+        if let Some(values) = schema.get("enum").and_then(|e| e.as_array()) {
+            if !values.contains(instance) {
+                violate(violations, location, format!("must be one of {}", Value::Array(values.clone())));
+            }
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(|a| a.as_array()) {
+            for part in all {
+                self.check(base, part, instance, location, violations);
+            }
+        }
+        for keyword in &["anyOf", "oneOf"] {
+            if let Some(parts) = schema.get(*keyword).and_then(|p| p.as_array()) {
+                let mut results: Vec<Vec<Violation>> = parts.iter()
+                    .map(|part| self.violations(base, part, instance, location))
+                    .collect();
+                let matches = results.iter().filter(|r| r.is_empty()).count();
+                if matches == 0 {
+                    // What is wrong according to the schema the instance comes closest to, a value
+                    // outside an enum tells the schema is for other instances, like other parameters
+                    results.sort_by_key(|r| (r.iter().filter(|v| v.message.starts_with("must be one of")).count(), r.len()));
+                    violations.extend(results.into_iter().next().unwrap_or_default());
+                } else if matches > 1 && *keyword == "oneOf" {
+                    violate(violations, location, "matches more than one of the schemas in oneOf".to_owned());
+                }
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            if self.violations(base, not, instance, location).is_empty() {
+                violate(violations, location, "matches the schema in not".to_owned());
+            }
+        }
+
+        match *instance {
+            Value::Object(ref object) => self.check_object(base, schema, object, location, violations),
+            Value::Array(ref items) => self.check_array(base, schema, items, location, violations),
+            Value::Number(_) => check_number(schema, instance.as_f64().unwrap_or_default(), location, violations),
+            Value::String(ref string) => self.check_string(schema, string, location, violations),
+            _ => {},
+        }
+    }
+
+    fn check_object(&self, base: &str, schema: &Value, object: &Map<String, Value>, location: &mut Vec<String>,
+            violations: &mut Vec<Violation>) {
+        for name in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten().filter_map(|n| n.as_str()) {
+            if !object.contains_key(name) {
+                violate(violations, location, format!("is missing required member '{}'", name));
+            }
+        }
+        if let Some(min) = schema.get("minProperties").and_then(|m| m.as_u64()) {
+            if (object.len() as u64) < min {
+                violate(violations, location, format!("must have at least {} members", min));
+            }
+        }
+        if let Some(max) = schema.get("maxProperties").and_then(|m| m.as_u64()) {
+            if object.len() as u64 > max {
+                violate(violations, location, format!("must have at most {} members", max));
+            }
+        }
+        if let Some(dependencies) = schema.get("dependencies").and_then(|d| d.as_object()) {
+            for (name, dependency) in dependencies.iter().filter(|&(name, _)| object.contains_key(name)) {
+                match *dependency {
+                    Value::Array(ref names) => {
+                        for missing in names.iter().filter_map(|n| n.as_str()).filter(|n| !object.contains_key(*n)) {
+                            let message = format!("is missing member '{}', which '{}' depends on", missing, name);
+                            violate(violations, location, message);
+                        }
+                    },
+                    _ => self.check(base, dependency, &Value::Object(object.clone()), location, violations),
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        let pattern_properties = schema.get("patternProperties").and_then(|p| p.as_object());
+        let additional = schema.get("additionalProperties");
+        for (name, value) in object {
+            location.push(name.clone());
+            let mut matched = false;
+            if let Some(property) = properties.and_then(|p| p.get(name)) {
+                matched = true;
+                self.check(base, property, value, location, violations);
+            }
+            for (pattern, property) in pattern_properties.into_iter().flatten() {
+                if self.patterns[pattern].is_match(name) {
+                    matched = true;
+                    self.check(base, property, value, location, violations);
+                }
+            }
+            match additional {
+                Some(&Value::Bool(false)) if !matched =>
+                    violate(violations, location, "is not allowed".to_owned()),
+                Some(additional @ &Value::Object(_)) if !matched => self.check(base, additional, value, location, violations),
+                _ => {},
+            }
+            location.pop();
+        }
+    }
+
+    fn check_array(&self, base: &str, schema: &Value, items: &[Value], location: &mut Vec<String>,
+            violations: &mut Vec<Violation>) {
+        if let Some(min) = schema.get("minItems").and_then(|m| m.as_u64()) {
+            if (items.len() as u64) < min {
+                violate(violations, location, format!("must have at least {} items", min));
+            }
+        }
+        if let Some(max) = schema.get("maxItems").and_then(|m| m.as_u64()) {
+            if items.len() as u64 > max {
+                violate(violations, location, format!("must have at most {} items", max));
+            }
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let repeated = items.iter().enumerate().any(|(i, item)| items[..i].contains(item));
+            if repeated {
+                violate(violations, location, "must not repeat items".to_owned());
+            }
+        }
+
+        for (i, item) in items.iter().enumerate() {
+            // A single schema applies to every item, a list of schemas to the items at the same index
+            let item_schema = match schema.get("items") {
+                Some(&Value::Array(ref tuple)) => match tuple.get(i) {
+                    Some(item_schema) => Some(item_schema),
+                    None => match schema.get("additionalItems") {
+                        Some(&Value::Bool(false)) => {
+                            let message = format!("must have at most {} items", tuple.len());
+                            violate(violations, location, message);
+                            break;
+                        },
+                        additional => additional.filter(|a| a.is_object()),
+                    },
+                },
+                items => items,
+            };
+            if let Some(item_schema) = item_schema {
+                location.push(i.to_string());
+                self.check(base, item_schema, item, location, violations);
+                location.pop();
+            }
+        }
+    }
+
+    fn check_string(&self, schema: &Value, string: &str, location: &[String], violations: &mut Vec<Violation>) {
+        let length = string.chars().count() as u64;
+        if let Some(min) = schema.get("minLength").and_then(|m| m.as_u64()) {
+            if length < min {
+                violate(violations, location, format!("must be at least {} characters long", min));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()) {
+            if length > max {
+                violate(violations, location, format!("must be at most {} characters long", max));
+            }
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(|p| p.as_str()) {
+            if !self.patterns[pattern].is_match(string) {
+                violate(violations, location, format!("must match '{}'", pattern));
+            }
+        }
+    }
+}
+
+fn check_number(schema: &Value, number: f64, location: &[String], violations: &mut Vec<Violation>) {
+    if let Some(minimum) = schema.get("minimum").and_then(|m| m.as_f64()) {
+        if schema.get("exclusiveMinimum") == Some(&Value::Bool(true)) {
+            if number <= minimum {
+                violate(violations, location, format!("must be greater than {}", minimum));
+            }
+        } else if number < minimum {
+            violate(violations, location, format!("must be at least {}", minimum));
+        }
+    }
+    if let Some(maximum) = schema.get("maximum").and_then(|m| m.as_f64()) {
+        if schema.get("exclusiveMaximum") == Some(&Value::Bool(true)) {
+            if number >= maximum {
+                violate(violations, location, format!("must be less than {}", maximum));
+            }
+        } else if number > maximum {
+            violate(violations, location, format!("must be at most {}", maximum));
+        }
+    }
+    if let Some(divisor) = schema.get("multipleOf").and_then(|m| m.as_f64()) {
+        if (number / divisor).fract() != 0.0 {
+            violate(violations, location, format!("must be a multiple of {}", divisor));
+        }
+    }
+}
+
+fn violate(violations: &mut Vec<Violation>, location: &[String], message: String) {
+    violations.push(Violation { location: pointer(location), message: message });
+}
+
+/// Whether `value` is of the JSON Schema primitive type, integers being numbers without fraction
+fn has_type(value: &Value, primitive: &str) -> bool {
+    match (primitive, value) {
+        ("null", &Value::Null) | ("boolean", &Value::Bool(_)) | ("number", &Value::Number(_)) |
+        ("string", &Value::String(_)) | ("array", &Value::Array(_)) | ("object", &Value::Object(_)) => true,
+        ("integer", &Value::Number(ref n)) => n.is_i64() || n.is_u64() || n.as_f64().map_or(false, |f| f.fract() == 0.0),
+        _ => false,
+    }
+}
+
+/// Compiles the patterns of `schema` and the schemas within it
+fn collect_patterns(schema: &Value, patterns: &mut BTreeMap<String, Regex>) -> Result<()> {
+    match *schema {
+        Value::Object(ref members) => {
+            let mut found: Vec<&str> = members.get("patternProperties").and_then(|p| p.as_object())
+                .into_iter().flat_map(|p| p.keys()).map(|k| k.as_str()).collect();
+            found.extend(members.get("pattern").and_then(|p| p.as_str()));
+            for pattern in found {
+                let regex = Regex::new(&rust_pattern(pattern))
+                    .chain_err(|| format!("While compiling schema pattern '{}'", pattern))?;
+                patterns.insert(pattern.to_owned(), regex);
+            }
+            for member in members.values() {
+                collect_patterns(member, patterns)?;
+            }
+        },
+        Value::Array(ref items) => {
+            for item in items {
+                collect_patterns(item, patterns)?;
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// The ECMA 262 `pattern` in the syntax of the regex crate, which only allows escaping punctuation
+/// that has a meaning in a pattern, while schemas escape others too, like `^\/`
+fn rust_pattern(pattern: &str) -> String {
+    let mut translated = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(escaped)) if escaped.is_ascii_punctuation() => {
+                translated.push_str(&regex::escape(&escaped.to_string()));
+                chars.next();
+            },
+            _ => translated.push(c),
+        }
+    }
+    translated
+}
+
+#[test]
+fn it_validates_against_json_schemas() {
+    let schema = r##"{
+        "type": "object",
+        "required": ["name"],
+        "additionalProperties": false,
+        "patternProperties": { "^x-": {} },
+        "properties": {
+            "name": { "type": "string", "pattern": "^\\/" },
+            "tags": { "type": "array", "items": { "$ref": "#/definitions/tag" }, "uniqueItems": true },
+            "size": { "type": "integer", "minimum": 0 },
+            "kind": { "oneOf": [{ "enum": ["a", "b"] }, { "enum": ["b", "c"] }] }
+        },
+        "definitions": { "tag": { "type": "string", "minLength": 1 } }
+    }"##;
+    let schema = JsonSchema::new(schema, &[]).expect("Schema");
+    let instance = json!({ "name": "/a", "tags": ["x"], "size": 3.0, "kind": "a", "x-extra": true });
+    assert_eq!(schema.validate(&instance), Vec::new());
+
+    let instance = json!({ "tags": ["", ""], "size": -1.5, "kind": "b", "extra": true });
+    let messages: Vec<String> = schema.validate(&instance).iter().map(|v| v.to_string()).collect();
+    assert_eq!(messages, vec![
+        ": is missing required member 'name'",
+        "/extra: is not allowed",
+        "/kind: matches more than one of the schemas in oneOf",
+        "/size: must be of type integer",
+        "/size: must be at least 0",
+        "/tags: must not repeat items",
+        "/tags/0: must be at least 1 characters long",
+        "/tags/1: must be at least 1 characters long",
+    ]);
+    assert_eq!(schema.validate(&json!({ "name": "a" }))[0].to_string(), "/name: must match '^\\/'");
+}
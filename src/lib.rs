@@ -10,6 +10,7 @@ mod errors {
             Io(::std::io::Error);
             Reqwest(::reqwest::Error);
             Url(::reqwest::UrlError);
+            Json(::serde_json::Error);
         }
 
         errors {
@@ -17,6 +18,34 @@ mod errors {
                 description("could not parse spec")
                 display("could not parse spec: '{}'", t)
             }
+            LoginRequired(url : String) {
+                description("documentation page requires logging in")
+                display("documentation page requires logging in, supply session cookies: '{}'", url)
+            }
+            CassetteMiss(url : String) {
+                description("page not recorded in the cassette")
+                display("page not recorded in the cassette: '{}'", url)
+            }
+            FailureBudgetExceeded(failures : u32, attempts : u32) {
+                description("too many documentation pages failed, giving up")
+                display("giving up after {} of {} documentation pages failed", failures, attempts)
+            }
+            AnnotationTargetMissing(pointer : String) {
+                description("annotated object not found in the spec")
+                display("annotated object not found in the spec: '{}'", pointer)
+            }
+            DanglingRef(reference : String, locations : Vec<String>) {
+                description("reference to something missing from the spec")
+                display("'{}' is missing from the spec, referred to at {}", reference, locations.join(", "))
+            }
+            InvalidOptions(reason : String) {
+                description("spec options can't be applied")
+                display("spec options can't be applied: {}", reason)
+            }
+            MergeConflict(locations : Vec<String>) {
+                description("merged specs disagree")
+                display("merged specs disagree on {}", locations.join(", "))
+            }
         }
     }
 }
@@ -24,9 +53,89 @@ mod errors {
 extern crate select;
 extern crate reqwest;
 extern crate openapi;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate regex;
 
 mod acquisition;
 pub use acquisition::*;
 
 mod transform;
-pub use transform::*;
\ No newline at end of file
+pub use transform::*;
+
+mod options;
+pub use options::*;
+
+mod config;
+pub use config::*;
+
+mod annotations;
+pub use annotations::*;
+
+mod openapi3;
+pub use openapi3::*;
+
+mod split;
+pub use split::*;
+
+mod inline;
+pub use inline::*;
+
+mod json_schema;
+pub use json_schema::*;
+
+mod validation;
+pub use validation::*;
+
+mod lint;
+pub use lint::*;
+
+mod diff;
+pub use diff::*;
+
+mod changelog;
+pub use changelog::*;
+
+mod overlay;
+pub use overlay::*;
+
+mod merge;
+pub use merge::*;
+
+mod canonical;
+pub use canonical::*;
+
+mod capabilities;
+pub use capabilities::*;
+
+mod webhooks;
+pub use webhooks::*;
+
+mod selectors;
+pub use selectors::*;
+
+mod cassette;
+pub use cassette::*;
+
+mod source;
+pub use source::*;
+
+mod doctor;
+pub use doctor::*;
+
+mod release_notes;
+pub use release_notes::*;
+
+mod rate_limits;
+pub use rate_limits::*;
+
+mod report;
+pub use report::*;
+
+mod serde_with;
+
+mod model_cache;
+pub use model_cache::*;
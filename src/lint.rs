@@ -0,0 +1,255 @@
+//! Style rules for generated specs, beyond what the specification requires
+
+use annotations::pointer;
+use validation::OPERATION_METHODS;
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Members of the OData envelope around entities, which need no description of their own
+const ENVELOPE_MEMBERS: &'static [&'static str] = &["d", "results"];
+
+/// A style rule the linter checks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// Every operation has an `operationId`
+    OperationIds,
+    /// No two operations share an `operationId`
+    UniqueOperationIds,
+    /// Every operation has a summary or description
+    OperationDescriptions,
+    /// Every property of a definition has a description
+    PropertyDescriptions,
+    /// Every operation has a successful response, and those of GET and POST have a schema
+    EmptyResponses,
+    /// `operationId`s are lowerCamelCase
+    OperationIdCase,
+    /// Definition names are UpperCamelCase
+    DefinitionNameCase,
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            LintRule::OperationIds => "operation_ids",
+            LintRule::UniqueOperationIds => "unique_operation_ids",
+            LintRule::OperationDescriptions => "operation_descriptions",
+            LintRule::PropertyDescriptions => "property_descriptions",
+            LintRule::EmptyResponses => "empty_responses",
+            LintRule::OperationIdCase => "operation_id_case",
+            LintRule::DefinitionNameCase => "definition_name_case",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What breaking a rule leads to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The rule is not checked
+    Off,
+    /// Findings end up in the report
+    Warning,
+    /// Findings end up in the report and the spec is not written
+    Error,
+}
+
+impl LintRule {
+    /// The severity unless configured otherwise. Rules on how the spec is written are off, as
+    /// the docs leave plenty undescribed; rules codegen tools depend on are warnings.
+    pub fn default_severity(&self) -> Severity {
+        match *self {
+            LintRule::OperationIds | LintRule::UniqueOperationIds | LintRule::EmptyResponses => Severity::Warning,
+            LintRule::OperationDescriptions | LintRule::PropertyDescriptions
+                | LintRule::OperationIdCase | LintRule::DefinitionNameCase => Severity::Off,
+        }
+    }
+}
+
+/// Severities of the lint rules, rules not listed have their default severity
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LintOptions {
+    pub rules: BTreeMap<LintRule, Severity>,
+}
+
+impl LintOptions {
+    pub fn severity(&self, rule: LintRule) -> Severity {
+        self.rules.get(&rule).cloned().unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// A broken rule, `location` points at the offending part of the spec
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub severity: Severity,
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}: {}", self.rule, self.location, self.message)
+    }
+}
+
+struct Linter<'a> {
+    options: &'a LintOptions,
+    findings: Vec<LintFinding>,
+}
+
+impl<'a> Linter<'a> {
+    fn report<S: AsRef<str>>(&mut self, rule: LintRule, location: &[S], message: String) {
+        let severity = self.options.severity(rule);
+        if severity != Severity::Off {
+            self.findings.push(LintFinding { rule: rule, severity: severity, location: pointer(location), message: message });
+        }
+    }
+}
+
+/// Checks the style rules on a Swagger 2.0 or OpenAPI 3.0 document
+pub fn lint_spec(spec: &Value, options: &LintOptions) -> Vec<LintFinding> {
+    let mut linter = Linter { options: options, findings: Vec::new() };
+    let mut operation_ids = BTreeMap::new();
+    let paths = spec.get("paths").and_then(|p| p.as_object()).cloned().unwrap_or_default();
+    for (path, item) in &paths {
+        for method in OPERATION_METHODS {
+            let operation = match item.get(*method) {
+                Some(operation) => operation,
+                None => continue,
+            };
+            let location = ["paths", path, method];
+            match operation.get("operationId").and_then(|i| i.as_str()) {
+                Some(id) => {
+                    if let Some(first) = operation_ids.insert(id.to_owned(), pointer(&location)) {
+                        linter.report(LintRule::UniqueOperationIds, &location,
+                            format!("operationId '{}' is also used by {}", id, first));
+                    }
+                    if !is_camel_case(id, false) {
+                        linter.report(LintRule::OperationIdCase, &location,
+                            format!("operationId '{}' is not lowerCamelCase", id));
+                    }
+                },
+                None => linter.report(LintRule::OperationIds, &location, "has no operationId".to_owned()),
+            }
+            if operation.get("summary").is_none() && operation.get("description").is_none() {
+                linter.report(LintRule::OperationDescriptions, &location, "has no summary or description".to_owned());
+            }
+            lint_responses(&mut linter, &location, method, operation);
+        }
+    }
+
+    let definitions = spec.pointer("/definitions").or_else(|| spec.pointer("/components/schemas"))
+        .and_then(|d| d.as_object()).cloned().unwrap_or_default();
+    let definitions_path = if spec.get("openapi").is_some() { vec!["components", "schemas"] } else { vec!["definitions"] };
+    for (name, definition) in &definitions {
+        let mut location = definitions_path.clone();
+        location.push(name.as_str());
+        if !is_camel_case(name, true) {
+            linter.report(LintRule::DefinitionNameCase, &location, format!("'{}' is not UpperCamelCase", name));
+        }
+        lint_properties(&mut linter, &mut location.iter().map(|s| s.to_string()).collect(), definition);
+    }
+    linter.findings
+}
+
+fn lint_responses(linter: &mut Linter, location: &[&str], method: &str, operation: &Value) {
+    let responses = operation.get("responses").and_then(|r| r.as_object()).cloned().unwrap_or_default();
+    let successes: Vec<_> = responses.iter().filter(|&(status, _)| status.starts_with('2')).collect();
+    if successes.is_empty() {
+        linter.report(LintRule::EmptyResponses, location, "has no successful response".to_owned());
+    }
+    if method != "get" && method != "post" {
+        return;
+    }
+    for (status, response) in successes {
+        let described = response.get("schema").is_some() || response.get("content").is_some() || response.get("$ref").is_some();
+        if !described {
+            let mut location = location.to_vec();
+            location.push("responses");
+            location.push(status);
+            linter.report(LintRule::EmptyResponses, &location, "has no schema".to_owned());
+        }
+    }
+}
+
+/// Checks the properties of the schema and those of the schemas it nests
+fn lint_properties(linter: &mut Linter, location: &mut Vec<String>, schema: &Value) {
+    if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+        for (name, property) in properties {
+            location.push("properties".to_owned());
+            location.push(name.clone());
+            let undescribed = property.get("description").is_none() && property.get("$ref").is_none();
+            if undescribed && !ENVELOPE_MEMBERS.contains(&name.as_str()) {
+                linter.report(LintRule::PropertyDescriptions, location, "has no description".to_owned());
+            }
+            lint_properties(linter, location, property);
+            location.pop();
+            location.pop();
+        }
+    }
+    if let Some(items) = schema.get("items") {
+        location.push("items".to_owned());
+        lint_properties(linter, location, items);
+        location.pop();
+    }
+}
+
+/// Whether the name consists of letters and digits only and starts with an upper or lower case letter
+fn is_camel_case(name: &str, upper: bool) -> bool {
+    let first = name.chars().next();
+    first.map_or(false, |c| c.is_alphabetic() && c.is_uppercase() == upper)
+        && name.chars().all(|c| c.is_alphanumeric())
+}
+
+#[test]
+fn it_lints_specs() {
+    let spec = json!({
+        "swagger": "2.0",
+        "paths": {
+            "/a": {
+                "get": { "operationId": "getA", "summary": "List A", "responses": { "200": {} } },
+                "delete": { "operationId": "getA", "summary": "Delete A", "responses": { "204": {} } }
+            },
+            "/b": {
+                "post": { "operationId": "Post_B", "responses": { "500": { "schema": {} } } },
+                "put": { "summary": "Update B", "responses": { "204": {} } }
+            }
+        },
+        "definitions": {
+            "a_response": { "properties": {
+                "ID": { "type": "string", "description": "Primary key" },
+                "Lines": { "type": "array", "items": { "properties": { "Code": { "type": "string" } } } }
+            } }
+        }
+    });
+    let findings: Vec<String> = lint_spec(&spec, &LintOptions::default()).iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, vec![
+        "empty_responses at /paths/~1a/get/responses/200: has no schema",
+        "unique_operation_ids at /paths/~1a/delete: operationId 'getA' is also used by /paths/~1a/get",
+        "operation_ids at /paths/~1b/put: has no operationId",
+        "empty_responses at /paths/~1b/post: has no successful response",
+    ]);
+
+    let mut options = LintOptions::default();
+    options.rules.insert(LintRule::UniqueOperationIds, Severity::Error);
+    options.rules.insert(LintRule::OperationDescriptions, Severity::Warning);
+    options.rules.insert(LintRule::PropertyDescriptions, Severity::Warning);
+    options.rules.insert(LintRule::DefinitionNameCase, Severity::Warning);
+    let findings: Vec<String> = lint_spec(&spec, &options).iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, vec![
+        "empty_responses at /paths/~1a/get/responses/200: has no schema",
+        "unique_operation_ids at /paths/~1a/delete: operationId 'getA' is also used by /paths/~1a/get",
+        "operation_ids at /paths/~1b/put: has no operationId",
+        "operation_descriptions at /paths/~1b/post: has no summary or description",
+        "empty_responses at /paths/~1b/post: has no successful response",
+        "definition_name_case at /definitions/a_response: 'a_response' is not UpperCamelCase",
+        "property_descriptions at /definitions/a_response/properties/Lines: has no description",
+        "property_descriptions at /definitions/a_response/properties/Lines/items/properties/Code: has no description",
+    ]);
+    assert_eq!(lint_spec(&spec, &options)[1].severity, Severity::Error);
+}
@@ -1,33 +1,295 @@
 extern crate exact_openapi_gen;
 extern crate reqwest;
 extern crate openapi;
+extern crate serde_json;
+extern crate serde_yaml;
 
+use exact_openapi_gen::{Config, Scraper, CheckStatus, CassetteMode, EndpointDetails, GenerationReport, ModelCache, OutputConfig, SpecOptions};
+
+use std::env;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
+use std::process;
+
+const CONFIG_FILE: &'static str = "exact-openapi-gen.json";
+/// Session cookies for the documentation site, overriding the ones in the config file
+const COOKIES_VAR: &'static str = "EXACT_DOCS_COOKIES";
+
+const USAGE: &'static str = "Usage: exact-openapi-gen [generate|doctor|refresh --budget N|diff OLD NEW [--markdown TITLE]|merge OUTPUT SPEC...]";
 
 fn main() {
-    let selected_endpoint_names = vec![
-        "SystemSystemMe",
-        "ProjectProjects", 
-        "ManufacturingOperations", 
-        "ManufacturingShopOrderRoutingStepPlans",
-        "PayrollEmploymentContracts",
-        "ManufacturingTimeTransactions"
-    ];
-
-    let urls = exact_openapi_gen::fetch_endpoint_urls().expect("Fetched endpoint urls");
-    let endpoints = urls.into_iter()
-        .filter(|url| {
-            let url = String::from(url.as_str());
-            selected_endpoint_names.iter().any(|selected| url.ends_with(selected))
-        })
-        .filter_map(|url| {
-            println!("{}", &url);
-            exact_openapi_gen::fetch_endpoint_details(url).ok()
-        }).collect();
-
-    let spec = exact_openapi_gen::build_spec(endpoints);
-    let json = openapi::to_json(&spec.expect("Valid spec")).expect("Valid json spec");
-    let mut file = File::create("api.json").expect("File opened");
-    file.write_all(json.as_bytes()).expect("Successfully written to file");
-}
\ No newline at end of file
+    // Reports a broken config instead of failing on it
+    if env::args().nth(1).as_ref().map(|s| s.as_str()) == Some("doctor") {
+        doctor();
+        return;
+    }
+    let mut config = if Path::new(CONFIG_FILE).exists() {
+        Config::from_path(CONFIG_FILE).expect("Valid config file")
+    } else {
+        Config::default()
+    };
+    if let Ok(cookies) = env::var(COOKIES_VAR) {
+        config.fetch.cookies = Some(cookies);
+    }
+
+    let selectors = config.selectors().expect("Valid selectors file");
+    let scraper = Scraper::new(config.fetch.clone(), selectors).expect("HTTP client");
+
+    match env::args().nth(1).as_ref().map(|s| s.as_str()) {
+        None | Some("generate") => generate(&config, &scraper),
+        Some("refresh") => match parse_budget() {
+            Some(budget) => refresh(&config, &scraper, budget),
+            None => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        },
+        Some("diff") => match (env::args().nth(2), env::args().nth(3)) {
+            (Some(old), Some(new)) => diff(&config, &old, &new, parse_flag("--markdown")),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        },
+        Some("merge") => {
+            let paths: Vec<String> = env::args().skip(2).collect();
+            if paths.len() < 2 {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+            merge(&config, &paths[0], &paths[1..]);
+        },
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
+
+fn generate(config: &Config, scraper: &Scraper) {
+    let mut report = GenerationReport::new();
+    let mut cache = match config.model_cache {
+        Some(ref path) => ModelCache::from_path(path).expect("Valid model cache"),
+        None => ModelCache::new(),
+    };
+    let selected = config.selected_endpoints();
+    let links = scraper.fetch_endpoint_index().expect("Fetched endpoint urls");
+    let mut tracker = config.fetch.failure_budget.tracker();
+    let mut endpoints = Vec::new();
+    for link in links.into_iter().filter(|link| matches_any(&link.url, &selected)) {
+        println!("{}", &link.url);
+        let outcome = match scraper.fetch_endpoint_incremental(&link, &mut cache) {
+            Ok(endpoint) => {
+                report.inspect_endpoint(&endpoint);
+                endpoints.push(endpoint);
+                tracker.record(true)
+            },
+            Err(e) => {
+                report.failed_endpoint(&link.url, &e);
+                tracker.record(false)
+            }
+        };
+        if let Err(e) = outcome {
+            // Keep the previous specs, the partial results only end up in the report
+            eprintln!("Error: {}", e);
+            write_report(config, &report);
+            process::exit(1);
+        }
+    }
+    report.inspect_cache(&cache);
+    if let Some(ref path) = config.model_cache {
+        cache.save(path).expect("Model cache written");
+    }
+
+    if let Some(ref path) = config.capabilities_output {
+        let capabilities = exact_openapi_gen::build_capabilities(&endpoints, &config.spec);
+        let json = serde_json::to_string_pretty(&capabilities).expect("Valid json capabilities");
+        let mut file = File::create(path).expect("File opened");
+        file.write_all(json.as_bytes()).expect("Successfully written to file");
+    }
+
+    for output in config.outputs() {
+        let selection: Vec<_> = endpoints.iter()
+            .filter(|e| matches_any(&e.doc_url, &output.endpoints))
+            .collect();
+        let options = output.spec.as_ref().unwrap_or(&config.spec);
+        let inputs = output_inputs(config, &output, &selection, options);
+        if config.model_cache.is_some() && cache.output_unchanged(&output.file, inputs) && Path::new(&output.file).exists() {
+            println!("{} is up to date", output.file);
+            continue;
+        }
+        let mut spec = exact_openapi_gen::build_document(&selection, options).expect("Valid spec");
+        for overlay in &config.overlays {
+            exact_openapi_gen::apply_overlay(&mut spec, &read_document(overlay));
+        }
+        for patch in &config.merge_patches {
+            exact_openapi_gen::merge_patch(&mut spec, &read_document(patch));
+        }
+        let violations = exact_openapi_gen::validate_spec(&spec);
+        if !violations.is_empty() {
+            // Keep the previous spec rather than handing out a broken one
+            eprintln!("Error: {} violates the specification", output.file);
+            report.invalid_output(&output.file, violations);
+            write_report(config, &report);
+            process::exit(1);
+        }
+        let findings = exact_openapi_gen::lint_spec(&spec, &config.lint);
+        if report.lint_output(&output.file, findings) {
+            eprintln!("Error: {} breaks lint rules set to error", output.file);
+            write_report(config, &report);
+            process::exit(1);
+        }
+        report.inspect_output(&output.file, &spec);
+        if output.split_by_tag {
+            let path = Path::new(&output.file);
+            let root_file = path.file_name().and_then(|f| f.to_str()).expect("Output file name");
+            let split = exact_openapi_gen::split_by_tag(&spec, root_file, output.extension());
+            for (name, part) in split.parts {
+                write_spec(&path.with_file_name(name), part, output.is_yaml());
+            }
+            write_spec(path, split.root, output.is_yaml());
+        } else {
+            write_spec(Path::new(&output.file), spec, output.is_yaml());
+        }
+        cache.store_output(&output.file, inputs);
+    }
+    if let Some(ref path) = config.model_cache {
+        cache.save(path).expect("Model cache written");
+    }
+
+    write_report(config, &report);
+}
+
+/// Hash of everything the output is generated from, so outputs are only generated again when
+/// their endpoints, options, overlays or this generator changed
+fn output_inputs(config: &Config, output: &OutputConfig, selection: &[&EndpointDetails], options: &SpecOptions) -> u64 {
+    let mut inputs = serde_json::to_string(selection).expect("Endpoints serialize");
+    inputs.push_str(&format!("{:?}{:?}{:?}", output, options, config.lint));
+    for path in config.overlays.iter().chain(&config.merge_patches) {
+        inputs.push_str(&read_document(path).to_string());
+    }
+    inputs.push_str(env!("CARGO_PKG_VERSION"));
+    exact_openapi_gen::content_hash(&inputs)
+}
+
+fn write_spec(path: &Path, mut spec: serde_json::Value, yaml: bool) {
+    // Overlays and merging can leave the spec out of order
+    exact_openapi_gen::canonicalize(&mut spec);
+    let serialized = if yaml {
+        serde_yaml::to_string(&spec).expect("Valid yaml spec")
+    } else {
+        serde_json::to_string_pretty(&spec).expect("Valid json spec")
+    };
+    let mut file = File::create(path).expect("File opened");
+    file.write_all(serialized.as_bytes()).expect("Successfully written to file");
+}
+
+fn write_report(config: &Config, report: &GenerationReport) {
+    for warning in &report.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if let Some(ref path) = config.report_output {
+        let json = serde_json::to_string_pretty(report).expect("Valid json report");
+        let mut file = File::create(path).expect("File opened");
+        file.write_all(json.as_bytes()).expect("Successfully written to file");
+    }
+}
+
+/// Whether the documentation url belongs to one of the named endpoints
+fn matches_any(url: &reqwest::Url, names: &[String]) -> bool {
+    let url = String::from(url.as_str());
+    names.iter().any(|selected| url.ends_with(selected.as_str()))
+}
+
+/// The value of the `--budget` flag
+fn parse_budget() -> Option<usize> {
+    parse_flag("--budget").and_then(|value| value.parse().ok())
+}
+
+/// The value following the flag on the command line
+fn parse_flag(flag: &str) -> Option<String> {
+    let args: Vec<_> = env::args().skip(2).collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// Re-fetches the stalest cached pages, then regenerates the specs from the cache alone
+fn refresh(config: &Config, scraper: &Scraper, budget: usize) {
+    for url in scraper.refresh(budget).expect("Refreshed cached pages") {
+        println!("{}", url);
+    }
+
+    let mut fetch = config.fetch.clone();
+    if let Some(ref mut cassette) = fetch.cassette {
+        cassette.mode = CassetteMode::Replay;
+    }
+    let cached = Scraper::new(fetch, scraper.selectors().clone()).expect("HTTP client");
+    generate(config, &cached);
+}
+
+fn doctor() {
+    let checks = exact_openapi_gen::diagnose(Path::new(CONFIG_FILE), env::var(COOKIES_VAR).ok());
+    for check in &checks {
+        println!("{}", check);
+    }
+    if checks.iter().any(|c| c.status == CheckStatus::Failed) {
+        process::exit(1);
+    }
+}
+
+/// Prints the differences between two specs, as a Markdown changelog with the given title if any,
+/// exiting with 1 if any of them is breaking
+fn diff(config: &Config, old: &str, new: &str, changelog_title: Option<String>) {
+    let diff = exact_openapi_gen::diff_specs(&read_spec(config, old), &read_spec(config, new));
+    if let Some(title) = changelog_title {
+        print!("{}", exact_openapi_gen::render_changelog(&diff, &title));
+    } else {
+        for change in &diff.changes {
+            let kind = if change.is_breaking() { "breaking" } else { "non-breaking" };
+            println!("[{}] {}", kind, change);
+        }
+    }
+    if !diff.breaking().is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Combines the specs, or model caches, of several runs into one written to `output`
+fn merge(config: &Config, output: &str, inputs: &[String]) {
+    let specs: Vec<_> = inputs.iter().map(|path| read_spec(config, path)).collect();
+    match exact_openapi_gen::merge_specs(&specs) {
+        Ok(spec) => write_spec(Path::new(output), spec, output.ends_with(".yaml") || output.ends_with(".yml")),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        },
+    }
+}
+
+/// Reads a spec, or generates one from a model cache file with the configured options
+fn read_spec(config: &Config, path: &str) -> serde_json::Value {
+    let spec = read_document(path);
+    if spec.get("pages").is_none() {
+        return spec;
+    }
+    let mut cache = ModelCache::from_path(path).expect("Valid model cache");
+    let endpoints = cache.endpoints();
+    for warning in cache.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    let endpoints: Vec<_> = endpoints.iter().collect();
+    exact_openapi_gen::build_document(&endpoints, &config.spec).expect("Valid spec")
+}
+
+/// Reads a YAML file, or a JSON file for any other extension
+fn read_document(path: &str) -> serde_json::Value {
+    let file = File::open(path).expect("File opened");
+    if path.ends_with(".yaml") || path.ends_with(".yml") {
+        serde_yaml::from_reader(file).expect("Valid yaml document")
+    } else {
+        serde_json::from_reader(file).expect("Valid json document")
+    }
+}
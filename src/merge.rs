@@ -0,0 +1,218 @@
+//! Specs generated from different runs, each covering a subset of the endpoints, combined into one
+
+use annotations::pointer;
+use errors::*;
+use errors::ErrorKind::{MergeConflict, SpecParseError};
+use validation::OPERATION_METHODS;
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Sections holding named entries, gathered from all specs. Path items are merged by method.
+const SECTIONS: &'static [&'static [&'static str]] = &[
+    &["paths"],
+    &["definitions"],
+    &["parameters"],
+    &["responses"],
+    &["securityDefinitions"],
+    &["x-webhooks"],
+    &["components", "schemas"],
+    &["components", "parameters"],
+    &["components", "responses"],
+    &["components", "securitySchemes"],
+];
+
+/// Combines the paths, definitions and other named parts of the specs into the first one, which
+/// also provides `info` and the other root members. Tags are combined by name.
+///
+/// Parts found in several specs must be equal, an endpoint scraped twice generates the same
+/// definitions, so they can't silently win over each other. Neither can operations on different
+/// paths share an `operationId`. Every part that differs is listed in the error.
+pub fn merge_specs(specs: &[Value]) -> Result<Value> {
+    if let Some(i) = specs.iter().position(|s| !s.is_object()) {
+        bail!(SpecParseError(format!("spec {} to merge is not an object", i + 1)));
+    }
+    let mut merged = match specs.first() {
+        Some(first) => first.clone(),
+        None => return Ok(Value::Object(Map::new())),
+    };
+    let mut conflicts = Vec::new();
+    for spec in &specs[1..] {
+        for section in SECTIONS {
+            let entries = match lookup(spec, section).and_then(|s| s.as_object()) {
+                Some(entries) => entries,
+                None => continue,
+            };
+            let target = section_mut(&mut merged, section);
+            for (name, entry) in entries {
+                let mut location: Vec<&str> = section.to_vec();
+                location.push(name);
+                if section[0] == "paths" {
+                    merge_path_item(target, name, entry, &location, &mut conflicts);
+                } else {
+                    merge_entry(target, name, entry, &location, &mut conflicts);
+                }
+            }
+        }
+        merge_tags(&mut merged, spec);
+    }
+    find_shared_operation_ids(&merged, &mut conflicts);
+    if !conflicts.is_empty() {
+        bail!(MergeConflict(conflicts));
+    }
+    Ok(merged)
+}
+
+/// Adds the entry, unless a different one of the same name exists
+fn merge_entry(target: &mut Map<String, Value>, name: &str, entry: &Value, location: &[&str], conflicts: &mut Vec<String>) {
+    match target.get(name) {
+        Some(existing) if existing != entry => conflicts.push(pointer(location)),
+        Some(_) => {},
+        None => {
+            target.insert(name.to_owned(), entry.clone());
+        },
+    }
+}
+
+/// Adds the operations of the path item, so runs can each contribute methods of a path
+fn merge_path_item(target: &mut Map<String, Value>, path: &str, item: &Value, location: &[&str], conflicts: &mut Vec<String>) {
+    let merged = match (target.get_mut(path), item.as_object()) {
+        (Some(&mut Value::Object(ref mut existing)), Some(item)) => {
+            for (key, value) in item {
+                let mut location = location.to_vec();
+                location.push(key);
+                merge_entry(existing, key, value, &location, conflicts);
+            }
+            true
+        },
+        _ => false,
+    };
+    if !merged {
+        merge_entry(target, path, item, location, conflicts);
+    }
+}
+
+/// Adds the tags the merged spec doesn't have yet
+fn merge_tags(merged: &mut Value, spec: &Value) {
+    let tags = match spec.get("tags").and_then(|t| t.as_array()) {
+        Some(tags) => tags,
+        None => return,
+    };
+    let object = match merged.as_object_mut() {
+        Some(object) => object,
+        None => return,
+    };
+    let target = object.entry("tags".to_owned()).or_insert_with(|| Value::Array(Vec::new()));
+    if let Value::Array(ref mut target) = *target {
+        for tag in tags {
+            if !target.iter().any(|t| t.get("name") == tag.get("name")) {
+                target.push(tag.clone());
+            }
+        }
+    }
+}
+
+/// Lists the operations whose `operationId` an operation on another path already has, which
+/// runs generate when they number colliding ids differently
+fn find_shared_operation_ids(spec: &Value, conflicts: &mut Vec<String>) {
+    let paths = match spec.get("paths").and_then(|p| p.as_object()) {
+        Some(paths) => paths,
+        None => return,
+    };
+    let mut used = BTreeMap::new();
+    for (path, item) in paths {
+        for method in OPERATION_METHODS {
+            let id = match item.pointer(&format!("/{}/operationId", method)).and_then(|i| i.as_str()) {
+                Some(id) => id,
+                None => continue,
+            };
+            let location = pointer(&["paths", path, method, "operationId"]);
+            if used.insert(id, path).map_or(false, |first| first != path) {
+                conflicts.push(location);
+            }
+        }
+    }
+}
+
+fn lookup<'a>(spec: &'a Value, section: &[&str]) -> Option<&'a Value> {
+    section.iter().fold(Some(spec), |value, key| value.and_then(|v| v.get(*key)))
+}
+
+/// The section of the spec, created when missing
+fn section_mut<'a>(spec: &'a mut Value, section: &[&str]) -> &'a mut Map<String, Value> {
+    let mut value = spec;
+    for key in section {
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        value = { value }.as_object_mut().expect("Object").entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    }
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    value.as_object_mut().expect("Object")
+}
+
+#[test]
+fn it_merges_specs() {
+    let first = json!({
+        "swagger": "2.0",
+        "info": { "title": "Exact Online REST API" },
+        "tags": [{ "name": "Project" }],
+        "paths": { "/project/Projects": { "get": { "operationId": "getProjectProjects" } } },
+        "definitions": { "Error": { "type": "object" }, "ProjectProjectsResponse": { "type": "object" } }
+    });
+    let second = json!({
+        "swagger": "2.0",
+        "info": { "title": "Second run" },
+        "tags": [{ "name": "Project" }, { "name": "Payroll" }],
+        "paths": {
+            "/project/Projects": { "post": { "operationId": "postProjectProjects" } },
+            "/payroll/EmploymentContracts": { "get": { "operationId": "getPayrollEmploymentContracts" } }
+        },
+        "definitions": { "Error": { "type": "object" }, "PayrollEmploymentContractsResponse": { "type": "object" } }
+    });
+    let merged = merge_specs(&[first.clone(), second]).expect("Specs without conflicts");
+    assert_eq!(merged, json!({
+        "swagger": "2.0",
+        "info": { "title": "Exact Online REST API" },
+        "tags": [{ "name": "Project" }, { "name": "Payroll" }],
+        "paths": {
+            "/project/Projects": {
+                "get": { "operationId": "getProjectProjects" },
+                "post": { "operationId": "postProjectProjects" }
+            },
+            "/payroll/EmploymentContracts": { "get": { "operationId": "getPayrollEmploymentContracts" } }
+        },
+        "definitions": {
+            "Error": { "type": "object" },
+            "ProjectProjectsResponse": { "type": "object" },
+            "PayrollEmploymentContractsResponse": { "type": "object" }
+        }
+    }));
+
+    let conflicting = json!({
+        "paths": { "/project/Projects": { "get": { "operationId": "listProjects" } } },
+        "definitions": { "ProjectProjectsResponse": { "type": "array" } }
+    });
+    match merge_specs(&[first.clone(), conflicting]) {
+        Err(Error(MergeConflict(locations), _)) => assert_eq!(locations, vec![
+            "/paths/~1project~1Projects/get",
+            "/definitions/ProjectProjectsResponse",
+        ]),
+        other => panic!("Expected a conflict, got {:?}", other),
+    }
+
+    let renumbered = json!({
+        "paths": { "/payroll/Contracts": { "get": { "operationId": "getProjectProjects" } } }
+    });
+    match merge_specs(&[first.clone(), renumbered]) {
+        Err(Error(MergeConflict(locations), _)) =>
+            assert_eq!(locations, vec!["/paths/~1project~1Projects/get/operationId"]),
+        other => panic!("Expected a conflict, got {:?}", other),
+    }
+    match merge_specs(&[json!([]), first]) {
+        Err(Error(SpecParseError(_), _)) => {},
+        other => panic!("Expected an error, got {:?}", other),
+    }
+}
@@ -0,0 +1,161 @@
+use acquisition::EndpointDetails;
+use selectors::Selectors;
+use errors::*;
+
+use reqwest::Url;
+use serde_json::{self, Value};
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Version of the cache format, raised whenever `EndpointDetails` changes shape so caches of
+/// earlier versions are parsed again rather than misread
+const CACHE_VERSION: u32 = 2;
+
+/// Parsed endpoint details from earlier runs, keyed by documentation url and tagged with
+/// a hash of the page they were parsed from, so unchanged pages need not be parsed again.
+/// Also remembers what each output was generated from, so unchanged outputs need not be
+/// generated again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelCache {
+    #[serde(default)]
+    version: u32,
+    pages: BTreeMap<String, CachedPage>,
+    /// Hash of the inputs of each output, keyed by output file
+    #[serde(default)]
+    outputs: BTreeMap<String, u64>,
+    /// Why cached details could not be used
+    #[serde(skip)]
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedPage {
+    hash: u64,
+    /// Hash of the selectors the page was parsed with, see `selectors_hash`
+    #[serde(default)]
+    selectors: u64,
+    details: Value,
+}
+
+impl ModelCache {
+    pub fn new() -> ModelCache {
+        ModelCache {
+            version: CACHE_VERSION,
+            pages: BTreeMap::new(),
+            outputs: BTreeMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Reads the cache from a JSON file, an absent file is an empty cache. So is a cache of
+    /// another format version, which is noted in the warnings.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<ModelCache> {
+        if !path.as_ref().exists() {
+            return Ok(ModelCache::new());
+        }
+        let file = File::open(path.as_ref())?;
+        let cache: ModelCache = serde_json::from_reader(file)
+            .chain_err(|| format!("While reading model cache {}", path.as_ref().display()))?;
+        if cache.version != CACHE_VERSION {
+            let mut cache = ModelCache::new();
+            cache.warnings.push(format!("{} is in format {} rather than {}, all pages are parsed again",
+                path.as_ref().display(), cache.version, CACHE_VERSION));
+            return Ok(cache);
+        }
+        Ok(cache)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        serde_json::to_writer(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    /// The details parsed earlier from the page at `url`, if its content is unchanged and it
+    /// was parsed with the same selectors
+    pub fn lookup(&mut self, url: &Url, hash: u64, selectors: u64) -> Option<EndpointDetails> {
+        let parsed = match self.pages.get(url.as_str()) {
+            Some(page) if page.hash == hash && page.selectors == selectors => serde_json::from_value(page.details.clone()),
+            _ => return None,
+        };
+        match parsed {
+            Ok(details) => Some(details),
+            Err(e) => {
+                self.warnings.push(format!("cached details of {} are unreadable, the page is parsed again: {}", url, e));
+                None
+            },
+        }
+    }
+
+    /// All details in the cache, whatever the pages look like now. Unreadable details are
+    /// left out and noted in the warnings.
+    pub fn endpoints(&mut self) -> Vec<EndpointDetails> {
+        let mut endpoints = Vec::new();
+        for (url, page) in &self.pages {
+            match serde_json::from_value(page.details.clone()) {
+                Ok(details) => endpoints.push(details),
+                Err(e) => self.warnings.push(format!("cached details of {} are unreadable, they are left out: {}", url, e)),
+            }
+        }
+        endpoints
+    }
+
+    pub fn store(&mut self, url: &Url, hash: u64, selectors: u64, details: &EndpointDetails) -> Result<()> {
+        let details = serde_json::to_value(details)?;
+        self.pages.insert(url.to_string(), CachedPage { hash: hash, selectors: selectors, details: details });
+        Ok(())
+    }
+
+    /// Whether `output` was last generated from inputs with the given hash
+    pub fn output_unchanged(&self, output: &str, inputs: u64) -> bool {
+        self.outputs.get(output) == Some(&inputs)
+    }
+
+    pub fn store_output(&mut self, output: &str, inputs: u64) {
+        self.outputs.insert(output.to_owned(), inputs);
+    }
+
+    /// Problems with the cache, like details it holds that can no longer be read
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+/// Hash of the selectors, pages parsed with other selectors are parsed again
+pub fn selectors_hash(selectors: &Selectors) -> u64 {
+    content_hash(&serde_json::to_string(selectors).expect("Selectors serialize"))
+}
+
+/// 64 bit FNV-1a hash of a page, stable across runs and platforms
+pub fn content_hash(content: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    content.bytes().fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+#[test]
+fn it_hashes_page_contents() {
+    assert_eq!(content_hash(""), 0xcbf29ce484222325);
+    assert_eq!(content_hash("a"), 0xaf63dc4c8601ec8c);
+    assert_ne!(content_hash("<html>1</html>"), content_hash("<html>2</html>"));
+}
+
+#[test]
+fn it_only_reuses_details_parsed_alike() {
+    let url = Url::parse("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=ProjectProjects").unwrap();
+    let mut cache = ModelCache::new();
+    cache.pages.insert(url.to_string(), CachedPage { hash: 1, selectors: 2, details: json!({ "name": 3 }) });
+    assert!(cache.lookup(&url, 1, 3).is_none());
+    assert!(cache.lookup(&url, 2, 2).is_none());
+    assert!(cache.warnings().is_empty());
+    // Details of an older shape are parsed again, and reported
+    assert!(cache.lookup(&url, 1, 2).is_none());
+    assert_eq!(cache.warnings().len(), 1);
+    assert!(cache.endpoints().is_empty());
+    assert_eq!(cache.warnings().len(), 2);
+
+    let old: ModelCache = serde_json::from_value(json!({ "pages": {} })).unwrap();
+    assert_eq!(old.version, 0);
+    assert_ne!(selectors_hash(&Selectors::default()), 0);
+}
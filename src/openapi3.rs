@@ -0,0 +1,502 @@
+//! Conversion of the generated Swagger 2.0 document into OpenAPI 3.0
+
+use serde_json::{Map, Value};
+
+/// Members of a Swagger 2.0 non-body parameter that describe its value, which OpenAPI 3.0 moves into `schema`
+const PARAMETER_SCHEMA_MEMBERS: &'static [&'static str] = &[
+    "type", "format", "items", "collectionFormat", "default", "maximum", "exclusiveMaximum", "minimum",
+    "exclusiveMinimum", "maxLength", "minLength", "pattern", "maxItems", "minItems", "uniqueItems",
+    "enum", "multipleOf",
+];
+
+/// Converts a Swagger 2.0 document into an OpenAPI 3.0 one: definitions, parameters, responses and
+/// security definitions move to `components`, body parameters become `requestBody`,
+/// response schemas move into `content` and host, base path and schemes become `servers`
+pub fn to_openapi3(swagger: &Value) -> Value {
+    let mut spec = rewrite_refs(swagger);
+    let root = match spec.as_object_mut() {
+        Some(root) => root,
+        None => return spec,
+    };
+    let consumes = media_types(root.remove("consumes"));
+    let produces = media_types(root.remove("produces"));
+
+    let mut converted = Map::new();
+    converted.insert("openapi".to_owned(), json!("3.0.0"));
+    if let Some(info) = root.remove("info") {
+        converted.insert("info".to_owned(), info);
+    }
+    converted.insert("servers".to_owned(), build_servers(root));
+    root.remove("swagger");
+
+    let mut components = Map::new();
+    if let Some(definitions) = root.remove("definitions") {
+        components.insert("schemas".to_owned(), definitions);
+    }
+    if let Some(Value::Object(parameters)) = root.remove("parameters") {
+        let parameters = parameters.into_iter().map(|(name, p)| (name, convert_parameter(p))).collect();
+        components.insert("parameters".to_owned(), Value::Object(parameters));
+    }
+    if let Some(Value::Object(responses)) = root.remove("responses") {
+        let responses = responses.into_iter().map(|(status, r)| (status, convert_response(r, &produces))).collect();
+        components.insert("responses".to_owned(), Value::Object(responses));
+    }
+    if let Some(Value::Object(schemes)) = root.remove("securityDefinitions") {
+        let schemes = schemes.into_iter().map(|(name, s)| (name, convert_security_scheme(s))).collect();
+        components.insert("securitySchemes".to_owned(), Value::Object(schemes));
+    }
+
+    if let Some(Value::Object(paths)) = root.remove("paths") {
+        let paths = paths.into_iter()
+            .map(|(path, item)| (path, convert_path_item(item, &consumes, &produces)))
+            .collect();
+        converted.insert("paths".to_owned(), Value::Object(paths));
+    }
+    converted.insert("components".to_owned(), Value::Object(components));
+    // Whatever is left, like `security`, `tags` and extensions, means the same in both versions
+    for (key, value) in root.iter() {
+        converted.insert(key.clone(), value.clone());
+    }
+    Value::Object(converted)
+}
+
+fn media_types(value: Option<Value>) -> Vec<String> {
+    let types: Vec<String> = value.and_then(|v| v.as_array().cloned()).unwrap_or_default()
+        .into_iter()
+        .filter_map(|t| t.as_str().map(|t| t.to_owned()))
+        .collect();
+    if types.is_empty() {
+        vec!["application/json".to_owned()]
+    } else {
+        types
+    }
+}
+
+fn build_servers(root: &mut Map<String, Value>) -> Value {
+    let host = root.remove("host").and_then(|h| h.as_str().map(|h| h.to_owned())).unwrap_or_default();
+    let base_path = root.remove("basePath").and_then(|b| b.as_str().map(|b| b.to_owned())).unwrap_or_default();
+    let schemes = root.remove("schemes").and_then(|s| s.as_array().cloned()).unwrap_or_default();
+    let base_path = base_path.trim_right_matches('/');
+    if host.is_empty() {
+        return json!([{ "url": if base_path.is_empty() { "/" } else { base_path } }]);
+    }
+    let mut schemes: Vec<String> = schemes.iter().filter_map(|s| s.as_str().map(|s| s.to_owned())).collect();
+    if schemes.is_empty() {
+        schemes.push("https".to_owned());
+    }
+    Value::Array(schemes.iter()
+        .map(|scheme| json!({ "url": format!("{}://{}{}", scheme, host, base_path) }))
+        .collect())
+}
+
+/// The country domains Exact Online runs under, the first is the default
+const REGIONS: &'static [&'static str] = &["nl", "be", "de", "co.uk", "com", "es", "fr"];
+
+/// Moves the `{base_path}/{division}` prefix of the paths into the server url, with variables for the
+/// division and the country of the Exact Online site. Paths without a division get their own server.
+pub fn use_server_variables(spec: &mut Value, base_path: &str) {
+    let base_path = base_path.trim_right_matches('/');
+    let division_prefix = format!("{}/{{division}}", base_path);
+    let region = json!({
+        "default": REGIONS[0],
+        "enum": REGIONS,
+        "description": "Country of the Exact Online site the administration is on",
+    });
+    let server = |url: &str, division: bool| {
+        let mut variables = Map::new();
+        variables.insert("region".to_owned(), region.clone());
+        if division {
+            variables.insert("division".to_owned(), json!({
+                "default": "0",
+                "description": "The division to work in, the current one is in `/current/Me`",
+            }));
+        }
+        json!({ "url": format!("https://start.exactonline.{{region}}{}", url), "variables": variables })
+    };
+    let root = match spec.as_object_mut() {
+        Some(root) => root,
+        None => return,
+    };
+    root.insert("servers".to_owned(), json!([server(&division_prefix, true)]));
+    let paths = match root.remove("paths") {
+        Some(Value::Object(paths)) => paths,
+        Some(other) => {
+            root.insert("paths".to_owned(), other);
+            return;
+        },
+        None => return,
+    };
+    let other_servers = json!([server(base_path, false)]);
+    let paths = paths.into_iter().map(|(path, item)| {
+        let mut item = match item {
+            Value::Object(item) => item,
+            other => return (path, other),
+        };
+        if path.starts_with(&(division_prefix.clone() + "/")) {
+            // The division is filled in through the server variable now
+            let parameters = match item.remove("parameters") {
+                Some(Value::Array(parameters)) => parameters.into_iter()
+                    .filter(|p| p["$ref"] != json!("#/components/parameters/Division"))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if !parameters.is_empty() {
+                item.insert("parameters".to_owned(), Value::Array(parameters));
+            }
+            (path[division_prefix.len()..].to_owned(), Value::Object(item))
+        } else if path.starts_with(&(base_path.to_owned() + "/")) {
+            item.insert("servers".to_owned(), other_servers.clone());
+            (path[base_path.len()..].to_owned(), Value::Object(item))
+        } else {
+            (path, Value::Object(item))
+        }
+    }).collect();
+    root.insert("paths".to_owned(), Value::Object(paths));
+}
+
+/// Points references at their new home under `components`
+fn rewrite_refs(value: &Value) -> Value {
+    match *value {
+        Value::Object(ref members) => Value::Object(members.iter().map(|(key, member)| {
+            let member = match *member {
+                Value::String(ref target) if key == "$ref" => Value::String(rewrite_ref(target)),
+                _ => rewrite_refs(member),
+            };
+            // OpenAPI 3.0 has a `nullable` keyword for what Swagger 2.0 tools call `x-nullable`
+            let key = if key == "x-nullable" { "nullable".to_owned() } else { key.clone() };
+            (key, member)
+        }).collect()),
+        Value::Array(ref items) => Value::Array(items.iter().map(rewrite_refs).collect()),
+        _ => value.clone(),
+    }
+}
+
+fn rewrite_ref(target: &str) -> String {
+    let moves = [
+        ("#/definitions/", "#/components/schemas/"),
+        ("#/parameters/", "#/components/parameters/"),
+        ("#/responses/", "#/components/responses/"),
+    ];
+    for &(from, to) in moves.iter() {
+        if target.starts_with(from) {
+            return format!("{}{}", to, &target[from.len()..]);
+        }
+    }
+    target.to_owned()
+}
+
+fn convert_path_item(item: Value, consumes: &[String], produces: &[String]) -> Value {
+    let item = match item {
+        Value::Object(item) => item,
+        other => return other,
+    };
+    Value::Object(item.into_iter().map(|(key, value)| {
+        let value = match key.as_str() {
+            "get" | "put" | "post" | "delete" | "options" | "head" | "patch" =>
+                convert_operation(value, consumes, produces),
+            "parameters" => convert_parameters(value),
+            _ => value,
+        };
+        (key, value)
+    }).collect())
+}
+
+fn convert_operation(operation: Value, consumes: &[String], produces: &[String]) -> Value {
+    let mut operation = match operation {
+        Value::Object(operation) => operation,
+        other => return other,
+    };
+    let consumes = operation.remove("consumes").map(|c| media_types(Some(c))).unwrap_or(consumes.to_vec());
+    let produces = operation.remove("produces").map(|p| media_types(Some(p))).unwrap_or(produces.to_vec());
+    // Operation level schemes have no equivalent, servers would have to be repeated per operation
+    operation.remove("schemes");
+
+    if let Some(Value::Array(parameters)) = operation.remove("parameters") {
+        let (body, others): (Vec<Value>, Vec<Value>) = parameters.into_iter()
+            .partition(|p| p.get("in").and_then(|l| l.as_str()) == Some("body"));
+        let (form, others): (Vec<Value>, Vec<Value>) = others.into_iter()
+            .partition(|p| p.get("in").and_then(|l| l.as_str()) == Some("formData"));
+        if !others.is_empty() {
+            operation.insert("parameters".to_owned(), convert_parameters(Value::Array(others)));
+        }
+        if let Some(body) = body.into_iter().next() {
+            operation.insert("requestBody".to_owned(), convert_body(body, &consumes));
+        } else if !form.is_empty() {
+            operation.insert("requestBody".to_owned(), convert_form(form, &consumes));
+        }
+    }
+    if let Some(Value::Object(responses)) = operation.remove("responses") {
+        let responses = responses.into_iter().map(|(status, r)| (status, convert_response(r, &produces))).collect();
+        operation.insert("responses".to_owned(), Value::Object(responses));
+    }
+    Value::Object(operation)
+}
+
+fn content(schema: Option<Value>, media_types: &[String]) -> Value {
+    Value::Object(media_types.iter().map(|media_type| {
+        let mut media = Map::new();
+        if let Some(ref schema) = schema {
+            media.insert("schema".to_owned(), schema.clone());
+        }
+        (media_type.clone(), Value::Object(media))
+    }).collect())
+}
+
+fn convert_body(body: Value, consumes: &[String]) -> Value {
+    let mut request_body = Map::new();
+    if let Some(description) = body.get("description") {
+        request_body.insert("description".to_owned(), description.clone());
+    }
+    request_body.insert("required".to_owned(), body.get("required").cloned().unwrap_or(json!(false)));
+    let mut content = content(body.get("schema").cloned(), consumes);
+    // Swagger 2.0 has no examples for bodies, the `x-examples` extension fills in
+    if let Some(&Value::Object(ref examples)) = body.get("x-examples") {
+        insert_examples(&mut content, examples.clone());
+    }
+    request_body.insert("content".to_owned(), content);
+    Value::Object(request_body)
+}
+
+/// Moves the examples, keyed by media type, into the media types of `content`
+fn insert_examples(content: &mut Value, examples: Map<String, Value>) {
+    if let Value::Object(ref mut content) = *content {
+        for (media_type, example) in examples {
+            if let Some(&mut Value::Object(ref mut media)) = content.get_mut(&media_type) {
+                media.insert("example".to_owned(), example);
+            }
+        }
+    }
+}
+
+/// `formData` parameters become the properties of a single form schema
+fn convert_form(form: Vec<Value>, consumes: &[String]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for parameter in form {
+        let name = parameter.get("name").and_then(|n| n.as_str()).unwrap_or("").to_owned();
+        if parameter.get("required") == Some(&json!(true)) {
+            required.push(json!(name));
+        }
+        let mut schema = Map::new();
+        for member in PARAMETER_SCHEMA_MEMBERS.iter().chain(["description"].iter()) {
+            if let Some(value) = parameter.get(*member) {
+                schema.insert(member.to_string(), value.clone());
+            }
+        }
+        // Files are binary strings in OpenAPI 3.0
+        if schema.get("type") == Some(&json!("file")) {
+            schema.insert("type".to_owned(), json!("string"));
+            schema.insert("format".to_owned(), json!("binary"));
+        }
+        properties.insert(name, Value::Object(schema));
+    }
+    let media_types: Vec<String> = consumes.iter()
+        .filter(|t| t.starts_with("multipart/") || t.as_str() == "application/x-www-form-urlencoded")
+        .cloned()
+        .collect();
+    let media_types = if media_types.is_empty() { vec!["multipart/form-data".to_owned()] } else { media_types };
+    json!({
+        "required": !required.is_empty(),
+        "content": content(Some(json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })), &media_types),
+    })
+}
+
+fn convert_parameters(parameters: Value) -> Value {
+    match parameters {
+        Value::Array(parameters) => Value::Array(parameters.into_iter().map(convert_parameter).collect()),
+        other => other,
+    }
+}
+
+fn convert_parameter(parameter: Value) -> Value {
+    let mut parameter = match parameter {
+        Value::Object(parameter) => parameter,
+        other => return other,
+    };
+    if parameter.contains_key("$ref") || parameter.contains_key("schema") {
+        return Value::Object(parameter);
+    }
+    let mut schema = Map::new();
+    for member in PARAMETER_SCHEMA_MEMBERS {
+        if let Some(value) = parameter.remove(*member) {
+            schema.insert(member.to_string(), value);
+        }
+    }
+    // `collectionFormat` is replaced by `style` and `explode`
+    if let Some((style, explode)) = schema.remove("collectionFormat").and_then(|f| collection_style(&f)) {
+        parameter.insert("style".to_owned(), json!(style));
+        parameter.insert("explode".to_owned(), json!(explode));
+    }
+    parameter.insert("schema".to_owned(), Value::Object(schema));
+    Value::Object(parameter)
+}
+
+/// The `style` and `explode` of a Swagger 2.0 `collectionFormat`. OData takes lists like
+/// `$select=ID,Code`, which is `csv` or an unexploded form.
+fn collection_style(format: &Value) -> Option<(&'static str, bool)> {
+    match format.as_str() {
+        Some("csv") => Some(("form", false)),
+        Some("ssv") => Some(("spaceDelimited", false)),
+        Some("pipes") => Some(("pipeDelimited", false)),
+        Some("multi") => Some(("form", true)),
+        _ => None,
+    }
+}
+
+fn convert_response(response: Value, produces: &[String]) -> Value {
+    let mut response = match response {
+        Value::Object(response) => response,
+        other => return other,
+    };
+    if response.contains_key("$ref") {
+        return Value::Object(response);
+    }
+    if let Some(schema) = response.remove("schema") {
+        // Files are binary strings in OpenAPI 3.0
+        let schema = if schema.get("type") == Some(&json!("file")) {
+            json!({ "type": "string", "format": "binary" })
+        } else {
+            schema
+        };
+        response.insert("content".to_owned(), content(Some(schema), produces));
+    }
+    if let Some(Value::Object(headers)) = response.remove("headers") {
+        let headers = headers.into_iter().map(|(name, header)| {
+            let mut header = match header {
+                Value::Object(header) => header,
+                other => return (name, other),
+            };
+            let mut schema = Map::new();
+            for member in PARAMETER_SCHEMA_MEMBERS {
+                if let Some(value) = header.remove(*member) {
+                    schema.insert(member.to_string(), value);
+                }
+            }
+            header.insert("schema".to_owned(), Value::Object(schema));
+            (name, Value::Object(header))
+        }).collect();
+        response.insert("headers".to_owned(), Value::Object(headers));
+    }
+    if let Some(Value::Object(examples)) = response.remove("examples") {
+        if let Some(content) = response.get_mut("content") {
+            insert_examples(content, examples);
+        }
+    }
+    Value::Object(response)
+}
+
+fn convert_security_scheme(scheme: Value) -> Value {
+    let mut scheme = match scheme {
+        Value::Object(scheme) => scheme,
+        other => return other,
+    };
+    match scheme.get("type").and_then(|t| t.as_str()).map(|t| t.to_owned()) {
+        Some(ref t) if t == "basic" => {
+            scheme.insert("type".to_owned(), json!("http"));
+            scheme.insert("scheme".to_owned(), json!("basic"));
+        },
+        Some(ref t) if t == "oauth2" => {
+            let flow = scheme.remove("flow").and_then(|f| f.as_str().map(|f| f.to_owned())).unwrap_or_default();
+            let flow_name = match flow.as_str() {
+                "accessCode" => "authorizationCode",
+                "application" => "clientCredentials",
+                "implicit" => "implicit",
+                _ => "password",
+            };
+            let mut flow = Map::new();
+            for member in &["authorizationUrl", "tokenUrl", "refreshUrl"] {
+                if let Some(value) = scheme.remove(*member) {
+                    flow.insert(member.to_string(), value);
+                }
+            }
+            flow.insert("scopes".to_owned(), scheme.remove("scopes").unwrap_or(json!({})));
+            let mut flows = Map::new();
+            flows.insert(flow_name.to_owned(), Value::Object(flow));
+            scheme.insert("flows".to_owned(), Value::Object(flows));
+        },
+        _ => {}
+    }
+    Value::Object(scheme)
+}
+
+#[test]
+fn it_converts_to_openapi3() {
+    let swagger = json!({
+        "swagger": "2.0",
+        "info": { "title": "Exact Online REST API" },
+        "host": "start.exactonline.nl",
+        "basePath": "/",
+        "schemes": ["https"],
+        "consumes": ["application/json"],
+        "produces": ["application/json"],
+        "paths": {
+            "/api/v1/{division}/project/Projects": {
+                "post": {
+                    "parameters": [
+                        { "$ref": "#/parameters/Division" },
+                        { "name": "body", "in": "body", "required": true,
+                          "schema": { "$ref": "#/definitions/ProjectProjectsPost" } },
+                        { "name": "$top", "in": "query", "type": "integer", "format": "int32", "maximum": 60 }
+                    ],
+                    "responses": {
+                        "201": { "description": "Created", "schema": { "$ref": "#/definitions/ProjectProjectsResponse" } }
+                    }
+                }
+            }
+        },
+        "definitions": { "ProjectProjectsPost": { "type": "object", "x-nullable": true } },
+        "parameters": { "Division": { "name": "division", "in": "path", "required": true, "type": "integer" } },
+        "securityDefinitions": { "oauth2": { "type": "oauth2", "flow": "accessCode",
+            "authorizationUrl": "https://start.exactonline.nl/api/oauth2/auth",
+            "tokenUrl": "https://start.exactonline.nl/api/oauth2/token", "scopes": {} } },
+        "security": [{ "oauth2": [] }]
+    });
+    let spec = to_openapi3(&swagger);
+    assert_eq!(spec["openapi"], json!("3.0.0"));
+    assert!(spec.get("swagger").is_none());
+    assert_eq!(spec["servers"], json!([{ "url": "https://start.exactonline.nl" }]));
+    assert_eq!(spec["components"]["schemas"]["ProjectProjectsPost"]["nullable"], json!(true));
+    assert_eq!(spec["components"]["parameters"]["Division"]["schema"], json!({ "type": "integer" }));
+    assert_eq!(spec["components"]["securitySchemes"]["oauth2"]["flows"]["authorizationCode"]["tokenUrl"],
+        json!("https://start.exactonline.nl/api/oauth2/token"));
+    assert_eq!(spec["security"], json!([{ "oauth2": [] }]));
+
+    let post = &spec["paths"]["/api/v1/{division}/project/Projects"]["post"];
+    assert_eq!(post["parameters"], json!([
+        { "$ref": "#/components/parameters/Division" },
+        { "name": "$top", "in": "query", "schema": { "type": "integer", "format": "int32", "maximum": 60 } }
+    ]));
+    assert_eq!(post["requestBody"], json!({
+        "required": true,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/ProjectProjectsPost" } } }
+    }));
+    assert_eq!(post["responses"]["201"]["content"]["application/json"]["schema"],
+        json!({ "$ref": "#/components/schemas/ProjectProjectsResponse" }));
+}
+
+#[test]
+fn it_moves_the_division_into_server_variables() {
+    let mut spec = json!({
+        "openapi": "3.0.0",
+        "servers": [{ "url": "https://start.exactonline.nl" }],
+        "paths": {
+            "/api/v1/{division}/project/Projects": {
+                "parameters": [{ "$ref": "#/components/parameters/Division" }],
+                "get": { "responses": {} }
+            },
+            "/api/v1/current/Me": { "get": { "responses": {} } }
+        }
+    });
+    use_server_variables(&mut spec, "/api/v1");
+    assert_eq!(spec["servers"][0]["url"], json!("https://start.exactonline.{region}/api/v1/{division}"));
+    assert_eq!(spec["servers"][0]["variables"]["region"]["default"], json!("nl"));
+    let projects = &spec["paths"]["/project/Projects"];
+    assert!(projects.get("get").is_some());
+    assert!(projects.get("parameters").is_none());
+    let me = &spec["paths"]["/current/Me"];
+    assert_eq!(me["servers"][0]["url"], json!("https://start.exactonline.{region}/api/v1"));
+}
@@ -0,0 +1,467 @@
+use acquisition::{EndpointDetails, EndpointKind, Property};
+use webhooks::WEBHOOK_TOPICS;
+use openapi::{Contact, License};
+use transform::TypeMapper;
+use errors::*;
+use errors::ErrorKind::InvalidOptions;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Settings that influence how a spec is generated from the scraped endpoints.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SpecOptions {
+    /// Maps an endpoint name to the only properties that should appear in its definitions.
+    /// Endpoints without an entry keep all of their properties.
+    pub property_allowlists: BTreeMap<String, Vec<String>>,
+    /// Generate paths and definitions for the `bulk/` resources
+    pub include_bulk: bool,
+    /// Generate paths and definitions for the `sync/` resources
+    pub include_sync: bool,
+    /// Generate POST operations for OData function imports and actions
+    pub include_functions: bool,
+    /// Generate paths and definitions for resources Exact marks as beta, their operations are
+    /// flagged with `x-preview`
+    pub include_beta: bool,
+    pub format_mapping: FormatMapping,
+    /// Give `Edm.Guid` values the standard `uuid` format, and properties a `pattern` as well,
+    /// so validation layers reject malformed IDs
+    pub guid_pattern: bool,
+    /// Generate the `WebhookSubscriptions` paths when the docs don't list them, and the payloads
+    /// posted to subscribers under `x-webhooks`
+    pub include_webhooks: bool,
+    /// The webhook topics to generate payloads for, the documented ones when empty
+    pub webhook_topics: Vec<String>,
+    pub binary_format: BinaryFormat,
+    pub byte_format: ByteFormat,
+    /// Maps an endpoint name to the maximum number of records it returns per page,
+    /// overriding the size found in the documentation
+    pub page_size_overrides: BTreeMap<String, u32>,
+    /// Replaces the `/api/v1` prefix of the scraped uris, e.g. with `/api/v2` or a beta path.
+    /// The scraped uris are used as-is when this is not set.
+    pub api_base_path: Option<String>,
+    /// Document `ETag` on fetched entities and accept `If-Match` on PUT and DELETE, for
+    /// optimistic concurrency
+    pub concurrency_headers: bool,
+    /// Offer the Atom XML representation besides JSON in `consumes` and `produces`
+    pub include_atom: bool,
+    /// Accept the OData `Prefer` header on POST and PUT, to ask for the entity back
+    pub prefer_header: bool,
+    /// Generate `{resource}/$count` paths answering the number of matching records
+    pub include_count_paths: bool,
+    /// Give each collection GET its own `$select` parameter enumerating the entity's properties,
+    /// instead of the shared free-form one
+    pub typed_select: bool,
+    /// Overrides for the `info` block, which describes this generator by default
+    pub info: InfoOptions,
+    /// The version of the generated document
+    pub spec_version: SpecVersion,
+    /// With OpenAPI 3.0, move the `/api/v1/{division}` prefix of the paths into the server url,
+    /// with variables for the division and the country of the Exact Online site
+    pub server_variables: bool,
+    /// Error statuses to document besides 400, 401, 404 and 500, `[403, 429, 503]` when not set
+    pub error_statuses: Option<Vec<u16>>,
+    /// Describe responses as a plain array or object, for gateways that strip the OData
+    /// `{"d": {"results": ...}}` envelope
+    pub flatten_envelope: bool,
+    /// Generate one `{Endpoint}` definition with the properties of all methods and compose the
+    /// per-method definitions of it with `allOf`, instead of repeating the properties in each
+    pub compose_definitions: bool,
+    /// Add an `x-methods` extension to every property listing the methods it is used with,
+    /// so the per-method views can be reconstructed from a shared schema
+    pub annotate_property_methods: bool,
+    /// Replace every `$ref` by a copy of what it refers to, for tools that can't resolve them
+    pub inline_refs: bool,
+    /// Fail instead of warning when a `$ref` in the generated spec resolves to nothing
+    pub strict_refs: bool,
+    /// Set `additionalProperties: false` on every object definition, envelopes included, so
+    /// validators used for contract testing reject fields the spec doesn't know. Entities in
+    /// responses then declare the `__metadata` OData adds to them.
+    pub closed_schemas: bool,
+    /// Replaces the mapping of Edm types onto OpenAPI types, only settable from code
+    #[serde(skip_deserializing)]
+    pub type_mapper: Option<Arc<TypeMapper>>,
+    /// How the definitions, and the references to them, are named
+    pub definition_naming: DefinitionNaming,
+    /// Names to use instead of scraped endpoint names, like `Me` for `SystemSystemMe`, in
+    /// definition names, operationIds and summaries. Service areas listed are renamed in the tags.
+    pub renames: BTreeMap<String, String>,
+    /// Maps an endpoint name to the properties to leave out of or rename in its definitions
+    pub property_rules: BTreeMap<String, PropertyRules>,
+    /// The `info` of the spec of a single service area, when building a spec per service.
+    /// Parts not given are taken from `info`, the title is then suffixed with the service.
+    pub service_infos: BTreeMap<String, InfoOptions>,
+    /// Media types of single operations, for endpoints exchanging PDFs or other documents rather
+    /// than JSON. Keyed by endpoint name, then by method, like `GET`. Operations producing no
+    /// JSON answer with a file.
+    pub media_types: BTreeMap<String, BTreeMap<String, MediaTypes>>,
+    /// Endpoints exchanging files besides the known attachment endpoints, by name. Their first
+    /// binary property gets a `$value` path to download and upload the raw contents.
+    pub file_endpoints: Vec<String>,
+}
+
+/// Media types replacing those an operation inherits, those not given are inherited
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MediaTypes {
+    pub consumes: Option<Vec<String>>,
+    pub produces: Option<Vec<String>>,
+}
+
+/// Properties of an endpoint to drop, like internal fields, or to name after the domain language
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PropertyRules {
+    /// Properties left out of the definitions. Keys can't be, the keyed paths depend on them.
+    pub exclude: Vec<String>,
+    /// Maps a property name to the name used in the definitions, the original is kept in
+    /// `x-exact-name`. Query options like `$select` keep using the original.
+    pub rename: BTreeMap<String, String>,
+}
+
+/// The parts of the `info` block to replace, parts not given keep their default
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct InfoOptions {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Version of the described API, the version of this generator by default
+    pub version: Option<String>,
+    pub contact: Option<Contact>,
+    pub license: Option<License>,
+}
+
+/// What a definition describes, besides the endpoint it belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefinitionKind {
+    /// The entity with all its properties, used when composing definitions
+    Entity,
+    /// A page of entities, as listed
+    Response,
+    /// A single entity, as fetched by key
+    SingleResponse,
+    /// The body of a POST
+    Post,
+    /// The entity a POST answers with
+    PostResponse,
+    /// The body of a PUT
+    Put,
+    /// The body posted to webhook subscribers
+    WebhookPayload,
+}
+
+impl DefinitionKind {
+    /// The suffix telling the definitions of an endpoint apart, as named by default
+    pub fn default_suffix(&self) -> &'static str {
+        match *self {
+            DefinitionKind::Entity => "",
+            DefinitionKind::Response => "Response",
+            DefinitionKind::SingleResponse => "SingleResponse",
+            DefinitionKind::Post => "Post",
+            DefinitionKind::PostResponse => "PostResponse",
+            DefinitionKind::Put => "Put",
+            DefinitionKind::WebhookPayload => "WebhookPayload",
+        }
+    }
+}
+
+/// The casing definition names are converted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameCasing {
+    /// As composed, which is UpperCamelCase for the default template
+    Unchanged,
+    /// lowerCamelCase
+    Camel,
+    /// snake_case
+    Snake,
+}
+
+impl Default for NameCasing {
+    fn default() -> NameCasing {
+        NameCasing::Unchanged
+    }
+}
+
+/// How definitions are named after their endpoint, like `ProjectProjectsResponse`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct DefinitionNaming {
+    /// The name with an `{endpoint}` and a `{kind}` placeholder, `{endpoint}{kind}` by default
+    pub template: String,
+    /// Replacements of the default `{kind}` suffixes
+    pub suffixes: BTreeMap<DefinitionKind, String>,
+    /// Put before every name, including `Error`
+    pub prefix: String,
+    /// Put after every name, including `Error`
+    pub suffix: String,
+    pub casing: NameCasing,
+}
+
+impl Default for DefinitionNaming {
+    fn default() -> DefinitionNaming {
+        DefinitionNaming {
+            template: "{endpoint}{kind}".to_owned(),
+            suffixes: BTreeMap::new(),
+            prefix: String::new(),
+            suffix: String::new(),
+            casing: NameCasing::default(),
+        }
+    }
+}
+
+impl DefinitionNaming {
+    /// The name of the definition of the given kind for the endpoint
+    pub fn name(&self, endpoint: &str, kind: DefinitionKind) -> String {
+        let kind_suffix = self.suffixes.get(&kind).map_or(kind.default_suffix(), |s| s.as_str());
+        let name = format!("{}{}{}", self.prefix,
+            self.template.replace("{endpoint}", endpoint).replace("{kind}", kind_suffix), self.suffix);
+        match self.casing {
+            NameCasing::Unchanged => name,
+            NameCasing::Camel => {
+                let mut chars = name.chars();
+                chars.next().map(|first| first.to_lowercase().chain(chars).collect()).unwrap_or_default()
+            },
+            NameCasing::Snake => {
+                // A run of capitals is a single word, like the `CRM` of `CRMAccounts`
+                let chars: Vec<char> = name.chars().collect();
+                let mut snake = String::new();
+                for (i, &c) in chars.iter().enumerate() {
+                    let previous = if i > 0 { chars[i - 1] } else { '_' };
+                    let next = chars.get(i + 1).cloned().unwrap_or('_');
+                    if c.is_uppercase() && previous != '_' && (!previous.is_uppercase() || next.is_lowercase()) {
+                        snake.push('_');
+                    }
+                    snake.extend(c.to_lowercase());
+                }
+                snake
+            },
+        }
+    }
+
+    /// The name of the shared error definition
+    pub fn error(&self) -> String {
+        self.name("Error", DefinitionKind::Entity)
+    }
+
+    /// Fails on a template without both placeholders, which would give the definitions of different
+    /// endpoints or kinds the same name
+    pub fn check(&self) -> Result<()> {
+        for placeholder in &["{endpoint}", "{kind}"] {
+            if !self.template.contains(placeholder) {
+                bail!(InvalidOptions(format!("definition naming template '{}' lacks {}", self.template, placeholder)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which OpenAPI version `build_document` produces
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum SpecVersion {
+    /// Swagger 2.0
+    #[serde(rename = "2.0")]
+    Swagger2,
+    /// OpenAPI 3.0
+    #[serde(rename = "3.0")]
+    OpenApi3,
+}
+
+impl Default for SpecVersion {
+    fn default() -> SpecVersion {
+        SpecVersion::Swagger2
+    }
+}
+
+/// Which `format`s the Edm types are mapped to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatMapping {
+    /// Formats named after the Edm types, like `edm-datetime` and `guid`, following
+    /// `binary_format` and `byte_format`
+    Edm,
+    /// Only formats defined by OpenAPI or JSON Schema, like `uuid` and `date-time`.
+    /// `Edm.Decimal` becomes a `string` with a `pattern`, the original types are kept in `x-edm-type`.
+    Standard,
+}
+
+impl Default for FormatMapping {
+    fn default() -> FormatMapping {
+        FormatMapping::Edm
+    }
+}
+
+/// How `Edm.Binary` properties are represented
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryFormat {
+    /// `string` with the custom `edm-binary` format
+    Edm,
+    /// `string` with the standard base64 `byte` format
+    Base64,
+}
+
+impl Default for BinaryFormat {
+    fn default() -> BinaryFormat {
+        BinaryFormat::Edm
+    }
+}
+
+/// How `Edm.Byte` and `Edm.SByte` properties are represented
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ByteFormat {
+    /// `string` with the custom `edm-byte` format for `Edm.Byte`, `integer` with `edm-int8` for `Edm.SByte`
+    Edm,
+    /// `integer` restricted to the range of the type
+    Integer,
+}
+
+impl Default for ByteFormat {
+    fn default() -> ByteFormat {
+        ByteFormat::Edm
+    }
+}
+
+impl SpecOptions {
+    pub fn includes_endpoint(&self, endpoint: &EndpointDetails) -> bool {
+        if endpoint.beta && !self.include_beta {
+            return false;
+        }
+        match endpoint.kind {
+            EndpointKind::Standard => true,
+            EndpointKind::Bulk => self.include_bulk,
+            EndpointKind::Sync => self.include_sync,
+            EndpointKind::Function => self.include_functions,
+        }
+    }
+
+    /// The path of `uri` under the configured api base path
+    pub fn rebase_uri(&self, uri: &str) -> String {
+        let base = match self.api_base_path {
+            Some(ref base) => base.trim_right_matches('/'),
+            None => return uri.to_owned(),
+        };
+        // Strip the leading `/api/<version>`
+        let segments: Vec<&str> = uri.trim_left_matches('/').splitn(3, '/').collect();
+        if segments.len() == 3 && segments[0] == "api" {
+            format!("{}/{}", base, segments[2])
+        } else {
+            uri.to_owned()
+        }
+    }
+
+    pub fn webhook_topics(&self) -> Vec<String> {
+        if self.webhook_topics.is_empty() {
+            WEBHOOK_TOPICS.iter().map(|t| t.to_string()).collect()
+        } else {
+            self.webhook_topics.clone()
+        }
+    }
+
+    /// The error statuses documented besides the ones every operation has
+    pub fn extra_error_statuses(&self) -> Vec<u16> {
+        self.error_statuses.clone().unwrap_or_else(|| vec![403, 429, 503])
+    }
+
+    pub fn max_page_size(&self, endpoint: &EndpointDetails) -> u32 {
+        self.page_size_overrides.get(&endpoint.name).cloned().unwrap_or(endpoint.max_page_size)
+    }
+
+    /// The name given in `renames`, or the name itself
+    pub fn renamed(&self, name: &str) -> String {
+        self.renames.get(name).cloned().unwrap_or_else(|| name.to_owned())
+    }
+
+    /// The name of the endpoint as used in the spec
+    pub fn endpoint_name(&self, endpoint: &EndpointDetails) -> String {
+        self.renamed(&endpoint.name)
+    }
+
+    /// The name of the definition of the given kind for the endpoint, named as in `renames`
+    pub fn definition_name(&self, endpoint: &str, kind: DefinitionKind) -> String {
+        self.definition_naming.name(&self.renamed(endpoint), kind)
+    }
+
+    /// A reference to the definition of the given kind for the endpoint
+    pub fn definition_ref(&self, endpoint: &str, kind: DefinitionKind) -> String {
+        format!("#/definitions/{}", self.definition_name(endpoint, kind))
+    }
+
+    /// The property holding the file contents the endpoint exchanges, if it does
+    pub fn file_property<'a>(&self, endpoint: &'a EndpointDetails) -> Option<&'a Property> {
+        if self.file_endpoints.contains(&endpoint.name) {
+            endpoint.binary_property()
+        } else {
+            endpoint.file_property()
+        }
+    }
+
+    pub fn includes_property(&self, endpoint: &EndpointDetails, property: &Property) -> bool {
+        // Keys are always kept, the keyed paths depend on them
+        if property.key {
+            return true;
+        }
+        let allowed = self.property_allowlists.get(&endpoint.name)
+            .map_or(true, |allowed| allowed.contains(&property.name));
+        let excluded = self.property_rules.get(&endpoint.name)
+            .map_or(false, |rules| rules.exclude.contains(&property.name));
+        allowed && !excluded
+    }
+
+    /// The name of the property as used in the definitions of the endpoint
+    pub fn property_name(&self, endpoint: &EndpointDetails, property: &Property) -> String {
+        self.property_rules.get(&endpoint.name)
+            .and_then(|rules| rules.rename.get(&property.name))
+            .cloned()
+            .unwrap_or_else(|| property.name.clone())
+    }
+}
+
+#[test]
+fn it_rebases_uris() {
+    let mut options = SpecOptions::default();
+    assert_eq!(options.rebase_uri("/api/v1/{division}/crm/Accounts"), "/api/v1/{division}/crm/Accounts");
+    options.api_base_path = Some("/api/v2/".to_owned());
+    assert_eq!(options.rebase_uri("/api/v1/{division}/crm/Accounts"), "/api/v2/{division}/crm/Accounts");
+    assert_eq!(options.rebase_uri("/other/Thing"), "/other/Thing");
+}
+
+#[test]
+fn it_names_definitions() {
+    let mut naming = DefinitionNaming::default();
+    assert_eq!(naming.name("ProjectProjects", DefinitionKind::Response), "ProjectProjectsResponse");
+    assert_eq!(naming.name("ProjectProjects", DefinitionKind::Entity), "ProjectProjects");
+    assert_eq!(naming.error(), "Error");
+
+    naming.prefix = "Exact".to_owned();
+    naming.suffixes.insert(DefinitionKind::Post, "Create".to_owned());
+    assert_eq!(naming.name("ProjectProjects", DefinitionKind::Post), "ExactProjectProjectsCreate");
+    assert_eq!(naming.error(), "ExactError");
+
+    naming.template = "{kind}{endpoint}".to_owned();
+    naming.casing = NameCasing::Snake;
+    assert_eq!(naming.name("ProjectProjects", DefinitionKind::Put), "exact_put_project_projects");
+    naming.casing = NameCasing::Camel;
+    assert_eq!(naming.name("ProjectProjects", DefinitionKind::Put), "exactPutProjectProjects");
+
+    let naming = DefinitionNaming { casing: NameCasing::Snake, ..DefinitionNaming::default() };
+    assert_eq!(naming.name("CRMAccounts", DefinitionKind::Entity), "crm_accounts");
+    assert_eq!(naming.name("ProjectWBS", DefinitionKind::Response), "project_wbs_response");
+    assert_eq!(naming.name("ProjectWBS", DefinitionKind::Entity), "project_wbs");
+    assert_eq!(naming.name("Project_WBS", DefinitionKind::Entity), "project_wbs");
+}
+
+#[test]
+fn it_rejects_definition_naming_without_placeholders() {
+    assert!(DefinitionNaming::default().check().is_ok());
+    for template in &["{endpoint}", "Exact{kind}", "Definition"] {
+        let naming = DefinitionNaming { template: template.to_string(), ..DefinitionNaming::default() };
+        match naming.check() {
+            Err(Error(InvalidOptions(_), _)) => {},
+            other => panic!("{} is accepted: {:?}", template, other),
+        }
+    }
+}
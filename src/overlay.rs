@@ -0,0 +1,146 @@
+//! Hand-maintained corrections laid over the generated spec, as overlays or JSON Merge Patches
+
+use serde_json::{Map, Value};
+
+/// Lays the partial spec `overlay` over `spec`, so corrections survive regeneration.
+///
+/// Objects are merged member by member and the overlay wins everywhere else: its strings, numbers
+/// and arrays replace those of the spec. The exception are `parameters`, which are matched by
+/// `name` and `in`, or by `$ref`, and merged one by one, so a parameter can be corrected without
+/// repeating the others. Parts of the overlay missing from the spec are added. Overlays applied
+/// after each other win over the earlier ones.
+pub fn apply_overlay(spec: &mut Value, overlay: &Value) {
+    overlay_value(spec, overlay, None);
+}
+
+fn overlay_value(target: &mut Value, overlay: &Value, key: Option<&str>) {
+    if let (&mut Value::Object(ref mut target), &Value::Object(ref overlay)) = (&mut *target, overlay) {
+        return overlay_object(target, overlay);
+    }
+    if key == Some("parameters") {
+        if let (&mut Value::Array(ref mut target), &Value::Array(ref overlay)) = (&mut *target, overlay) {
+            return overlay_parameters(target, overlay);
+        }
+    }
+    *target = overlay.clone();
+}
+
+fn overlay_object(target: &mut Map<String, Value>, overlay: &Map<String, Value>) {
+    for (key, value) in overlay {
+        let merged = match target.get_mut(key) {
+            Some(existing) => {
+                overlay_value(existing, value, Some(key));
+                true
+            },
+            None => false,
+        };
+        if !merged {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn overlay_parameters(target: &mut Vec<Value>, overlay: &[Value]) {
+    for parameter in overlay {
+        let identity = parameter_identity(parameter);
+        let existing = target.iter().position(|p| identity.is_some() && parameter_identity(p) == identity);
+        match existing {
+            Some(i) => overlay_value(&mut target[i], parameter, None),
+            None => target.push(parameter.clone()),
+        }
+    }
+}
+
+/// Applies a JSON Merge Patch (RFC 7386): objects are merged, `null` removes a member and
+/// anything else replaces the target
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match *patch {
+        Value::Object(ref patch) => patch,
+        _ => {
+            *target = patch.clone();
+            return;
+        },
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(ref mut target) = *target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// What identifies a parameter within an operation
+fn parameter_identity(parameter: &Value) -> Option<(String, String)> {
+    if let Some(target) = parameter.get("$ref").and_then(|r| r.as_str()) {
+        return Some(("$ref".to_owned(), target.to_owned()));
+    }
+    match (parameter.get("name").and_then(|n| n.as_str()), parameter.get("in").and_then(|i| i.as_str())) {
+        (Some(name), Some(location)) => Some((name.to_owned(), location.to_owned())),
+        _ => None,
+    }
+}
+
+#[test]
+fn it_applies_overlays() {
+    let mut spec = json!({
+        "paths": { "/a": { "get": {
+            "tags": ["A", "B"],
+            "parameters": [
+                { "$ref": "#/parameters/filter" },
+                { "name": "ID", "in": "path", "type": "string", "format": "guid" }
+            ]
+        } } },
+        "definitions": { "A": { "properties": { "Code": { "type": "string" } } } }
+    });
+    apply_overlay(&mut spec, &json!({
+        "paths": { "/a": { "get": {
+            "tags": ["C"],
+            "parameters": [
+                { "name": "ID", "in": "path", "description": "Key of A" },
+                { "name": "top", "in": "query", "type": "integer" }
+            ]
+        } } },
+        "definitions": { "A": { "properties": { "Code": { "type": "integer", "description": "Fixed" } } } }
+    }));
+    assert_eq!(spec, json!({
+        "paths": { "/a": { "get": {
+            "tags": ["C"],
+            "parameters": [
+                { "$ref": "#/parameters/filter" },
+                { "name": "ID", "in": "path", "type": "string", "format": "guid", "description": "Key of A" },
+                { "name": "top", "in": "query", "type": "integer" }
+            ]
+        } } },
+        "definitions": { "A": { "properties": { "Code": { "type": "integer", "description": "Fixed" } } } }
+    }));
+}
+
+#[test]
+fn it_applies_merge_patches() {
+    let mut spec = json!({
+        "info": { "title": "Exact Online REST API", "contact": { "name": "Daan" } },
+        "tags": [{ "name": "A" }],
+        "x-webhooks": { "Accounts": {} }
+    });
+    merge_patch(&mut spec, &json!({
+        "info": { "title": "Our API", "contact": null },
+        "tags": [{ "name": "B" }],
+        "x-webhooks": null,
+        "host": "start.exactonline.be"
+    }));
+    assert_eq!(spec, json!({
+        "info": { "title": "Our API" },
+        "tags": [{ "name": "B" }],
+        "host": "start.exactonline.be"
+    }));
+
+    let mut value = json!("a");
+    merge_patch(&mut value, &json!({ "b": { "c": null, "d": 1 } }));
+    assert_eq!(value, json!({ "b": { "d": 1 } }));
+}
@@ -0,0 +1,70 @@
+use select::document::Document;
+use select::predicate::Name;
+
+/// The API call limits Exact documents, per app and division
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RateLimits {
+    pub per_minute: Option<u32>,
+    pub per_day: Option<u32>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Period {
+    Minute,
+    Day,
+}
+
+fn period_of(word: &str) -> Option<Period> {
+    if word.starts_with("minute") || word == "min" {
+        Some(Period::Minute)
+    } else if word.starts_with("day") || word == "daily" {
+        Some(Period::Day)
+    } else {
+        None
+    }
+}
+
+/// Finds limits in phrases like "60 calls per minute" or "a daily limit of 5,000 calls"
+pub fn parse_rate_limits(text: &str) -> RateLimits {
+    let mut limits = RateLimits::default();
+    for sentence in text.split(". ") {
+        parse_sentence(sentence, &mut limits);
+    }
+    limits
+}
+
+fn parse_sentence(sentence: &str, limits: &mut RateLimits) {
+    let words: Vec<String> = sentence.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .collect();
+    for (i, word) in words.iter().enumerate() {
+        let limit = match word.replace(',', "").replace('.', "").parse::<u32>() {
+            Ok(limit) => limit,
+            Err(_) => continue,
+        };
+        // The period usually follows the number, but may precede it
+        let period = words.iter().skip(i + 1).take(4).filter_map(|w| period_of(w)).next()
+            .or_else(|| words[i.saturating_sub(3)..i].iter().filter_map(|w| period_of(w)).next());
+        match period {
+            Some(Period::Minute) if limits.per_minute.is_none() => limits.per_minute = Some(limit),
+            Some(Period::Day) if limits.per_day.is_none() => limits.per_day = Some(limit),
+            _ => {}
+        }
+    }
+}
+
+/// The limits stated anywhere in the body of the page
+pub fn parse_rate_limits_page(document: &Document) -> RateLimits {
+    document.find(Name("body")).next()
+        .map(|body| parse_rate_limits(&body.text()))
+        .unwrap_or_default()
+}
+
+#[test]
+fn it_parses_rate_limits() {
+    assert_eq!(parse_rate_limits("You can make 60 calls per minute and 5,000 calls per day."),
+        RateLimits { per_minute: Some(60), per_day: Some(5000) });
+    assert_eq!(parse_rate_limits("There is a daily limit of 5000 API calls. The minutely limit is 60."),
+        RateLimits { per_minute: Some(60), per_day: Some(5000) });
+    assert_eq!(parse_rate_limits("Version 2 of the API"), RateLimits::default());
+}
@@ -0,0 +1,100 @@
+use select::document::Document;
+use select::predicate::{Name, Or};
+
+/// What a release note says happened
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Deprecated,
+    Changed,
+}
+
+/// A single entry of the REST API release notes
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReleaseNote {
+    /// The heading the entry is listed under, e.g. `Release 290`
+    pub release: String,
+    pub change: Change,
+    /// The endpoint the entry mentions, as written in the notes
+    pub endpoint: Option<String>,
+    /// The property the entry mentions
+    pub property: Option<String>,
+    pub text: String,
+}
+
+impl ReleaseNote {
+    /// Interprets entries like "Added property Blocked to endpoint crm/Accounts"
+    pub fn from_text(release: &str, text: &str) -> ReleaseNote {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let words: Vec<String> = text.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '/' && c != '_').to_owned())
+            .collect();
+        let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+        let mentions = |markers: &[&str]| lower.iter().any(|w| markers.contains(&w.as_str()));
+
+        let change = if mentions(&["removed", "deleted"]) {
+            Change::Removed
+        } else if mentions(&["deprecated", "obsolete"]) {
+            Change::Deprecated
+        } else if mentions(&["added", "new"]) {
+            Change::Added
+        } else {
+            Change::Changed
+        };
+        let endpoint = word_after(&words, &lower, &["endpoint", "resource"])
+            .or_else(|| words.iter().find(|w| w.contains('/') && w.len() > 1).cloned());
+        let property = word_after(&words, &lower, &["property", "field"]);
+        ReleaseNote {
+            release: release.to_owned(),
+            change: change,
+            endpoint: endpoint,
+            property: property,
+            text: text,
+        }
+    }
+}
+
+/// The word following the first of `markers`
+fn word_after(words: &[String], lower: &[String], markers: &[&str]) -> Option<String> {
+    lower.iter().position(|w| markers.contains(&w.as_str()))
+        .and_then(|i| words.get(i + 1))
+        .and_then(|w| if w.is_empty() { None } else { Some(w.clone()) })
+}
+
+/// Collects the list items of the release notes page, grouped by the heading above them
+pub fn parse_release_notes(document: &Document) -> Vec<ReleaseNote> {
+    let mut release = String::new();
+    let mut notes = Vec::new();
+    for node in document.find(Or(Or(Name("h2"), Name("h3")), Name("li"))) {
+        let text = node.text();
+        if node.name() == Some("li") {
+            if !release.is_empty() && !text.trim().is_empty() {
+                notes.push(ReleaseNote::from_text(&release, &text));
+            }
+        } else {
+            release = text.trim().to_owned();
+        }
+    }
+    notes
+}
+
+#[test]
+fn it_interprets_release_notes() {
+    let note = ReleaseNote::from_text("Release 290", "Added property `Blocked` to endpoint crm/Accounts.");
+    assert_eq!(note.change, Change::Added);
+    assert_eq!(note.endpoint, Some("crm/Accounts".to_owned()));
+    assert_eq!(note.property, Some("Blocked".to_owned()));
+
+    let note = ReleaseNote::from_text("Release 290", "Removed resource ProjectTimeTransactions");
+    assert_eq!(note.change, Change::Removed);
+    assert_eq!(note.endpoint, Some("ProjectTimeTransactions".to_owned()));
+    assert_eq!(note.property, None);
+
+    let note = ReleaseNote::from_text("Release 291", "Field Description of logistics/Items\n  now allows 100 characters");
+    assert_eq!(note.change, Change::Changed);
+    assert_eq!(note.endpoint, Some("logistics/Items".to_owned()));
+    assert_eq!(note.property, Some("Description".to_owned()));
+    assert_eq!(note.text, "Field Description of logistics/Items now allows 100 characters");
+}
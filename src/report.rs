@@ -0,0 +1,224 @@
+use acquisition::{EndpointDetails, is_expected_uri};
+use annotations::pointer;
+use lint::{LintFinding, Severity};
+use model_cache::ModelCache;
+use validation::Violation;
+use reqwest::Url;
+use serde_json::Value;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Something suspicious encountered while generating, which did not stop the run
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Warning {
+    /// The documentation page could not be fetched or parsed
+    FailedEndpoint { url: String, error: String },
+    /// No property of the endpoint is marked as key
+    KeylessEndpoint { endpoint: String },
+    /// The property has no description in the docs
+    MissingDescription { endpoint: String, property: String },
+    /// A row of the reference table could not be parsed
+    FailedProperty { endpoint: String, error: String },
+    /// The docs list a method this generator does not know
+    UnknownMethod { endpoint: String, method: String },
+    /// The service uri doesn't look like `/api/v1/{division}/...`
+    UnexpectedUri { endpoint: String, uri: String },
+    /// `$ref`s in a generated spec point at nothing, `locations` are the pointers to the `$ref`s
+    DanglingRef { output: String, reference: String, locations: Vec<String> },
+    /// A generated spec breaks a rule of the specification
+    InvalidSpec { output: String, location: String, message: String },
+    /// A generated spec breaks one of the style rules
+    Lint { output: String, finding: LintFinding },
+    /// Details in the model cache could not be used
+    ModelCache { message: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::FailedEndpoint { ref url, ref error } =>
+                write!(f, "{} failed with: {}", url, error),
+            Warning::KeylessEndpoint { ref endpoint } =>
+                write!(f, "{} has no primary key", endpoint),
+            Warning::MissingDescription { ref endpoint, ref property } =>
+                write!(f, "{}>{} has no description", endpoint, property),
+            Warning::FailedProperty { ref endpoint, ref error } =>
+                write!(f, "in {} failed with: {}", endpoint, error),
+            Warning::UnknownMethod { ref endpoint, ref method } =>
+                write!(f, "{} lists unrecognized method {}", endpoint, method),
+            Warning::UnexpectedUri { ref endpoint, ref uri } =>
+                write!(f, "{} has unexpected uri {}", endpoint, uri),
+            Warning::DanglingRef { ref output, ref reference, ref locations } =>
+                write!(f, "{} refers to missing {} at {}", output, reference, locations.join(", ")),
+            Warning::InvalidSpec { ref output, ref location, ref message } =>
+                write!(f, "{} is invalid at {}: {}", output, location, message),
+            Warning::Lint { ref output, ref finding } =>
+                write!(f, "{} breaks {}", output, finding),
+            Warning::ModelCache { ref message } =>
+                write!(f, "model cache: {}", message),
+        }
+    }
+}
+
+/// Summary of a generator run, written as JSON for automation to pick up
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct GenerationReport {
+    /// Number of endpoints crawled successfully
+    pub endpoints: usize,
+    /// Files written
+    pub outputs: Vec<String>,
+    pub warnings: Vec<Warning>,
+}
+
+impl GenerationReport {
+    pub fn new() -> GenerationReport {
+        GenerationReport::default()
+    }
+
+    pub fn failed_endpoint(&mut self, url: &Url, error: &::errors::Error) {
+        self.warnings.push(Warning::FailedEndpoint { url: url.to_string(), error: error.to_string() });
+    }
+
+    /// Records the problems found in the scraped details of an endpoint
+    pub fn inspect_endpoint(&mut self, endpoint: &EndpointDetails) {
+        self.endpoints += 1;
+        if !is_expected_uri(&endpoint.uri) {
+            self.warnings.push(Warning::UnexpectedUri { endpoint: endpoint.name.clone(), uri: endpoint.uri.clone() });
+        }
+        if endpoint.keys().is_empty() {
+            self.warnings.push(Warning::KeylessEndpoint { endpoint: endpoint.name.clone() });
+        }
+        for p in endpoint.properties.iter().filter(|p| p.description.is_none()) {
+            self.warnings.push(Warning::MissingDescription {
+                endpoint: endpoint.name.clone(),
+                property: p.name.clone(),
+            });
+        }
+        for e in &endpoint.failed_properties {
+            self.warnings.push(Warning::FailedProperty { endpoint: endpoint.name.clone(), error: e.to_string() });
+        }
+        for m in &endpoint.unknown_methods {
+            self.warnings.push(Warning::UnknownMethod { endpoint: endpoint.name.clone(), method: m.clone() });
+        }
+    }
+
+    /// Records why details in the model cache could not be used
+    pub fn inspect_cache(&mut self, cache: &ModelCache) {
+        for message in cache.warnings() {
+            self.warnings.push(Warning::ModelCache { message: message.clone() });
+        }
+    }
+
+    /// Records a written spec and the local references in it that resolve to nothing
+    pub fn inspect_output(&mut self, output: &str, spec: &Value) {
+        self.outputs.push(output.to_owned());
+        for (reference, locations) in find_dangling_refs(spec) {
+            self.warnings.push(Warning::DanglingRef {
+                output: output.to_owned(),
+                reference: reference,
+                locations: locations,
+            });
+        }
+    }
+
+    /// Records the style rules the spec breaks, answering whether any of them is an error
+    pub fn lint_output(&mut self, output: &str, findings: Vec<LintFinding>) -> bool {
+        let failed = findings.iter().any(|f| f.severity == Severity::Error);
+        for finding in findings {
+            self.warnings.push(Warning::Lint { output: output.to_owned(), finding: finding });
+        }
+        failed
+    }
+
+    /// Records the violations of a spec that was not written because of them
+    pub fn invalid_output(&mut self, output: &str, violations: Vec<Violation>) {
+        for violation in violations {
+            self.warnings.push(Warning::InvalidSpec {
+                output: output.to_owned(),
+                location: violation.location,
+                message: violation.message,
+            });
+        }
+    }
+}
+
+/// The local references in `spec` that resolve to nothing, by target with the pointers to the
+/// `$ref`s referring to it, as a misspelled target tends to be used all over the spec
+pub fn find_dangling_refs(spec: &Value) -> BTreeMap<String, Vec<String>> {
+    let mut dangling = BTreeMap::new();
+    find_dangling(spec, spec, &mut Vec::new(), &mut dangling);
+    dangling
+}
+
+fn find_dangling(spec: &Value, value: &Value, location: &mut Vec<String>, dangling: &mut BTreeMap<String, Vec<String>>) {
+    match *value {
+        Value::Object(ref members) => {
+            for (key, member) in members {
+                location.push(key.clone());
+                match *member {
+                    Value::String(ref target) if key == "$ref" => {
+                        let resolves = target.starts_with("#/") && spec.pointer(&target[1..]).is_some();
+                        if !resolves {
+                            dangling.entry(target.clone()).or_insert_with(Vec::new).push(pointer(location));
+                        }
+                    },
+                    _ => find_dangling(spec, member, location, dangling),
+                }
+                location.pop();
+            }
+        },
+        Value::Array(ref items) => {
+            for (i, item) in items.iter().enumerate() {
+                location.push(i.to_string());
+                find_dangling(spec, item, location, dangling);
+                location.pop();
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Collects the targets of all `$ref`s in `value`
+pub fn collect_refs(value: &Value, references: &mut Vec<String>) {
+    match *value {
+        Value::Object(ref members) => {
+            for (key, member) in members {
+                match *member {
+                    Value::String(ref target) if key == "$ref" => references.push(target.clone()),
+                    _ => collect_refs(member, references),
+                }
+            }
+        },
+        Value::Array(ref items) => {
+            for item in items {
+                collect_refs(item, references);
+            }
+        },
+        _ => {},
+    }
+}
+
+#[test]
+fn it_reports_dangling_refs() {
+    let spec = json!({
+        "definitions": { "Error": { "type": "object" } },
+        "paths": { "/a": { "get": { "responses": {
+            "200": { "schema": { "$ref": "#/definitions/AResponse" } },
+            "400": { "schema": { "$ref": "#/definitions/Error" } }
+        } }, "post": { "responses": {
+            "201": { "schema": { "$ref": "#/definitions/AResponse" } }
+        } } } }
+    });
+    let mut report = GenerationReport::new();
+    report.inspect_output("api.json", &spec);
+    assert_eq!(report.warnings, vec![Warning::DanglingRef {
+        output: "api.json".to_owned(),
+        reference: "#/definitions/AResponse".to_owned(),
+        locations: vec![
+            "/paths/~1a/get/responses/200/schema/$ref".to_owned(),
+            "/paths/~1a/post/responses/201/schema/$ref".to_owned(),
+        ],
+    }]);
+}
@@ -0,0 +1,111 @@
+use errors::*;
+
+use std::fs::File;
+use std::path::Path;
+use serde_json;
+
+/// Where to find things in the documentation HTML.
+/// The defaults match the current layout of the Exact Online docs; a selector file lets users
+/// patch scraping when the layout changes without waiting for a release.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Selectors {
+    /// Prefix of the `href` of links from the overview to the detail pages
+    pub detail_link_prefix: String,
+    /// Prefix of the `href` of links to further pages or sections of the overview
+    pub overview_continuation_prefix: String,
+    /// `id` of the element holding the endpoint name
+    pub endpoint_id: String,
+    /// `id` of the element holding the service uri
+    pub service_uri_id: String,
+    /// `id` of the introductory paragraph describing the endpoint
+    pub description_id: String,
+    /// `id` of the element listing the Exact Online packages the endpoint requires
+    pub packages_id: String,
+    /// `id` of the element listing the OAuth scopes the endpoint requires
+    pub scope_id: String,
+    /// Class of the elements holding remarks, which apply to the methods they mention
+    pub remarks_class: String,
+    /// Class of the elements holding sample JSON payloads
+    pub example_class: String,
+    /// Attribute of a sample naming the method it is sent or returned with, GET when missing
+    pub example_method_attr: String,
+    /// Class of the badge marking a resource as beta
+    pub beta_class: String,
+    /// Class of the notice marking a resource as deprecated, which may name its replacement
+    pub deprecated_class: String,
+    /// `id` of the table listing the properties
+    pub reference_table_id: String,
+    /// `name` of the inputs listing the supported methods
+    pub supported_methods_name: String,
+    /// `name` of the inputs listing the supported OData query options
+    pub query_options_name: String,
+    /// Attribute of a property input holding its Edm type
+    pub property_type_attr: String,
+    /// Attribute of a property input that is `True` for keys
+    pub property_key_attr: String,
+    /// Attribute of a property input holding its default value
+    pub property_default_attr: String,
+    /// Attribute of a property input that is `True` when the value may be null
+    pub property_nullable_attr: String,
+    /// Attribute of a property input that is `True` when it must be given on POST
+    pub property_mandatory_attr: String,
+    /// Attribute of a property input holding the maximum length of strings
+    pub property_max_length_attr: String,
+    /// Attributes of a property input holding the total number of digits of numbers, and
+    /// how many of those follow the decimal point
+    pub property_precision_attr: String,
+    pub property_scale_attr: String,
+    /// Attribute of a property input that is `True` for navigation properties, whose type
+    /// names the entity set they refer to
+    pub property_navigation_attr: String,
+    /// Classes marking a property as available for a method
+    pub get_class: String,
+    pub post_class: String,
+    pub put_class: String,
+    pub delete_class: String,
+}
+
+impl Default for Selectors {
+    fn default() -> Selectors {
+        Selectors {
+            detail_link_prefix: "HlpRestAPIResourcesDetails.aspx".to_owned(),
+            overview_continuation_prefix: "HlpRestAPIResources.aspx?".to_owned(),
+            endpoint_id: "endpoint".to_owned(),
+            service_uri_id: "serviceUri".to_owned(),
+            description_id: "description".to_owned(),
+            packages_id: "packages".to_owned(),
+            scope_id: "scopes".to_owned(),
+            remarks_class: "remarks".to_owned(),
+            example_class: "example".to_owned(),
+            example_method_attr: "data-method".to_owned(),
+            beta_class: "beta".to_owned(),
+            deprecated_class: "deprecated".to_owned(),
+            reference_table_id: "referencetable".to_owned(),
+            supported_methods_name: "supportedmethods".to_owned(),
+            query_options_name: "queryoptions".to_owned(),
+            property_type_attr: "data-type".to_owned(),
+            property_key_attr: "data-key".to_owned(),
+            property_default_attr: "data-default".to_owned(),
+            property_nullable_attr: "data-nullable".to_owned(),
+            property_mandatory_attr: "data-mandatory".to_owned(),
+            property_max_length_attr: "data-maxlength".to_owned(),
+            property_precision_attr: "data-precision".to_owned(),
+            property_scale_attr: "data-scale".to_owned(),
+            property_navigation_attr: "data-navigation".to_owned(),
+            get_class: "showget".to_owned(),
+            post_class: "showpost".to_owned(),
+            put_class: "showput".to_owned(),
+            delete_class: "showdelete".to_owned(),
+        }
+    }
+}
+
+impl Selectors {
+    /// Reads selectors from a JSON file, selectors missing from the file keep their default
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Selectors> {
+        let file = File::open(path.as_ref())?;
+        serde_json::from_reader(file)
+            .chain_err(|| format!("While reading selectors {}", path.as_ref().display()))
+    }
+}
@@ -0,0 +1,56 @@
+//! Serialization of the foreign types held by the scraped model, for `#[serde(with = "...")]`
+
+/// `Url`s as strings
+pub mod url_string {
+    use reqwest::Url;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(url: &Url, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(url.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Url, D::Error> {
+        let url = String::deserialize(deserializer)?;
+        Url::parse(&url).map_err(D::Error::custom)
+    }
+}
+
+/// Lists of HTTP methods as their names, e.g. `["GET", "POST"]`
+pub mod methods {
+    use reqwest::Method;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(methods: &Vec<Method>, serializer: S) -> Result<S::Ok, S::Error> {
+        let names: Vec<String> = methods.iter().map(|m| m.to_string()).collect();
+        names.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Method>, D::Error> {
+        let names = Vec::<String>::deserialize(deserializer)?;
+        names.iter().map(|name| match name.as_str() {
+            "GET" => Ok(Method::Get),
+            "POST" => Ok(Method::Post),
+            "PUT" => Ok(Method::Put),
+            "DELETE" => Ok(Method::Delete),
+            other => Err(D::Error::custom(format!("unknown method {}", other))),
+        }).collect()
+    }
+}
+
+/// Errors as their messages, which is all that's left of them after a round trip
+pub mod error_messages {
+    use errors::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(errors: &Vec<Error>, serializer: S) -> Result<S::Ok, S::Error> {
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        messages.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Error>, D::Error> {
+        let messages = Vec::<String>::deserialize(deserializer)?;
+        Ok(messages.into_iter().map(Error::from).collect())
+    }
+}
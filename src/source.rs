@@ -0,0 +1,156 @@
+use acquisition::{FetchOptions, TlsOptions};
+use cassette::{Cassette, CassetteMode};
+use errors::*;
+use errors::ErrorKind::LoginRequired;
+
+use reqwest::{self, Certificate, Url};
+use reqwest::header::Headers;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// Where documentation pages come from
+pub trait DocSource {
+    /// The HTML of the page at `url`
+    fn fetch_page(&self, url: &Url) -> Result<String>;
+}
+
+impl<S: DocSource + ?Sized> DocSource for Box<S> {
+    fn fetch_page(&self, url: &Url) -> Result<String> {
+        (**self).fetch_page(url)
+    }
+}
+
+/// The live documentation site
+pub struct HttpSource {
+    client: reqwest::Client,
+    cookies: Option<String>,
+    language: Option<String>,
+}
+
+impl HttpSource {
+    pub fn new(options: &FetchOptions) -> Result<HttpSource> {
+        Ok(HttpSource {
+            client: build_client(&options.tls)?,
+            cookies: options.cookies.clone(),
+            language: options.language.clone(),
+        })
+    }
+}
+
+fn build_client(tls: &TlsOptions) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()?;
+    for path in &tls.root_certificates {
+        let mut der = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut der))
+            .chain_err(|| format!("While reading certificate {}", path.display()))?;
+        let certificate = Certificate::from_der(&der)
+            .chain_err(|| format!("While parsing certificate {}", path.display()))?;
+        builder.add_root_certificate(certificate)?;
+    }
+    if tls.danger_disable_hostname_verification {
+        builder.danger_disable_hostname_verification();
+    }
+    Ok(builder.build()?)
+}
+
+impl DocSource for HttpSource {
+    fn fetch_page(&self, url: &Url) -> Result<String> {
+        let mut headers = Headers::new();
+        if let Some(ref cookies) = self.cookies {
+            headers.set_raw("Cookie", cookies.clone());
+        }
+        if let Some(ref language) = self.language {
+            headers.set_raw("Accept-Language", language.clone());
+        }
+        let mut response = self.client.get(url.clone()).headers(headers).send()?;
+        // Gated pages redirect to the login form instead of failing
+        if response.url().path().to_lowercase().contains("login") {
+            bail!(LoginRequired(url.to_string()));
+        }
+        let mut body = String::new();
+        response.read_to_string(&mut body)?;
+        Ok(body)
+    }
+}
+
+/// Pages saved to a local directory, e.g. by `wget`, named after the last segment of their url
+/// including the query: `HlpRestAPIResourcesDetails.aspx?name=CRMAccounts`
+pub struct DirectorySource {
+    dir: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> DirectorySource {
+        DirectorySource { dir: dir.into() }
+    }
+
+    /// The file the page at `url` is expected in
+    pub fn path(&self, url: &Url) -> PathBuf {
+        let mut name = url.path_segments()
+            .and_then(|segments| segments.last())
+            .unwrap_or("")
+            .to_owned();
+        if let Some(query) = url.query() {
+            name.push('?');
+            name.push_str(query);
+        }
+        self.dir.join(name)
+    }
+}
+
+impl DocSource for DirectorySource {
+    fn fetch_page(&self, url: &Url) -> Result<String> {
+        let path = self.path(url);
+        let mut body = String::new();
+        File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut body))
+            .chain_err(|| format!("While reading {} for {}", path.display(), url))?;
+        Ok(body)
+    }
+}
+
+/// Replays pages from a cassette, or records the pages of another source into it
+pub struct CassetteSource<S: DocSource> {
+    cassette: Cassette,
+    inner: S,
+}
+
+impl<S: DocSource> CassetteSource<S> {
+    pub fn new(cassette: Cassette, inner: S) -> CassetteSource<S> {
+        CassetteSource { cassette: cassette, inner: inner }
+    }
+}
+
+impl<S: DocSource> DocSource for CassetteSource<S> {
+    fn fetch_page(&self, url: &Url) -> Result<String> {
+        match self.cassette.mode {
+            CassetteMode::Replay => self.cassette.load(url),
+            CassetteMode::Record => {
+                let body = self.inner.fetch_page(url)?;
+                self.cassette.store(url, &body)?;
+                Ok(body)
+            }
+        }
+    }
+}
+
+#[test]
+fn it_reads_pages_from_a_directory() {
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    let dir = env::temp_dir().join(format!("exact-openapi-gen-directory-test-{}", ::std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = DirectorySource::new(dir.clone());
+    let url = Url::parse("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=CRMAccounts").unwrap();
+    assert_eq!(source.path(&url), dir.join("HlpRestAPIResourcesDetails.aspx?name=CRMAccounts"));
+    assert!(source.fetch_page(&url).is_err());
+
+    File::create(source.path(&url)).unwrap().write_all(b"<html></html>").unwrap();
+    assert_eq!(source.fetch_page(&url).expect("Page read"), "<html></html>");
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,223 @@
+//! Splitting a generated spec into a file per service area
+
+use annotations::pointer;
+use report::collect_refs;
+
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A spec split by tag: the root document, and the files its paths refer to by name
+#[derive(Clone, Debug)]
+pub struct SplitSpec {
+    pub root: Value,
+    pub parts: BTreeMap<String, Value>,
+}
+
+/// Moves the paths of every tag into a file of their own, together with the definitions only
+/// those paths use. Definitions shared by several tags stay in the root, which refers to the
+/// paths in the parts. The parts refer back to the root, named `root_file`, for everything else.
+pub fn split_by_tag(spec: &Value, root_file: &str, extension: &str) -> SplitSpec {
+    let definitions_path: &[&str] = if spec.get("openapi").is_some() {
+        &["components", "schemas"]
+    } else {
+        &["definitions"]
+    };
+    let schema_prefix = format!("#{}/", pointer(definitions_path));
+    let definitions = spec.pointer(&pointer(definitions_path))
+        .and_then(|d| d.as_object()).cloned().unwrap_or_default();
+    let paths = spec.get("paths").and_then(|p| p.as_object()).cloned().unwrap_or_default();
+
+    let mut tagged: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+    for (path, item) in paths {
+        tagged.entry(tag_of(&item)).or_insert_with(Map::new).insert(path, item);
+    }
+    let files = file_names(tagged.keys(), root_file, extension);
+    let file_of_tag = |tag: &str| files[tag].clone();
+
+    // Definitions used by the paths of a single tag move into its file
+    let mut users: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for (tag, paths) in &tagged {
+        for name in reachable_definitions(&Value::Object(paths.clone()), &definitions, &schema_prefix) {
+            users.entry(name).or_insert_with(BTreeSet::new).insert(tag.clone());
+        }
+    }
+    let owner = |name: &str| -> Option<String> {
+        users.get(name).and_then(|tags| if tags.len() == 1 { tags.iter().next().cloned() } else { None })
+    };
+
+    let mut parts = BTreeMap::new();
+    for (tag, paths) in &tagged {
+        let file = file_of_tag(tag);
+        let owned: Map<String, Value> = definitions.iter()
+            .filter(|&(name, _)| owner(name).as_ref() == Some(tag))
+            .map(|(name, d)| (name.clone(), d.clone()))
+            .collect();
+        let mut part = Map::new();
+        part.insert("paths".to_owned(), Value::Object(paths.clone()));
+        insert_at(&mut part, definitions_path, Value::Object(owned));
+        let part = rewrite_refs(&Value::Object(part), Some(&file), root_file, &schema_prefix,
+            &|name: &str| owner(name).map(|t| file_of_tag(&t)));
+        parts.insert(file, part);
+    }
+
+    let mut root = rewrite_refs(spec, None, root_file, &schema_prefix, &|name: &str| owner(name).map(|t| file_of_tag(&t)));
+    if let Some(root) = root.as_object_mut() {
+        let paths = tagged.iter().flat_map(|(tag, paths)| {
+            let file = file_of_tag(tag);
+            paths.keys().map(move |path| {
+                (path.clone(), json!({ "$ref": format!("{}#{}", file, pointer(&["paths", path])) }))
+            }).collect::<Vec<_>>()
+        }).collect();
+        root.insert("paths".to_owned(), Value::Object(paths));
+        let shared = definitions.into_iter().filter(|&(ref name, _)| owner(name).is_none()).collect();
+        let shared = rewrite_refs(&Value::Object(shared), None, root_file, &schema_prefix,
+            &|name: &str| owner(name).map(|t| file_of_tag(&t)));
+        insert_at(root, definitions_path, shared);
+    }
+    SplitSpec { root: root, parts: parts }
+}
+
+/// The tag of the first operation of the path item
+fn tag_of(item: &Value) -> String {
+    item.as_object()
+        .and_then(|operations| operations.values().filter_map(|o| o.pointer("/tags/0")).next())
+        .and_then(|tag| tag.as_str())
+        .unwrap_or("Other")
+        .to_owned()
+}
+
+/// A file name for the tag, without characters that need escaping in a `$ref`
+fn file_stem(tag: &str) -> String {
+    tag.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect()
+}
+
+/// The file of every tag. Tags whose file would be the root file, or the file of an earlier tag,
+/// get a numbered one instead. Names are compared ignoring case, as file systems may.
+fn file_names<'a, I: Iterator<Item=&'a String>>(tags: I, root_file: &str, extension: &str) -> BTreeMap<String, String> {
+    let mut taken = BTreeSet::new();
+    taken.insert(root_file.to_lowercase());
+    tags.map(|tag| {
+        let stem = file_stem(tag);
+        let mut file = format!("{}.{}", stem, extension);
+        let mut n = 2;
+        while !taken.insert(file.to_lowercase()) {
+            file = format!("{}-{}.{}", stem, n, extension);
+            n += 1;
+        }
+        (tag.clone(), file)
+    }).collect()
+}
+
+/// The names of the definitions `value` refers to, directly or through other definitions
+fn reachable_definitions(value: &Value, definitions: &Map<String, Value>, schema_prefix: &str) -> BTreeSet<String> {
+    let mut reached = BTreeSet::new();
+    let mut pending = Vec::new();
+    collect_refs(value, &mut pending);
+    while let Some(reference) = pending.pop() {
+        if !reference.starts_with(schema_prefix) {
+            continue;
+        }
+        let name = reference[schema_prefix.len()..].to_owned();
+        if !reached.insert(name.clone()) {
+            continue;
+        }
+        if let Some(definition) = definitions.get(&name) {
+            collect_refs(definition, &mut pending);
+        }
+    }
+    reached
+}
+
+/// Points the local references of the document named `current` (the root if `None`) at the file
+/// now holding their target
+fn rewrite_refs(value: &Value, current: Option<&str>, root_file: &str, schema_prefix: &str,
+        definition_file: &Fn(&str) -> Option<String>) -> Value {
+    match *value {
+        Value::Object(ref members) => Value::Object(members.iter().map(|(key, member)| {
+            let member = match *member {
+                Value::String(ref target) if key == "$ref" && target.starts_with("#/") => {
+                    let file = if target.starts_with(schema_prefix) {
+                        definition_file(&target[schema_prefix.len()..])
+                    } else {
+                        None
+                    };
+                    let file = file.unwrap_or_else(|| root_file.to_owned());
+                    let is_current = current.map_or(file == root_file, |c| c == file);
+                    if is_current {
+                        member.clone()
+                    } else {
+                        Value::String(format!("{}{}", file, target))
+                    }
+                },
+                _ => rewrite_refs(member, current, root_file, schema_prefix, definition_file),
+            };
+            (key.clone(), member)
+        }).collect()),
+        Value::Array(ref items) => Value::Array(items.iter()
+            .map(|item| rewrite_refs(item, current, root_file, schema_prefix, definition_file))
+            .collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Sets the member at the path of keys, creating the objects along the way
+fn insert_at(object: &mut Map<String, Value>, path: &[&str], value: Value) {
+    if path.len() == 1 {
+        object.insert(path[0].to_owned(), value);
+        return;
+    }
+    let child = object.entry(path[0].to_owned()).or_insert_with(|| json!({}));
+    if let Some(child) = child.as_object_mut() {
+        insert_at(child, &path[1..], value);
+    }
+}
+
+#[test]
+fn it_splits_specs_by_tag() {
+    let spec = json!({
+        "swagger": "2.0",
+        "paths": {
+            "/api/v1/{division}/project/Projects": {
+                "parameters": [{ "$ref": "#/parameters/Division" }],
+                "get": { "tags": ["Project"], "responses": {
+                    "200": { "schema": { "$ref": "#/definitions/ProjectProjectsResponse" } },
+                    "500": { "schema": { "$ref": "#/definitions/Error" } }
+                } }
+            },
+            "/api/v1/{division}/crm/Accounts": {
+                "get": { "tags": ["CRM"], "responses": {
+                    "200": { "schema": { "$ref": "#/definitions/CRMAccountsResponse" } },
+                    "500": { "schema": { "$ref": "#/definitions/Error" } }
+                } }
+            }
+        },
+        "definitions": {
+            "Error": { "type": "object" },
+            "ProjectProjects": { "type": "object" },
+            "ProjectProjectsResponse": { "items": { "$ref": "#/definitions/ProjectProjects" } },
+            "CRMAccountsResponse": { "type": "object" }
+        },
+        "parameters": { "Division": { "name": "division", "in": "path" } }
+    });
+    let split = split_by_tag(&spec, "api.json", "json");
+    assert_eq!(split.parts.keys().collect::<Vec<_>>(), vec!["CRM.json", "Project.json"]);
+    assert_eq!(split.root["paths"]["/api/v1/{division}/project/Projects"]["$ref"],
+        json!("Project.json#/paths/~1api~1v1~1{division}~1project~1Projects"));
+    assert_eq!(split.root["definitions"], json!({ "Error": { "type": "object" } }));
+
+    let project = &split.parts["Project.json"];
+    let get = &project["paths"]["/api/v1/{division}/project/Projects"];
+    assert_eq!(get["parameters"][0]["$ref"], json!("api.json#/parameters/Division"));
+    assert_eq!(get["get"]["responses"]["200"]["schema"]["$ref"], json!("#/definitions/ProjectProjectsResponse"));
+    assert_eq!(get["get"]["responses"]["500"]["schema"]["$ref"], json!("api.json#/definitions/Error"));
+    assert!(project["definitions"].get("ProjectProjects").is_some());
+    assert!(project["definitions"].get("CRMAccountsResponse").is_none());
+}
+
+#[test]
+fn it_gives_every_tag_a_file_of_its_own() {
+    let tags = ["API".to_owned(), "CRM Accounts".to_owned(), "CRM-Accounts".to_owned(), "Crm-accounts".to_owned()];
+    let files = file_names(tags.iter(), "api.json", "json");
+    assert_eq!(files.values().collect::<Vec<_>>(),
+        vec!["API-2.json", "CRM-Accounts.json", "CRM-Accounts-2.json", "Crm-accounts-3.json"]);
+}
@@ -1,103 +1,601 @@
-use openapi::{Contact, Info, License, Operation, Operations, Parameter, Response, Schema, Spec, ParameterOrRef, Security};
-use acquisition::{EndpointDetails, EdmType};
+use openapi::{Contact, Flow, Info, License, Operation, Operations, Parameter, Response, Schema, Spec, ParameterOrRef, Security, Tag};
+use acquisition::{EndpointDetails, EndpointKind, EdmType, Property, QueryOption, SYNC_TIMESTAMP, function_path};
+use options::{SpecOptions, SpecVersion, FormatMapping, BinaryFormat, ByteFormat, DefinitionKind, InfoOptions};
+use inline::inline_refs;
+use canonical::canonicalize;
+use openapi3::{to_openapi3, use_server_variables};
+use webhooks::{WEBHOOK_SUBSCRIPTIONS, build_payload_definition, subscription_endpoint};
+use annotations::{Annotations, pointer};
+use report::find_dangling_refs;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::iter::FromIterator;
 use errors::*;
+use errors::ErrorKind::{DanglingRef, InvalidOptions};
 use reqwest::Method;
+use serde_json::{self, Map, Value};
 
-fn build_paths<'a, T: Iterator<Item=&'a EndpointDetails>>(endpoints: T) -> Result<BTreeMap<String, Operations>> {
+fn build_paths<'a, T: Iterator<Item=&'a EndpointDetails>>(endpoints: T, options: &SpecOptions, annotations: &mut Annotations) -> Result<BTreeMap<String, Operations>> {
     let mut paths = BTreeMap::new();
+    let mut operation_ids = BTreeSet::new();
+    // Colliding operationIds are numbered in the order of the uris, not the order of scraping
+    let mut endpoints: Vec<&EndpointDetails> = endpoints.collect();
+    endpoints.sort_by(|a, b| (&a.uri, &a.name).cmp(&(&b.uri, &b.name)));
     for endpoint in endpoints {
-        if endpoint.methods.contains(&Method::Put) || endpoint.methods.contains(&Method::Delete) {
-            let url = format!("{}(guid'{{id}}')", endpoint.uri);
-            paths.insert(url, Operations {
-                put: build_operation(Method::Put, endpoint),
-                delete: build_operation(Method::Delete, endpoint),
+        if endpoint.kind == EndpointKind::Function {
+            let url = options.rebase_uri(function_path(&endpoint.uri));
+            let operations = Operations {
+                post: identify(Some(build_function_operation(endpoint, options)),
+                    format!("call{}", options.endpoint_name(endpoint)), &mut operation_ids),
+                parameters: path_parameters(endpoint),
                 ..Operations::default()
-            });
+            };
+            let operations = describe_operations(operations, endpoint, false, options);
+            let operations = override_media_types(operations, endpoint, options);
+            let operations = finish_operations(&url, operations, endpoint, options, annotations);
+            paths.insert(url, operations);
+            continue;
+        }
+        if endpoint.methods.contains(&Method::Put) || endpoint.methods.contains(&Method::Delete) || has_single_get(endpoint) {
+            let url = format!("{}{}", options.rebase_uri(&endpoint.uri), key_segment(endpoint));
+            let operations = Operations {
+                get: identify(build_single_get_operation(endpoint, &url, options, annotations),
+                    operation_id(&Method::Get, endpoint, true, options), &mut operation_ids),
+                put: identify(build_operation(Method::Put, endpoint, &url, options, annotations),
+                    operation_id(&Method::Put, endpoint, true, options), &mut operation_ids),
+                delete: identify(build_operation(Method::Delete, endpoint, &url, options, annotations),
+                    operation_id(&Method::Delete, endpoint, true, options), &mut operation_ids),
+                parameters: path_parameters(endpoint),
+                ..Operations::default()
+            };
+            let operations = describe_operations(operations, endpoint, true, options);
+            let operations = override_media_types(operations, endpoint, options);
+            let operations = finish_operations(&url, operations, endpoint, options, annotations);
+            paths.insert(url, operations);
+        }
+        if let Some(property) = options.file_property(endpoint) {
+            let url = format!("{}{}/$value", options.rebase_uri(&endpoint.uri), key_segment(endpoint));
+            let name = options.endpoint_name(endpoint);
+            let operations = Operations {
+                get: identify(build_download_operation(endpoint, property, options),
+                    format!("download{}", name), &mut operation_ids),
+                put: identify(build_upload_operation(endpoint, property, options),
+                    format!("upload{}", name), &mut operation_ids),
+                parameters: path_parameters(endpoint),
+                ..Operations::default()
+            };
+            if operations.get.is_some() || operations.put.is_some() {
+                let operations = finish_operations(&url, operations, endpoint, options, annotations);
+                paths.insert(url, operations);
+            }
         }
         if endpoint.methods.contains(&Method::Get) || endpoint.methods.contains(&Method::Post) {
-            paths.insert(endpoint.uri.clone(), Operations {
-                get: build_operation(Method::Get, endpoint),
-                post: build_operation(Method::Post, endpoint),
+            let url = options.rebase_uri(&endpoint.uri);
+            let operations = Operations {
+                get: identify(build_operation(Method::Get, endpoint, &url, options, annotations),
+                    operation_id(&Method::Get, endpoint, false, options), &mut operation_ids),
+                post: identify(build_operation(Method::Post, endpoint, &url, options, annotations),
+                    operation_id(&Method::Post, endpoint, false, options), &mut operation_ids),
+                parameters: path_parameters(endpoint),
                 ..Operations::default()
-            });
+            };
+            let operations = describe_operations(operations, endpoint, false, options);
+            let operations = override_media_types(operations, endpoint, options);
+            let operations = finish_operations(&url, operations, endpoint, options, annotations);
+            paths.insert(url, operations);
+        }
+        if options.include_count_paths && endpoint.methods.contains(&Method::Get) && !endpoint.singleton {
+            let url = format!("{}/$count", options.rebase_uri(&endpoint.uri));
+            let operations = Operations {
+                get: identify(Some(build_count_operation(endpoint, options)),
+                    format!("count{}", options.endpoint_name(endpoint)), &mut operation_ids),
+                parameters: path_parameters(endpoint),
+                ..Operations::default()
+            };
+            let operations = finish_operations(&url, operations, endpoint, options, annotations);
+            paths.insert(url, operations);
         }
     }
     Ok(paths)
 }
 
-fn build_operation<'a>(method: Method, details: &'a EndpointDetails) -> Option<Operation> {
+/// Adds what all operations of the endpoint share: tags, security, docs and extensions
+fn finish_operations(path: &str, operations: Operations, endpoint: &EndpointDetails, options: &SpecOptions, annotations: &mut Annotations) -> Operations {
+    let operations = secure_operations(tag_operations(operations, options.renamed(&service_tag(endpoint))), endpoint);
+    if endpoint.beta {
+        mark_preview(path, &operations, annotations);
+    }
+    link_docs(path, &operations, endpoint, annotations);
+    if endpoint.deprecated {
+        mark_deprecated(path, &operations, endpoint, annotations);
+    }
+    annotate_response_headers(path, &operations, options, annotations);
+    annotate_exact_metadata(path, &operations, endpoint, annotations);
+    operations
+}
+
+/// GET on `$count`, answering the number of records matching the filter as plain text
+fn build_count_operation(details: &EndpointDetails, options: &SpecOptions) -> Operation {
+    let mut responses = BTreeMap::new();
+    responses.insert("200".to_owned(), Response {
+        description: "Number of records".to_owned(),
+        schema: Some(Schema {
+            schema_type: Some("integer".to_owned()),
+            format: Some("int64".to_owned()),
+            ..Schema::default()
+        })
+    });
+    insert_error_responses(&mut responses, options);
+    let mut parameters = Vec::new();
+    if details.query_options.contains(&QueryOption::Filter) {
+        parameters.push(ParameterOrRef::Ref {
+            ref_path: "#/parameters/filter".to_owned()
+        });
+    }
+    Operation {
+        summary: Some(format!("Count {}", options.endpoint_name(details))),
+        produces: Some(vec!["text/plain".to_owned()]),
+        responses: responses,
+        parameters: Some(parameters),
+        ..Operation::default()
+    }
+}
+
+/// The parameters shared by all operations on the paths of the endpoint
+fn path_parameters(endpoint: &EndpointDetails) -> Option<Vec<ParameterOrRef>> {
+    if endpoint.requires_division {
+        Some(vec![ParameterOrRef::Ref {
+            ref_path: "#/parameters/Division".to_owned()
+        }])
+    } else {
+        None
+    }
+}
+
+/// The operationId for calling `method` on the endpoint, e.g. `getProjectProjects` or
+/// `updateProjectProjectsById` for operations on a single entity
+fn operation_id(method: &Method, endpoint: &EndpointDetails, keyed: bool, options: &SpecOptions) -> String {
+    let verb = match *method {
+        Method::Get => "get",
+        Method::Post => "create",
+        Method::Put => "update",
+        Method::Delete => "delete",
+        _ => unreachable!()
+    };
+    let suffix = if keyed { "ById" } else { "" };
+    format!("{}{}{}", verb, options.endpoint_name(endpoint), suffix)
+}
+
+/// Sets the operationId, numbering it when an earlier operation already took it
+fn identify(operation: Option<Operation>, id: String, used: &mut BTreeSet<String>) -> Option<Operation> {
+    operation.map(|mut operation| {
+        let mut unique = id.clone();
+        let mut n = 2;
+        while used.contains(&unique) {
+            unique = format!("{}{}", id, n);
+            n += 1;
+        }
+        used.insert(unique.clone());
+        operation.operation_id = Some(unique);
+        operation
+    })
+}
+
+/// The tag grouping the operations of an endpoint: its service area from the overview,
+/// or the first segment of its uri after the division
+fn service_tag(endpoint: &EndpointDetails) -> String {
+    if let Some(ref service) = endpoint.service {
+        return service.clone();
+    }
+    let segment = endpoint.uri.split('/')
+        .filter(|s| !s.is_empty())
+        .skip(2)
+        .find(|s| *s != "{division}" && *s != "bulk" && *s != "sync")
+        .unwrap_or("");
+    let mut chars = segment.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Other".to_owned(),
+    }
+}
+
+fn tag_operations(operations: Operations, tag: String) -> Operations {
+    let with_tag = |operation: Option<Operation>| operation.map(|operation| Operation {
+        tags: Some(vec![tag.clone()]),
+        ..operation
+    });
+    Operations {
+        get: with_tag(operations.get),
+        post: with_tag(operations.post),
+        put: with_tag(operations.put),
+        delete: with_tag(operations.delete),
+        ..operations
+    }
+}
+
+/// Summarizes the operations and prefixes their descriptions with what the docs say about the
+/// endpoint, so the spec reads well without the Exact docs at hand
+fn describe_operations(operations: Operations, endpoint: &EndpointDetails, keyed: bool, options: &SpecOptions) -> Operations {
+    let function = endpoint.kind == EndpointKind::Function;
+    let name = options.endpoint_name(endpoint);
+    let describe = |method: Method, operation: Option<Operation>| operation.map(|operation| {
+        let listing = method == Method::Get && !keyed && !endpoint.singleton && !function;
+        let creating = method == Method::Post && !function;
+        let summary = if function {
+            format!("Call {}", name)
+        } else {
+            match (method, keyed) {
+                (Method::Get, false) if endpoint.singleton => format!("Get {}", name),
+                (Method::Get, false) => format!("List {}", name),
+                (Method::Get, true) => format!("Get {} by key", name),
+                (Method::Post, _) => format!("Create {}", name),
+                (Method::Put, _) => format!("Update {} by key", name),
+                (Method::Delete, _) => format!("Delete {} by key", name),
+                _ => unreachable!(),
+            }
+        };
+        let mut paragraphs = Vec::new();
+        if let Some(ref description) = endpoint.description {
+            paragraphs.push(description.clone());
+        }
+        if let Some(ref description) = operation.description {
+            paragraphs.push(description.clone());
+        }
+        let mandatory: Vec<String> = endpoint.properties.iter()
+            .filter(|p| p.mandatory && p.methods.contains(&Method::Post) && options.includes_property(endpoint, p))
+            .map(|p| format!("`{}`", options.property_name(endpoint, p)))
+            .collect();
+        if creating && !mandatory.is_empty() {
+            paragraphs.push(format!("Requires {}.", mandatory.join(", ")));
+        }
+        if listing {
+            paragraphs.push(format!("Returns at most {} records per page.", options.max_page_size(endpoint)));
+        }
+        Operation {
+            summary: Some(summary),
+            description: if paragraphs.is_empty() { None } else { Some(paragraphs.join("\n\n")) },
+            ..operation
+        }
+    });
+    Operations {
+        get: describe(Method::Get, operations.get),
+        post: describe(Method::Post, operations.post),
+        put: describe(Method::Put, operations.put),
+        delete: describe(Method::Delete, operations.delete),
+        ..operations
+    }
+}
+
+/// The representations of entities, JSON and optionally the Atom XML of older integrations
+fn media_types(options: &SpecOptions) -> Vec<String> {
+    let mut media_types = vec!["application/json".to_owned()];
+    if options.include_atom {
+        media_types.push("application/atom+xml".to_owned());
+    }
+    media_types
+}
+
+/// Replaces the media types of the operations configured in `media_types`, so documents like
+/// PDFs aren't described as JSON
+fn override_media_types(operations: Operations, endpoint: &EndpointDetails, options: &SpecOptions) -> Operations {
+    let overrides = match options.media_types.get(&endpoint.name) {
+        Some(overrides) => overrides,
+        None => return operations,
+    };
+    let override_operation = |method: Method, operation: Option<Operation>| operation.map(|mut operation| {
+        let media_types = match overrides.get(&method_key(&method).to_uppercase()) {
+            Some(media_types) => media_types,
+            None => return operation,
+        };
+        let json = |media_type: &String| media_type.contains("json");
+        if media_types.produces.as_ref().map_or(false, |produces| !produces.iter().any(json)) {
+            // The entity definitions only describe JSON, other responses are files
+            for (status, response) in &mut operation.responses {
+                if status.starts_with('2') && response.schema.is_some() {
+                    response.schema = Some(Schema {
+                        schema_type: Some("file".to_owned()),
+                        ..Schema::default()
+                    });
+                }
+            }
+        }
+        Operation {
+            consumes: media_types.consumes.clone().or(operation.consumes.clone()),
+            produces: media_types.produces.clone().or(operation.produces.clone()),
+            ..operation
+        }
+    });
+    Operations {
+        get: override_operation(Method::Get, operations.get),
+        post: override_operation(Method::Post, operations.post),
+        put: override_operation(Method::Put, operations.put),
+        delete: override_operation(Method::Delete, operations.delete),
+        ..operations
+    }
+}
+
+/// Requires the scopes the endpoint documents from OAuth2 tokens. Without documented scopes
+/// the operations keep the top-level requirements.
+fn secure_operations(operations: Operations, endpoint: &EndpointDetails) -> Operations {
+    if endpoint.scopes.is_empty() {
+        return operations;
+    }
+    let mut oauth2 = BTreeMap::new();
+    oauth2.insert("oauth2".to_owned(), endpoint.scopes.clone());
+    let mut api_key = BTreeMap::new();
+    api_key.insert("ApiKey".to_owned(), Vec::new());
+    let security = vec![oauth2, api_key];
+    let with_security = |operation: Option<Operation>| operation.map(|operation| Operation {
+        security: Some(security.clone()),
+        ..operation
+    });
+    Operations {
+        get: with_security(operations.get),
+        post: with_security(operations.post),
+        put: with_security(operations.put),
+        delete: with_security(operations.delete),
+        ..operations
+    }
+}
+
+/// A top-level tag for every service area with operations in the spec
+fn build_tags(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Vec<Tag> {
+    let services: BTreeSet<String> = endpoints.iter().map(|e| options.renamed(&service_tag(e))).collect();
+    services.into_iter().map(|service| Tag {
+        description: Some(format!("Resources of the {} service area", service)),
+        name: service,
+        external_docs: None,
+    }).collect()
+}
+
+/// The operations present in the path item, by their key
+fn present_operations(operations: &Operations) -> Vec<(&'static str, &Operation)> {
+    let all = [
+        ("get", &operations.get),
+        ("post", &operations.post),
+        ("put", &operations.put),
+        ("delete", &operations.delete),
+    ];
+    all.iter().filter_map(|&(method, operation)| operation.as_ref().map(|o| (method, o))).collect()
+}
+
+/// The keys of the operations present in the path item
+fn present_methods(operations: &Operations) -> Vec<&'static str> {
+    present_operations(operations).into_iter().map(|(method, _)| method).collect()
+}
+
+/// Carries the scraped metadata OpenAPI has no place for in `x-exact-*` extensions
+fn annotate_exact_metadata(path: &str, operations: &Operations, endpoint: &EndpointDetails, annotations: &mut Annotations) {
+    for method in present_methods(operations) {
+        let operation = pointer(&["paths", path, method]);
+        annotations.insert(operation.clone(), "x-exact-service", json!(service_tag(endpoint)));
+        if !endpoint.scopes.is_empty() {
+            annotations.insert(operation.clone(), "x-exact-scope", json!(endpoint.scopes));
+        }
+        if !endpoint.required_packages.is_empty() {
+            annotations.insert(operation, "x-exact-package", json!(endpoint.required_packages));
+        }
+    }
+}
+
+/// Headers Exact sends with every response, for the daily and the minutely limit
+const RATE_LIMIT_HEADERS: &'static [(&'static str, &'static str)] = &[
+    ("X-RateLimit-Limit", "Calls allowed per day"),
+    ("X-RateLimit-Remaining", "Calls left today"),
+    ("X-RateLimit-Reset", "When the daily limit resets, in milliseconds since the Unix epoch"),
+    ("X-RateLimit-Minutely-Limit", "Calls allowed per minute"),
+    ("X-RateLimit-Minutely-Remaining", "Calls left this minute"),
+    ("X-RateLimit-Minutely-Reset", "When the minutely limit resets, in milliseconds since the Unix epoch"),
+];
+
+/// Documents the rate limit headers on the successful and throttled responses, which are the ones
+/// callers pace themselves by, and the `ETag` of fetched entities if asked for.
+/// `Response` has no field for headers.
+fn annotate_response_headers(path: &str, operations: &Operations, options: &SpecOptions, annotations: &mut Annotations) {
+    let headers: Map<String, Value> = RATE_LIMIT_HEADERS.iter().map(|&(name, description)| {
+        (name.to_owned(), json!({ "type": "integer", "format": "int64", "description": description }))
+    }).collect();
+    for (method, operation) in present_operations(operations) {
+        for status in operation.responses.keys().filter(|s| s.starts_with('2') || *s == "429") {
+            let mut headers = headers.clone();
+            if options.concurrency_headers && method == "get" && status.starts_with('2') {
+                headers.insert("ETag".to_owned(), json!({
+                    "type": "string",
+                    "description": "Version of the entity, to send in `If-Match` when updating or deleting it",
+                }));
+            }
+            annotations.insert(pointer(&["paths", path, method, "responses", status]), "headers", Value::Object(headers));
+        }
+    }
+}
+
+/// Flags the operations of a beta resource with `x-preview`
+fn mark_preview(path: &str, operations: &Operations, annotations: &mut Annotations) {
+    for method in present_methods(operations) {
+        annotations.insert(pointer(&["paths", path, method]), "x-preview", json!(true));
+    }
+}
+
+/// Flags the operations of a deprecated resource, naming its replacement in `x-replaced-by`.
+/// `Operation` has no field for `deprecated` either.
+fn mark_deprecated(path: &str, operations: &Operations, endpoint: &EndpointDetails, annotations: &mut Annotations) {
+    for method in present_methods(operations) {
+        annotations.insert(pointer(&["paths", path, method]), "deprecated", json!(true));
+        if let Some(ref replacement) = endpoint.replaced_by {
+            annotations.insert(pointer(&["paths", path, method]), "x-replaced-by", json!(replacement));
+        }
+    }
+}
+
+/// Links the operations to the documentation page they were generated from.
+/// `Operation` has no field for `externalDocs`, so they are annotated.
+fn link_docs(path: &str, operations: &Operations, endpoint: &EndpointDetails, annotations: &mut Annotations) {
+    let docs = json!({
+        "description": format!("Exact Online documentation of {}", endpoint.name),
+        "url": endpoint.doc_url.as_str(),
+    });
+    for method in present_methods(operations) {
+        annotations.insert(pointer(&["paths", path, method]), "externalDocs", docs.clone());
+    }
+}
+
+/// The key of an operation within its path item
+fn method_key(method: &Method) -> &'static str {
+    match *method {
+        Method::Get => "get",
+        Method::Post => "post",
+        Method::Put => "put",
+        Method::Delete => "delete",
+        _ => unreachable!()
+    }
+}
+
+fn build_operation<'a>(method: Method, details: &'a EndpointDetails, path: &str, options: &SpecOptions, annotations: &mut Annotations) -> Option<Operation> {
     if details.methods.contains(&method) {
         let mut responses = BTreeMap::new();
         let success_status = match method {
             Method::Get => "200",
             Method::Post => "201",
             Method::Put => "204",
-            Method::Delete => "200",
+            Method::Delete => "204",
             _ => unreachable!()
         };
-        let success_schema = if method == Method::Delete { None } else {
-            Some(Schema {
-                ref_path: Some(format!("#/definitions/{}Response", details.name)),
-                ..Schema::default()
-            })
-        };
-        responses.insert(success_status.to_owned(), Response {
-            description: "Command successful".to_owned(),
-            schema: success_schema
-        });
-        responses.insert("400".to_owned(), Response {
-            description: "Bad request (syntax invalid)".to_owned(),
-            schema: None
-        });
-        responses.insert("401".to_owned(), Response {
-            description: "Unauthorized".to_owned(),
-            schema: None
-        });
-        responses.insert("404".to_owned(), Response {
-            description: "Not found".to_owned(),
-            schema: None
-        });
-        responses.insert("500".to_owned(), Response {
-            description: "Error".to_owned(),
-            schema: Some(Schema {
-                ref_path: Some("#/definitions/Error".to_owned()),
-                ..Schema::default()
-            })
-        });
-        let mut parameters = Vec::new();
-        if method == Method::Get {
-            // Add $filter, $select
-            parameters.push(ParameterOrRef::Ref {
-                ref_path: "#/parameters/filter".to_owned()
+        if method == Method::Delete {
+            // Exact answers a successful delete with an empty body
+            responses.insert(success_status.to_owned(), Response {
+                description: "Entity deleted".to_owned(),
+                schema: None
             });
-            parameters.push(ParameterOrRef::Ref {
-                ref_path: "#/parameters/select".to_owned()
-            });
-            parameters.push(ParameterOrRef::Ref {
-                ref_path: "#/parameters/order_by".to_owned()
+        } else {
+            responses.insert(success_status.to_owned(), Response {
+                description: "Command successful".to_owned(),
+                schema: Some(Schema {
+                    ref_path: Some(options.definition_ref(&details.name, response_definition(&method, details))),
+                    ..Schema::default()
+                })
             });
         }
-        if details.uri.contains("{division}") {
-            parameters.push(ParameterOrRef::Ref {
-                ref_path: "#/parameters/Division".to_owned()
-            });
+        insert_error_responses(&mut responses, options);
+        let example = details.examples.get(&method_key(&method).to_uppercase());
+        // The created entity is echoed back like it is fetched
+        let returned = match method {
+            Method::Get => example,
+            Method::Post => details.examples.get("GET"),
+            _ => None,
+        };
+        if let Some(returned) = returned {
+            let envelope = match response_definition(&method, details) {
+                DefinitionKind::Response => Envelope::Collection,
+                _ => Envelope::Single,
+            };
+            annotate_response_example(path, method_key(&method), success_status, returned, envelope, options, annotations);
+        }
+        let mut parameters = Vec::new();
+        if method == Method::Get {
+            // Only offer the query options the endpoint documents
+            if details.query_options.contains(&QueryOption::Filter) {
+                parameters.push(ParameterOrRef::Ref {
+                    ref_path: "#/parameters/filter".to_owned()
+                });
+            }
+            if details.query_options.contains(&QueryOption::Select) && options.typed_select {
+                let selectable: Vec<String> = details.properties.iter()
+                    .filter(|p| p.methods.contains(&Method::Get) && options.includes_property(details, p))
+                    // The API takes the names it documents, whatever the definitions call them
+                    .map(|p| p.name.clone())
+                    .collect();
+                let select_pointer = pointer(&["paths", path, method_key(&method), "parameters", &parameters.len().to_string()]);
+                annotations.insert(select_pointer.clone(), "items", json!({ "type": "string", "enum": selectable }));
+                annotations.insert(select_pointer, "collectionFormat", json!("csv"));
+                parameters.push(ParameterOrRef::Parameter {
+                    name: "$select".to_owned(),
+                    location: "query".to_owned(),
+                    required: Some(false),
+                    schema: None,
+                    unique_items: Some(true),
+                    param_type: Some("array".to_owned()),
+                    format: None,
+                    description: Some("Properties to return".to_owned()),
+                });
+            } else if details.query_options.contains(&QueryOption::Select) {
+                parameters.push(ParameterOrRef::Ref {
+                    ref_path: "#/parameters/select".to_owned()
+                });
+            }
+            let expandable: Vec<String> = details.navigation_properties().iter()
+                .filter(|p| p.methods.contains(&Method::Get) && options.includes_property(details, p))
+                .map(|p| p.name.clone())
+                .collect();
+            if !expandable.is_empty() && details.query_options.contains(&QueryOption::Expand) {
+                // The relations differ per endpoint, so $expand can't be shared
+                let expand_pointer = pointer(&["paths", path, method_key(&method), "parameters", &parameters.len().to_string()]);
+                annotations.insert(expand_pointer.clone(), "items", json!({ "type": "string", "enum": expandable }));
+                annotations.insert(expand_pointer, "collectionFormat", json!("csv"));
+                parameters.push(ParameterOrRef::Parameter {
+                    name: "$expand".to_owned(),
+                    location: "query".to_owned(),
+                    required: Some(false),
+                    schema: None,
+                    unique_items: Some(true),
+                    param_type: Some("array".to_owned()),
+                    format: None,
+                    description: Some("Related entities to include inline".to_owned()),
+                });
+            }
+            // Paging only applies to collections
+            let collection = !details.singleton;
+            if collection && details.query_options.contains(&QueryOption::OrderBy) {
+                parameters.push(ParameterOrRef::Ref {
+                    ref_path: "#/parameters/order_by".to_owned()
+                });
+            }
+            if collection && details.query_options.contains(&QueryOption::Top) {
+                // The page size cap differs per endpoint, so $top can't be shared
+                let max_page_size = options.max_page_size(details);
+                let top_pointer = pointer(&["paths", path, method_key(&method), "parameters", &parameters.len().to_string()]);
+                annotations.insert(top_pointer.clone(), "minimum", json!(1));
+                annotations.insert(top_pointer, "maximum", json!(max_page_size));
+                parameters.push(ParameterOrRef::Parameter {
+                    name: "$top".to_owned(),
+                    location: "query".to_owned(),
+                    required: Some(false),
+                    schema: None,
+                    unique_items: None,
+                    param_type: Some("integer".to_owned()),
+                    format: Some("int32".to_owned()),
+                    description: Some(format!("Number of records to return, at most {} per page", max_page_size)),
+                });
+            }
+            if collection && details.query_options.contains(&QueryOption::Skip) {
+                parameters.push(ParameterOrRef::Ref {
+                    ref_path: "#/parameters/skip".to_owned()
+                });
+            }
+            if collection && details.query_options.contains(&QueryOption::InlineCount) {
+                parameters.push(ParameterOrRef::Ref {
+                    ref_path: "#/parameters/inlinecount".to_owned()
+                });
+            }
         }
         if method == Method::Post || method == Method::Put {
-            let def_suffix = match method {
-                Method::Post => "Post",
-                Method::Put => "Put",
+            let kind = match method {
+                Method::Post => DefinitionKind::Post,
+                Method::Put => DefinitionKind::Put,
                 // We checked this in the if guard
                 _ => unreachable!()
             };
+            if let Some(example) = example {
+                // Swagger 2.0 has no examples for bodies, tools understand the `x-examples` extension
+                let body_pointer = pointer(&["paths", path, method_key(&method), "parameters", &parameters.len().to_string()]);
+                annotations.insert(body_pointer, "x-examples", json!({ "application/json": envelop_example(example, Envelope::Bare) }));
+            }
             parameters.push(ParameterOrRef::Parameter {
                 name: "body".to_owned(),
                 location: "body".to_owned(),
                 required: Some(true),
                 schema: Some(Schema {
-                    ref_path: Some(format!("#/definitions/{}{}", details.name, def_suffix)),
+                    ref_path: Some(options.definition_ref(&details.name, kind)),
                     ..Schema::default()
                 }),
                 unique_items: None,
@@ -107,19 +605,34 @@ fn build_operation<'a>(method: Method, details: &'a EndpointDetails) -> Option<O
             })
         }
         if method == Method::Put || method == Method::Delete {
-            parameters.push(ParameterOrRef::Parameter {
-                name: "id".to_owned(),
-                location: "path".to_owned(),
-                required: Some(true),
-                schema: None,
-                unique_items: None,
-                param_type: Some("string".to_owned()),
-                format: None,
-                description: Some("ID of the entity to modify/delete".to_owned()),
+            parameters.extend(key_path_parameters(details, options, "modify/delete"));
+        }
+        if (method == Method::Post || method == Method::Put) && options.prefer_header {
+            parameters.push(ParameterOrRef::Ref {
+                ref_path: "#/parameters/Prefer".to_owned()
+            });
+        }
+        if (method == Method::Put || method == Method::Delete) && options.concurrency_headers {
+            parameters.push(ParameterOrRef::Ref {
+                ref_path: "#/parameters/IfMatch".to_owned()
+            });
+        }
+
+        let mut description = if method == Method::Get && details.kind == EndpointKind::Sync {
+            Some(format!("Returns the records changed since a given {0}. Filter on `{0} gt <value>` and \
+                continue from the highest {0} received until no more records are returned.", SYNC_TIMESTAMP))
+        } else {
+            None
+        };
+        if let Some(remark) = details.method_remarks.get(&method_key(&method).to_uppercase()) {
+            description = Some(match description {
+                Some(d) => format!("{} {}", d, remark),
+                None => remark.clone(),
             });
         }
 
         Some(Operation {
+            description: description,
             responses: responses,
             parameters: Some(parameters),
             ..Operation::default()
@@ -130,139 +643,972 @@ fn build_operation<'a>(method: Method, details: &'a EndpointDetails) -> Option<O
     }
 }
 
-struct OpenApiType {
-    type_: String,
-    format: Option<String>,
+/// The path parameters of the keyed path, described as identifying the entity to `purpose`
+fn key_path_parameters(details: &EndpointDetails, options: &SpecOptions, purpose: &str) -> Vec<ParameterOrRef> {
+    key_parameters(details).into_iter().map(|(name, edm_type)| {
+        let openapi_type = OpenApiType::from_edm(&edm_type, options);
+        let description = if details.has_composite_key() {
+            format!("{} of the entity to {}", name, purpose)
+        } else {
+            format!("ID of the entity to {}", purpose)
+        };
+        ParameterOrRef::Parameter {
+            name: name,
+            location: "path".to_owned(),
+            required: Some(true),
+            schema: None,
+            unique_items: None,
+            param_type: Some(openapi_type.type_),
+            format: openapi_type.format,
+            description: Some(description),
+        }
+    }).collect()
+}
+
+/// Whether a single entity can be fetched from the keyed path
+fn has_single_get(endpoint: &EndpointDetails) -> bool {
+    endpoint.methods.contains(&Method::Get) && endpoint.kind == EndpointKind::Standard && !endpoint.singleton
+}
+
+/// Whether some GET answers with a single entity in `d` rather than a `d.results` array
+fn has_single_response(endpoint: &EndpointDetails) -> bool {
+    has_single_get(endpoint) || (endpoint.singleton && endpoint.methods.contains(&Method::Get))
+}
+
+/// Kind of the definition describing the successful response to `method` on the endpoint path
+fn response_definition(method: &Method, endpoint: &EndpointDetails) -> DefinitionKind {
+    if *method == Method::Get && endpoint.singleton {
+        DefinitionKind::SingleResponse
+    } else if *method == Method::Post {
+        DefinitionKind::PostResponse
+    } else {
+        DefinitionKind::Response
+    }
+}
+
+/// GET on the keyed path, returning a single entity
+fn build_single_get_operation(details: &EndpointDetails, path: &str, options: &SpecOptions, annotations: &mut Annotations) -> Option<Operation> {
+    if !has_single_get(details) {
+        return None;
+    }
+    if let Some(example) = details.examples.get("GET") {
+        annotate_response_example(path, "get", "200", example, Envelope::Single, options, annotations);
+    }
+    let mut responses = BTreeMap::new();
+    responses.insert("200".to_owned(), Response {
+        description: "Command successful".to_owned(),
+        schema: Some(Schema {
+            ref_path: Some(options.definition_ref(&details.name, DefinitionKind::SingleResponse)),
+            ..Schema::default()
+        })
+    });
+    insert_error_responses(&mut responses, options);
+    let mut parameters = Vec::new();
+    if details.query_options.contains(&QueryOption::Select) {
+        parameters.push(ParameterOrRef::Ref {
+            ref_path: "#/parameters/select".to_owned()
+        });
+    }
+    parameters.extend(key_path_parameters(details, options, "fetch"));
+    Some(Operation {
+        description: details.method_remarks.get("GET").cloned(),
+        responses: responses,
+        parameters: Some(parameters),
+        ..Operation::default()
+    })
+}
+
+/// GET on `$value` of the keyed path, answering the file contents of the entity raw instead
+/// of base64 encoded in JSON
+fn build_download_operation(details: &EndpointDetails, property: &Property, options: &SpecOptions) -> Option<Operation> {
+    if !has_single_get(details) {
+        return None;
+    }
+    let mut responses = BTreeMap::new();
+    responses.insert("200".to_owned(), Response {
+        description: format!("Contents of the {}", property.name),
+        schema: Some(Schema {
+            schema_type: Some("file".to_owned()),
+            ..Schema::default()
+        })
+    });
+    insert_error_responses(&mut responses, options);
+    Some(Operation {
+        summary: Some(format!("Download the {} of {}", property.name, options.endpoint_name(details))),
+        produces: Some(vec!["application/octet-stream".to_owned()]),
+        responses: responses,
+        parameters: Some(key_path_parameters(details, options, "download")),
+        ..Operation::default()
+    })
+}
+
+/// PUT on `$value` of the keyed path, replacing the file contents of the entity by the raw body
+fn build_upload_operation(details: &EndpointDetails, property: &Property, options: &SpecOptions) -> Option<Operation> {
+    if !details.methods.contains(&Method::Put) {
+        return None;
+    }
+    let mut responses = BTreeMap::new();
+    responses.insert("204".to_owned(), Response {
+        description: format!("{} replaced", property.name),
+        schema: None
+    });
+    insert_error_responses(&mut responses, options);
+    let mut parameters = vec![ParameterOrRef::Parameter {
+        name: "body".to_owned(),
+        location: "body".to_owned(),
+        required: Some(true),
+        schema: Some(Schema {
+            schema_type: Some("string".to_owned()),
+            format: Some("binary".to_owned()),
+            ..Schema::default()
+        }),
+        unique_items: None,
+        param_type: None,
+        format: None,
+        description: Some(format!("Contents of the {}", property.name)),
+    }];
+    parameters.extend(key_path_parameters(details, options, "upload to"));
+    Some(Operation {
+        summary: Some(format!("Upload the {} of {}", property.name, options.endpoint_name(details))),
+        consumes: Some(vec!["application/octet-stream".to_owned()]),
+        responses: responses,
+        parameters: Some(parameters),
+        ..Operation::default()
+    })
+}
+
+/// The path parameters addressing a single entity. A single key is always called `id`,
+/// composite keys use the names of their properties.
+fn key_parameters(endpoint: &EndpointDetails) -> Vec<(String, EdmType)> {
+    let keys = endpoint.keys();
+    match keys.len() {
+        // Undocumented keys are most likely guids
+        0 => vec![("id".to_owned(), EdmType::Guid)],
+        1 => vec![("id".to_owned(), keys[0].edm_type.clone())],
+        _ => keys.iter().map(|k| (k.name.clone(), k.edm_type.clone())).collect(),
+    }
+}
+
+/// OData literal for a key of the given type, wrapping the path template `{name}`
+fn key_literal(name: &str, edm_type: &EdmType) -> String {
+    match *edm_type {
+        EdmType::Guid => format!("guid'{{{}}}'", name),
+        EdmType::DateTime => format!("datetime'{{{}}}'", name),
+        EdmType::Int64 => format!("{{{}}}L", name),
+        EdmType::Decimal => format!("{{{}}}M", name),
+        EdmType::Double => format!("{{{}}}d", name),
+        EdmType::Single => format!("{{{}}}f", name),
+        EdmType::Boolean | EdmType::Byte | EdmType::SByte | EdmType::Int16 | EdmType::Int32 => format!("{{{}}}", name),
+        _ => format!("'{{{}}}'", name),
+    }
+}
+
+/// The `(...)` suffix addressing a single entity, e.g. `(guid'{id}')` or `(Code='{Code}',Year={Year})`
+fn key_segment(endpoint: &EndpointDetails) -> String {
+    let parameters = key_parameters(endpoint);
+    let literals: Vec<String> = if parameters.len() == 1 {
+        vec![key_literal(&parameters[0].0, &parameters[0].1)]
+    } else {
+        parameters.iter().map(|&(ref name, ref edm_type)| format!("{}={}", name, key_literal(name, edm_type))).collect()
+    };
+    format!("({})", literals.join(","))
+}
+
+fn insert_error_responses(responses: &mut BTreeMap<String, Response>, options: &SpecOptions) {
+    let error_ref = format!("#/definitions/{}", options.definition_naming.error());
+    responses.insert("400".to_owned(), Response {
+        description: "Bad request (syntax invalid)".to_owned(),
+        schema: None
+    });
+    responses.insert("401".to_owned(), Response {
+        description: "Unauthorized".to_owned(),
+        schema: None
+    });
+    responses.insert("404".to_owned(), Response {
+        description: "Not found".to_owned(),
+        schema: None
+    });
+    responses.insert("500".to_owned(), Response {
+        description: "Error".to_owned(),
+        schema: Some(Schema {
+            ref_path: Some(error_ref.clone()),
+            ..Schema::default()
+        })
+    });
+    // Whatever else goes wrong is answered with an OData error as well
+    responses.insert("default".to_owned(), Response {
+        description: "Unexpected error".to_owned(),
+        schema: Some(Schema {
+            ref_path: Some(error_ref.clone()),
+            ..Schema::default()
+        })
+    });
+    for status in options.extra_error_statuses() {
+        // Listing one of the statuses every operation has keeps its description
+        if responses.contains_key(&status.to_string()) {
+            continue;
+        }
+        let description = match status {
+            403 => "Forbidden, the token lacks the scope or the user the rights",
+            429 => "Too many requests, the rate limit is exceeded",
+            503 => "Service unavailable, e.g. during maintenance",
+            _ => "Error",
+        };
+        responses.insert(status.to_string(), Response {
+            description: description.to_owned(),
+            schema: Some(Schema {
+                ref_path: Some(error_ref.clone()),
+                ..Schema::default()
+            })
+        });
+    }
+}
+
+/// Functions are invoked by posting their parameters
+fn build_function_operation(endpoint: &EndpointDetails, options: &SpecOptions) -> Operation {
+    let mut responses = BTreeMap::new();
+    let has_result = endpoint.methods.contains(&Method::Get) || endpoint.methods.contains(&Method::Post);
+    responses.insert("200".to_owned(), Response {
+        description: "Function executed".to_owned(),
+        schema: if has_result {
+            Some(Schema {
+                ref_path: Some(options.definition_ref(&endpoint.name, DefinitionKind::Response)),
+                ..Schema::default()
+            })
+        } else {
+            None
+        },
+    });
+    insert_error_responses(&mut responses, options);
+
+    let mut parameters = Vec::new();
+    let properties = BTreeMap::from_iter(endpoint.function_parameters.iter().map(|p| {
+        let openapi_type = OpenApiType::from_edm(&p.edm_type, options);
+        (p.name.clone(), Schema {
+            schema_type: Some(openapi_type.type_),
+            format: openapi_type.format,
+            ..Schema::default()
+        })
+    }));
+    parameters.push(ParameterOrRef::Parameter {
+        name: "body".to_owned(),
+        location: "body".to_owned(),
+        required: Some(true),
+        schema: Some(Schema {
+            schema_type: Some("object".to_owned()),
+            required: Some(endpoint.function_parameters.iter().map(|p| p.name.clone()).collect()),
+            properties: Some(properties),
+            ..Schema::default()
+        }),
+        unique_items: None,
+        param_type: None,
+        format: None,
+        description: Some("Parameters of the function".to_owned()),
+    });
+
+    Operation {
+        responses: responses,
+        parameters: Some(parameters),
+        ..Operation::default()
+    }
+}
+
+/// The `type` and `format` of a schema
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenApiType {
+    pub type_: String,
+    pub format: Option<String>,
 }
 
 impl OpenApiType {
     pub fn new<T: Into<String>>(type_: T, format: Option<T>) -> OpenApiType {
         OpenApiType {type_: type_.into(), format: format.and_then(|f| Some(f.into()))}
     }
+
+    fn from_edm(edm: &EdmType, options: &SpecOptions) -> OpenApiType {
+        match options.type_mapper {
+            Some(ref mapper) => mapper.map_type(edm, options),
+            None => DefaultTypeMapper.map_type(edm, options),
+        }
+    }
+}
+
+/// Maps the Edm types of properties and parameters onto OpenAPI types. Set one as
+/// `SpecOptions::type_mapper` to replace the default mapping. Patterns, ranges and
+/// `x-edm-type` are added to suit the types it answers.
+pub trait TypeMapper: fmt::Debug {
+    fn map_type(&self, edm: &EdmType, options: &SpecOptions) -> OpenApiType;
 }
 
-impl From<EdmType> for OpenApiType {
-    fn from(edm: EdmType) -> OpenApiType {
-        let (t, f) = match edm {
+/// The mapping following `format_mapping`, `binary_format` and `byte_format`, which other
+/// mappers can fall back on
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultTypeMapper;
+
+impl TypeMapper for DefaultTypeMapper {
+    fn map_type(&self, edm: &EdmType, options: &SpecOptions) -> OpenApiType {
+        if options.format_mapping == FormatMapping::Standard {
+            if let Some((t, f)) = standard_format(edm) {
+                return OpenApiType::new(t, f);
+            }
+        }
+        let (t, f) = match *edm {
             EdmType::Null => ("null", None),
-            EdmType::Binary => ("string", Some("edm-binary")),
+            EdmType::Binary => match options.binary_format {
+                BinaryFormat::Edm => ("string", Some("edm-binary")),
+                BinaryFormat::Base64 => ("string", Some("byte")),
+            },
             EdmType::Boolean => ("boolean", None),
-            EdmType::Byte => ("string", Some("edm-byte")),
+            EdmType::Byte => match options.byte_format {
+                ByteFormat::Edm => ("string", Some("edm-byte")),
+                ByteFormat::Integer => ("integer", Some("int32")),
+            },
             EdmType::DateTime => ("string", Some("edm-datetime")),
             EdmType::Decimal => ("string", Some("edm-decimal")),
             EdmType::Double => ("number", Some("double")),
             EdmType::Single => ("number", Some("float")),
+            EdmType::Guid if options.guid_pattern => ("string", Some("uuid")),
             EdmType::Guid => ("string", Some("guid")),
             EdmType::Int16 => ("integer", Some("int16")),
             EdmType::Int32 => ("integer", Some("int32")),
             EdmType::Int64 => ("integer", Some("int64")),
-            EdmType::SByte => ("integer", Some("edm-int8")),
+            EdmType::SByte => match options.byte_format {
+                ByteFormat::Edm => ("integer", Some("edm-int8")),
+                ByteFormat::Integer => ("integer", Some("int32")),
+            },
             EdmType::String => ("string", None),
             EdmType::Time => ("string", Some("edm-time")),
             EdmType::DateTimeOffset => ("string", Some("edm-date-time-offset")),
+            EdmType::Collection(_) => ("array", None),
+            EdmType::Complex(_) => ("object", None),
         };
         OpenApiType::new(t, f)
     }
 }
 
-fn build_definition(method: Method, endpoint: &EndpointDetails) -> Schema {
-    let properties = BTreeMap::from_iter(endpoint.properties.iter()
-        .filter(|p| p.methods.contains(&method))
-        .map(|p| {
-            let openapi_type = OpenApiType::from(p.edm_type.clone());
-            (p.name.clone(), Schema {
-                description: p.description.clone(),
-                schema_type: Some(openapi_type.type_),
-                format: openapi_type.format,
-                ..Schema::default()
-            })
-        }));
-    // If the method is Post of Put, all keys are required properties
-    let required_properties = if method == Method::Post || method == Method::Put {
-        Some(endpoint.properties.iter().filter(|p| p.key).map(|p| p.name.clone()).collect())
-    } else {
-        None
-    };
-    let schema = Schema {
-        schema_type: Some("object".to_owned()),
-        required: required_properties,
-        properties: Some(properties),
+/// Type and standard format of the Edm types whose Edm mapping uses a custom format
+fn standard_format(edm: &EdmType) -> Option<(&'static str, Option<&'static str>)> {
+    Some(match *edm {
+        EdmType::Binary => ("string", Some("byte")),
+        EdmType::Byte | EdmType::SByte | EdmType::Int16 => ("integer", Some("int32")),
+        EdmType::DateTime | EdmType::DateTimeOffset => ("string", Some("date-time")),
+        EdmType::Decimal => ("string", None),
+        EdmType::Guid => ("string", Some("uuid")),
+        EdmType::Time => ("string", Some("duration")),
+        _ => return None,
+    })
+}
+
+/// A GUID in the 8-4-4-4-12 hexadecimal notation Exact uses
+const GUID_PATTERN: &'static str = r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$";
+
+/// Decimals are serialized as strings to keep their precision
+const DECIMAL_PATTERN: &'static str = r"^-?[0-9]+(\.[0-9]+)?$";
+
+/// The schema of a value of the given type, collections become arrays of their element schema
+pub(crate) fn edm_schema(edm: &EdmType, options: &SpecOptions) -> Schema {
+    let openapi_type = OpenApiType::from_edm(edm, options);
+    Schema {
+        schema_type: Some(openapi_type.type_),
+        format: openapi_type.format,
+        items: match *edm {
+            EdmType::Collection(ref element) => Some(Box::new(edm_schema(element, options))),
+            _ => None,
+        },
         ..Schema::default()
-    };
-    if method == Method::Get {
-        let mut data = BTreeMap::new();
-        let mut d = BTreeMap::new();
-        d.insert("results".to_owned(), Schema {
-            schema_type: Some("array".to_owned()),
-            items: Some(Box::new(schema)),
-            ..Schema::default()
-        });
-        data.insert("d".to_owned(), Schema {
-            schema_type: Some("object".to_owned()),
-            properties: Some(d),
-            ..Schema::default()
-        });
-        let required = vec!["d".to_owned()];
-        Schema {
-            schema_type: Some("object".to_owned()),
-            required: Some(required),
-            properties: Some(data),
-            ..Schema::default()
+    }
+}
+
+/// Records the original type of properties whose mapping codegen tools tend to trip over,
+/// so consumers can tell which convention was used. What the type it is mapped onto leaves
+/// unsaid, like the digits of a decimal string or the range of a small integer, is added.
+fn annotate_edm_oddities(edm: &EdmType, options: &SpecOptions, pointer: String, annotations: &mut Annotations) {
+    let mapped = OpenApiType::from_edm(edm, options);
+    let format = mapped.format.as_ref().map(|f| f.as_str());
+    if *edm == EdmType::Guid && options.guid_pattern && mapped.type_ == "string" {
+        annotations.insert(pointer.clone(), "pattern", json!(GUID_PATTERN));
+    }
+    match *edm {
+        EdmType::Collection(ref element) => {
+            annotate_edm_oddities(element, options, pointer + "/items", annotations);
+            return;
+        },
+        // The docs don't describe the members of complex types, so at least name them
+        EdmType::Complex(ref name) => {
+            annotations.insert(pointer, "x-edm-type", json!(name));
+            return;
+        },
+        EdmType::Binary | EdmType::Byte | EdmType::SByte => {
+            annotations.insert(pointer.clone(), "x-edm-type", json!(edm.name()));
+        },
+        _ if standard_format(edm).is_some() && !names_edm_type(format) => {
+            annotations.insert(pointer.clone(), "x-edm-type", json!(edm.name()));
+        },
+        _ => return,
+    }
+    if *edm == EdmType::Decimal && mapped.type_ == "string" && format.is_none() {
+        annotations.insert(pointer.clone(), "pattern", json!(DECIMAL_PATTERN));
+    }
+    if let Some((minimum, maximum)) = edm_range(edm, &mapped) {
+        annotations.insert(pointer.clone(), "minimum", json!(minimum));
+        annotations.insert(pointer, "maximum", json!(maximum));
+    }
+}
+
+/// Whether the format is one of those named after the Edm types, like `edm-datetime`
+fn names_edm_type(format: Option<&str>) -> bool {
+    format.map_or(false, |f| f.starts_with("edm-") || f == "guid" || f == "int16")
+}
+
+/// The range of the small integer types, when they are mapped onto an integer whose format
+/// doesn't tell it
+fn edm_range(edm: &EdmType, mapped: &OpenApiType) -> Option<(i64, i64)> {
+    if mapped.type_ != "integer" || names_edm_type(mapped.format.as_ref().map(|f| f.as_str())) {
+        return None;
+    }
+    match *edm {
+        EdmType::Byte => Some((0, 255)),
+        EdmType::SByte => Some((-128, 127)),
+        EdmType::Int16 => Some((-32768, 32767)),
+        _ => None,
+    }
+}
+
+/// Documents the representation chosen for the Edm types that have no obvious OpenAPI
+/// counterpart, of the ones the endpoints use. `None` if they use none of them.
+fn build_edm_conventions(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Option<Value> {
+    let mut oddities = vec![EdmType::Binary, EdmType::Byte, EdmType::SByte];
+    if options.format_mapping == FormatMapping::Standard {
+        oddities.extend(vec![EdmType::DateTime, EdmType::DateTimeOffset, EdmType::Decimal, EdmType::Guid,
+            EdmType::Int16, EdmType::Time]);
+    }
+    let used: Vec<&EdmType> = endpoints.iter()
+        .flat_map(|e| e.properties.iter().map(|p| &p.edm_type).chain(e.function_parameters.iter().map(|p| &p.edm_type)))
+        .map(element_type)
+        .collect();
+    let mut conventions = Map::new();
+    for edm in oddities.iter().filter(|edm| used.contains(edm)) {
+        let openapi_type = OpenApiType::from_edm(edm, options);
+        conventions.insert(edm.name(), json!({
+            "type": openapi_type.type_,
+            "format": openapi_type.format,
+        }));
+    }
+    if conventions.is_empty() { None } else { Some(Value::Object(conventions)) }
+}
+
+/// The type of the elements of collections, the type itself otherwise
+fn element_type(edm: &EdmType) -> &EdmType {
+    match *edm {
+        EdmType::Collection(ref element) => element_type(element),
+        ref edm => edm,
+    }
+}
+
+/// How a definition wraps the entity it describes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Envelope {
+    /// The entity itself, as in request bodies
+    Bare,
+    /// `{"d": {"results": [...]}}`, as returned when listing
+    Collection,
+    /// `{"d": {...}}`, as returned for a single entity
+    Single,
+    /// `[...]`, a list without the OData wrapping
+    Array,
+}
+
+impl Envelope {
+    /// The envelope left once a gateway strips the OData `d` and `results` wrappers
+    fn flattened(self) -> Envelope {
+        match self {
+            Envelope::Collection => Envelope::Array,
+            Envelope::Single => Envelope::Bare,
+            other => other,
         }
     }
-    else {
-        schema
+}
+
+/// Shows the sample from the docs as the successful response of the operation
+fn annotate_response_example(path: &str, method: &str, status: &str, sample: &Value, envelope: Envelope, options: &SpecOptions, annotations: &mut Annotations) {
+    let envelope = if options.flatten_envelope { envelope.flattened() } else { envelope };
+    annotations.insert(pointer(&["paths", path, method, "responses", status]), "examples",
+        json!({ "application/json": envelop_example(sample, envelope) }));
+}
+
+/// A sample from the docs wrapped as `envelope` says, whether the docs show it with the OData
+/// wrapping or without
+fn envelop_example(sample: &Value, envelope: Envelope) -> Value {
+    let unwrapped = sample.pointer("/d/results").or_else(|| sample.get("d")).unwrap_or(sample);
+    let entities = match *unwrapped {
+        Value::Array(ref entities) => entities.clone(),
+        ref entity => vec![entity.clone()],
+    };
+    let entity = entities.first().cloned().unwrap_or_else(|| json!({}));
+    match envelope {
+        Envelope::Bare => entity,
+        Envelope::Array => Value::Array(entities),
+        Envelope::Collection => json!({ "d": { "results": entities } }),
+        Envelope::Single => json!({ "d": entity }),
+    }
+}
+
+/// Pointer to the object holding the properties of the given definition
+fn properties_pointer(envelope: Envelope, definition: &str) -> Vec<String> {
+    let mut segments = vec!["definitions".to_owned(), definition.to_owned()];
+    let wrapping: &[&str] = match envelope {
+        Envelope::Bare => &[],
+        Envelope::Collection => &["properties", "d", "properties", "results", "items"],
+        Envelope::Single => &["properties", "d"],
+        Envelope::Array => &["items"],
+    };
+    for s in wrapping {
+        segments.push(s.to_string());
+    }
+    segments.push("properties".to_owned());
+    segments
+}
+
+/// Summary of an entity for the top of its definitions: what the docs say it is,
+/// what identifies it and how many records come back at once
+fn describe_entity(endpoint: &EndpointDetails, options: &SpecOptions) -> String {
+    let mut sentences = Vec::new();
+    if let Some(ref description) = endpoint.description {
+        sentences.push(description.clone());
+    }
+    let keys: Vec<String> = endpoint.keys().iter().map(|p| format!("`{}`", options.property_name(endpoint, p))).collect();
+    if !keys.is_empty() {
+        sentences.push(format!("Identified by {}.", keys.join(" and ")));
+    }
+    if endpoint.singleton {
+        sentences.push("Returned as a single object.".to_owned());
+    } else if endpoint.kind != EndpointKind::Function {
+        sentences.push(format!("Lists return at most {} records per page.", options.max_page_size(endpoint)));
+    }
+    sentences.join(" ")
+}
+
+fn build_definition(method: Method, endpoint: &EndpointDetails, name: &str, options: &SpecOptions, annotations: &mut Annotations) -> Schema {
+    let envelope = if method == Method::Get { Envelope::Collection } else { Envelope::Bare };
+    build_enveloped_definition(method, envelope, endpoint, name, options, annotations)
+}
+
+/// The definition of the entity as used with `method`, wrapped as given. When composing
+/// definitions it refers to the shared `{Endpoint}` definition instead of repeating the properties,
+/// adding the properties only used with `method`.
+fn build_enveloped_definition(method: Method, envelope: Envelope, endpoint: &EndpointDetails, name: &str, options: &SpecOptions, annotations: &mut Annotations) -> Schema {
+    let envelope = if options.flatten_envelope { envelope.flattened() } else { envelope };
+    let description = Some(describe_entity(endpoint, options));
+    let counted = endpoint.query_options.contains(&QueryOption::InlineCount);
+    if !options.compose_definitions {
+        let schema = build_entity(Some(method.clone()), endpoint, |p| p.methods.contains(&method), properties_pointer(envelope, name), options, annotations);
+        return wrap_entity(envelope, schema, description, counted);
+    }
+    let base = Schema {
+        ref_path: Some(options.definition_ref(&endpoint.name, DefinitionKind::Entity)),
+        ..Schema::default()
+    };
+    let additional = endpoint.properties.iter()
+        .any(|p| p.methods.contains(&method) && !in_base_definition(endpoint, p) && options.includes_property(endpoint, p));
+    let required = if envelope == Envelope::Bare { required_properties(&method, endpoint, options) } else { None };
+    if !additional && envelope != Envelope::Bare {
+        return wrap_entity(envelope, base, description, counted);
+    }
+    let mut parts = vec![serde_json::to_value(&base).expect("Schema serializes")];
+    if additional {
+        // Annotated apart, as `allOf` is an annotation itself
+        let mut part_annotations = Annotations::new();
+        let part = build_entity(Some(method.clone()), endpoint, |p| p.methods.contains(&method) && !in_base_definition(endpoint, p),
+            vec!["properties".to_owned()], options, &mut part_annotations);
+        let mut part = serde_json::to_value(&Schema { required: required, ..part }).expect("Schema serializes");
+        part_annotations.apply(&mut part).expect("Annotations of the part apply");
+        parts.push(part);
+    } else if let Some(required) = required {
+        parts.push(json!({ "type": "object", "required": required }));
+    }
+    let mut entity_pointer = properties_pointer(envelope, name);
+    entity_pointer.pop();
+    annotations.insert(pointer(&entity_pointer), "allOf", json!(parts));
+    wrap_entity(envelope, Schema::default(), description, counted)
+}
+
+/// Whether the property is in the shared `{Endpoint}` definition: whether it is used with
+/// every method there are definitions for, so the definitions of each method can refer to it
+fn in_base_definition(endpoint: &EndpointDetails, property: &Property) -> bool {
+    [Method::Post, Method::Put].iter()
+        .filter(|m| endpoint.methods.contains(m))
+        .chain(Some(&Method::Get))
+        .all(|m| property.methods.contains(m))
+}
+
+/// The shared definition of an entity with the properties all methods use, which the per-method
+/// definitions are composed of
+fn build_base_definition(endpoint: &EndpointDetails, options: &SpecOptions, annotations: &mut Annotations) -> Schema {
+    let name = options.definition_name(&endpoint.name, DefinitionKind::Entity);
+    let properties_pointer = properties_pointer(Envelope::Bare, &name);
+    Schema {
+        description: Some(describe_entity(endpoint, options)),
+        ..build_entity(None, endpoint, |p| in_base_definition(endpoint, p), properties_pointer, options, annotations)
+    }
+}
+
+/// The object holding the `selected` properties, as used with `method` or with any method if
+/// not given
+fn build_entity<F: Fn(&Property) -> bool>(method: Option<Method>, endpoint: &EndpointDetails, selected: F, properties_pointer: Vec<String>, options: &SpecOptions, annotations: &mut Annotations) -> Schema {
+    let writable = endpoint.methods.contains(&Method::Post) || endpoint.methods.contains(&Method::Put);
+    let read = method.as_ref().map_or(true, |m| *m == Method::Get);
+    let mut properties = BTreeMap::from_iter(endpoint.properties.iter()
+        .filter(|p| selected(p) && options.includes_property(endpoint, p))
+        .map(|p| {
+            let name = options.property_name(endpoint, p);
+            let mut property_pointer = properties_pointer.clone();
+            property_pointer.push(name.clone());
+            annotate_edm_oddities(&p.edm_type, options, pointer(&property_pointer), annotations);
+            if name != p.name {
+                annotations.insert(pointer(&property_pointer), "x-exact-name", json!(p.name));
+            }
+            if p.key {
+                annotations.insert(pointer(&property_pointer), "x-exact-key", json!(true));
+            }
+            if p.nullable {
+                annotations.insert(pointer(&property_pointer), "x-nullable", json!(true));
+            }
+            if read && writable && !p.methods.contains(&Method::Post) && !p.methods.contains(&Method::Put) {
+                // Set by Exact, like `Created` or `Modified`
+                annotations.insert(pointer(&property_pointer), "readOnly", json!(true));
+            }
+            if options.annotate_property_methods {
+                let methods: Vec<String> = p.methods.iter().map(|m| method_key(m).to_uppercase()).collect();
+                annotations.insert(pointer(&property_pointer), "x-methods", json!(methods));
+            }
+            let mut schema = Schema {
+                description: p.description.clone(),
+                ..edm_schema(&p.edm_type, options)
+            };
+            annotate_limits(p, &schema, options, pointer(&property_pointer), annotations);
+            let values = enum_values(p, &schema);
+            if let Some(default) = default_value(p, &schema, values.as_ref()) {
+                annotations.insert(pointer(&property_pointer), "default", default);
+            }
+            match values {
+                Some(ref values) if schema.schema_type.as_ref().map_or(false, |t| t == "string") => {
+                    schema.enum_values = Some(values.iter().filter_map(|v| v.as_str()).map(|v| v.to_owned()).collect());
+                },
+                // `Schema` can only hold string enums
+                Some(values) => annotations.insert(pointer(&property_pointer), "enum", json!(values)),
+                None => {},
+            }
+            (name, schema)
+        }));
+    if read && options.closed_schemas {
+        // Closed schemas must allow what OData adds to every entity it returns
+        properties.insert("__metadata".to_owned(), metadata_schema());
+    }
+    Schema {
+        schema_type: Some("object".to_owned()),
+        required: method.and_then(|m| required_properties(&m, endpoint, options)),
+        properties: Some(properties),
+        ..Schema::default()
+    }
+}
+
+/// The `__metadata` OData adds to the entities in responses
+fn metadata_schema() -> Schema {
+    object_schema(vec![
+        ("uri", string_schema("Location of the entity", None)),
+        ("type", string_schema("Server side type of the entity, e.g. `Exact.Web.Api.Models.Account`", None)),
+        ("etag", string_schema("Version of the entity, for concurrency checks", None)),
+    ], &["uri", "type"], "OData metadata of the entity")
+}
+
+/// Constrains the property to the length, precision and scale the docs give. Decimals
+/// serialized as strings are limited through their `pattern` instead. The scale of numbers
+/// isn't enforced, `multipleOf` with a fraction suffers from floating point rounding.
+fn annotate_limits(property: &Property, schema: &Schema, options: &SpecOptions, pointer: String, annotations: &mut Annotations) {
+    let schema_type = schema.schema_type.as_ref().map(|t| t.as_str());
+    if let (Some(max_length), Some("string")) = (property.max_length, schema_type) {
+        if property.edm_type == EdmType::String {
+            annotations.insert(pointer.clone(), "maxLength", json!(max_length));
+        }
+    }
+    let precision = match property.precision {
+        Some(precision) => precision,
+        None => return,
+    };
+    let scale = property.scale.unwrap_or(0).min(precision);
+    let integer_digits = precision - scale;
+    match schema_type {
+        Some("integer") => {
+            let documented = if integer_digits <= 18 {
+                let largest = 10i64.pow(integer_digits) - 1;
+                Some((-largest, largest))
+            } else {
+                None
+            };
+            // Both the documented precision and the range of the type apply
+            let range = match (documented, edm_range(&property.edm_type, &OpenApiType::from_edm(&property.edm_type, options))) {
+                (Some((minimum, maximum)), Some((lowest, highest))) => Some((minimum.max(lowest), maximum.min(highest))),
+                (documented, range) => documented.or(range),
+            };
+            if let Some((minimum, maximum)) = range {
+                annotations.insert(pointer.clone(), "minimum", json!(minimum));
+                annotations.insert(pointer, "maximum", json!(maximum));
+            }
+        },
+        Some("number") => {
+            // The largest number of `integer_digits` digits stays below the next power of ten
+            annotations.insert(pointer.clone(), "minimum", power_of_ten(integer_digits as i32, -1));
+            annotations.insert(pointer.clone(), "exclusiveMinimum", json!(true));
+            annotations.insert(pointer.clone(), "maximum", power_of_ten(integer_digits as i32, 1));
+            annotations.insert(pointer, "exclusiveMaximum", json!(true));
+        },
+        Some("string") if property.edm_type == EdmType::Decimal => {
+            let integer_part = if integer_digits == 0 { "0".to_owned() } else { format!("[0-9]{{1,{}}}", integer_digits) };
+            let pattern = if scale == 0 {
+                format!("^-?{}$", integer_part)
+            } else {
+                format!(r"^-?{}(\.[0-9]{{1,{}}})?$", integer_part, scale)
+            };
+            annotations.insert(pointer, "pattern", json!(pattern));
+        },
+        _ => {},
+    }
+}
+
+/// `sign * 10^exponent` as the closest JSON number, whole powers without a fraction
+fn power_of_ten(exponent: i32, sign: i64) -> Value {
+    if exponent >= 0 && exponent <= 18 {
+        json!(sign * 10i64.pow(exponent as u32))
+    } else {
+        json!(sign as f64 * format!("1e{}", exponent).parse::<f64>().expect("Valid float"))
+    }
+}
+
+/// The values the description limits the property to, typed like the property.
+/// Not an enumeration if any of them doesn't fit the type.
+fn enum_values(property: &Property, schema: &Schema) -> Option<Vec<Value>> {
+    if property.allowed_values.is_empty() {
+        return None;
+    }
+    property.allowed_values.iter().map(|allowed| typed_value(&allowed.value, schema)).collect()
+}
+
+/// The documented value as a JSON value of the type of the schema, if it fits
+fn typed_value(value: &str, schema: &Schema) -> Option<Value> {
+    match schema.schema_type.as_ref().map(|t| t.as_str()) {
+        Some("string") => Some(json!(value)),
+        Some("integer") => value.parse::<i64>().ok().map(|v| json!(v)),
+        Some("number") => value.parse::<f64>().ok().map(|v| json!(v)),
+        _ => None,
+    }
+}
+
+/// The documented default typed like the property, or the value of the enumeration it names,
+/// as in "Default: Open" for "1 = Open, 2 = Processed". Defaults that don't fit the type or
+/// enumeration are left out, as are those of GUIDs, dates and times, which are rarely literal.
+fn default_value(property: &Property, schema: &Schema, enum_values: Option<&Vec<Value>>) -> Option<Value> {
+    let default = match property.default {
+        Some(ref default) => default.trim(),
+        None => return None,
+    };
+    let value = match property.edm_type {
+        EdmType::Boolean => match default.to_lowercase().as_str() {
+            "true" => Some(json!(true)),
+            "false" => Some(json!(false)),
+            _ => None,
+        },
+        EdmType::Guid | EdmType::DateTime | EdmType::DateTimeOffset | EdmType::Time | EdmType::Binary |
+        EdmType::Collection(_) | EdmType::Complex(_) | EdmType::Null => None,
+        _ => typed_value(default, schema),
+    };
+    let named = property.allowed_values.iter()
+        .find(|allowed| allowed.description.eq_ignore_ascii_case(default))
+        .and_then(|allowed| typed_value(&allowed.value, schema));
+    match enum_values {
+        Some(values) => value.into_iter().chain(named).find(|v| values.contains(v)),
+        None => value,
+    }
+}
+
+/// The properties that must be sent with `method`. Creating requires the mandatory properties,
+/// keys are usually generated. Updates only change the properties sent, so require nothing.
+fn required_properties(method: &Method, endpoint: &EndpointDetails, options: &SpecOptions) -> Option<Vec<String>> {
+    if *method != Method::Post {
+        return None;
+    }
+    let required: Vec<String> = endpoint.properties.iter()
+        .filter(|p| p.mandatory && p.methods.contains(&Method::Post) && options.includes_property(endpoint, p))
+        .map(|p| options.property_name(endpoint, p))
+        .collect();
+    // JSON Schema doesn't allow empty `required` lists
+    if required.is_empty() { None } else { Some(required) }
+}
+
+/// Puts the entity schema in the envelope. Collections link to their next page in `__next`,
+/// and carry the total in `__count` if `counted`.
+fn wrap_entity(envelope: Envelope, schema: Schema, description: Option<String>, counted: bool) -> Schema {
+    let d = match envelope {
+        Envelope::Bare => return Schema { description: description, ..schema },
+        Envelope::Array => return Schema {
+            description: description,
+            schema_type: Some("array".to_owned()),
+            items: Some(Box::new(schema)),
+            ..Schema::default()
+        },
+        Envelope::Collection => {
+            let mut results = BTreeMap::new();
+            results.insert("results".to_owned(), Schema {
+                schema_type: Some("array".to_owned()),
+                items: Some(Box::new(schema)),
+                ..Schema::default()
+            });
+            results.insert("__next".to_owned(), Schema {
+                description: Some("Url of the next page, absent on the last page".to_owned()),
+                schema_type: Some("string".to_owned()),
+                ..Schema::default()
+            });
+            if counted {
+                // OData v2 serializes the count as a string
+                results.insert("__count".to_owned(), Schema {
+                    description: Some("Total number of records, only with `$inlinecount=allpages`".to_owned()),
+                    schema_type: Some("string".to_owned()),
+                    ..Schema::default()
+                });
+            }
+            Schema {
+                schema_type: Some("object".to_owned()),
+                required: Some(vec!["results".to_owned()]),
+                properties: Some(results),
+                ..Schema::default()
+            }
+        },
+        Envelope::Single => schema,
+    };
+    let mut data = BTreeMap::new();
+    data.insert("d".to_owned(), d);
+    Schema {
+        description: description,
+        schema_type: Some("object".to_owned()),
+        required: Some(vec!["d".to_owned()]),
+        properties: Some(data),
+        ..Schema::default()
     }
 }
 
-fn build_definitions<'a, T: Iterator<Item=&'a EndpointDetails>>(endpoints: T) -> Result<BTreeMap<String, Schema>> {
+fn build_definitions<'a, T: Iterator<Item=&'a EndpointDetails>>(endpoints: T, options: &SpecOptions, annotations: &mut Annotations) -> Result<BTreeMap<String, Schema>> {
     let mut definitions = BTreeMap::new();
-    definitions.insert("Error".to_owned(), build_error_schema());
+    definitions.insert(options.definition_naming.error(), build_error_schema());
     for endpoint in endpoints {
-        if endpoint.methods.contains(&Method::Get) || endpoint.methods.contains(&Method::Post) {
-            definitions.insert(format!("{}Response", endpoint.name), build_definition(Method::Get, endpoint));
+        if options.compose_definitions {
+            let definition = build_base_definition(endpoint, options, annotations);
+            definitions.insert(options.definition_name(&endpoint.name, DefinitionKind::Entity), definition);
+        }
+        let lists = endpoint.methods.contains(&Method::Get) && !endpoint.singleton;
+        // Functions answer with the collection envelope whichever method they are invoked with
+        let function = endpoint.kind == EndpointKind::Function && endpoint.methods.contains(&Method::Post);
+        if lists || function {
+            let name = options.definition_name(&endpoint.name, DefinitionKind::Response);
+            let definition = build_definition(Method::Get, endpoint, &name, options, annotations);
+            definitions.insert(name, definition);
+        }
+        if has_single_response(endpoint) {
+            let name = options.definition_name(&endpoint.name, DefinitionKind::SingleResponse);
+            let definition = build_enveloped_definition(Method::Get, Envelope::Single, endpoint, &name, options, annotations);
+            definitions.insert(name, definition);
         }
         if endpoint.methods.contains(&Method::Post) {
-            definitions.insert(format!("{}Post", endpoint.name), build_definition(Method::Post, endpoint));
+            let name = options.definition_name(&endpoint.name, DefinitionKind::Post);
+            let definition = build_definition(Method::Post, endpoint, &name, options, annotations);
+            definitions.insert(name, definition);
+        }
+        if endpoint.methods.contains(&Method::Post) && endpoint.kind != EndpointKind::Function {
+            // The created entity is echoed back with all its readable properties
+            let name = options.definition_name(&endpoint.name, DefinitionKind::PostResponse);
+            let definition = build_enveloped_definition(Method::Get, Envelope::Single, endpoint, &name, options, annotations);
+            definitions.insert(name, definition);
         }
         if endpoint.methods.contains(&Method::Put) {
-            definitions.insert(format!("{}Put", endpoint.name), build_definition(Method::Put, endpoint));
+            let name = options.definition_name(&endpoint.name, DefinitionKind::Put);
+            let definition = build_definition(Method::Put, endpoint, &name, options, annotations);
+            definitions.insert(name, definition);
+        }
+        for kind in ENDPOINT_DEFINITION_KINDS {
+            let name = options.definition_name(&endpoint.name, *kind);
+            if definitions.contains_key(&name) {
+                let title = definition_title(&name, &options.endpoint_name(endpoint), *kind);
+                annotations.insert(pointer(&["definitions", &name]), "title", json!(title));
+            }
         }
     }
+    annotations.insert(pointer(&["definitions", &options.definition_naming.error()]), "title", json!("Error"));
     Ok(definitions)
 }
 
-fn build_error_schema() -> Schema {
-    let mut error_properties = BTreeMap::new();
-    error_properties.insert("code".to_owned(), Schema {
-        schema_type: Some("string".to_owned()),
-        ..Schema::default()
-    });
-    let mut message_properties = BTreeMap::new();
-    message_properties.insert("value".to_owned(), Schema {
-        description: Some("Error cause".to_owned()),
-        schema_type: Some("string".to_owned()),
-        ..Schema::default()
-    });
-    error_properties.insert("message".to_owned(), Schema {
-        schema_type: Some("object".to_owned()),
-        properties: Some(message_properties),
-        ..Schema::default()
-    });
-    let mut error_property = BTreeMap::new();
-    error_property.insert("error".to_owned(), Schema {
+/// The kinds of definitions generated for an endpoint
+const ENDPOINT_DEFINITION_KINDS: &'static [DefinitionKind] = &[
+    DefinitionKind::Entity,
+    DefinitionKind::Response,
+    DefinitionKind::SingleResponse,
+    DefinitionKind::Post,
+    DefinitionKind::PostResponse,
+    DefinitionKind::Put,
+];
+
+/// The title code generators name the model after: the name of the definition, or of the
+/// endpoint for the entity itself, so it is a valid identifier wherever the name is
+fn definition_title(name: &str, endpoint: &str, kind: DefinitionKind) -> String {
+    match kind {
+        DefinitionKind::Entity => endpoint.to_owned(),
+        _ => name.to_owned(),
+    }
+}
+
+/// An object schema with the given properties, of which `required` must be present
+pub(crate) fn object_schema(properties: Vec<(&str, Schema)>, required: &[&str], description: &str) -> Schema {
+    Schema {
+        description: Some(description.to_owned()),
         schema_type: Some("object".to_owned()),
-        properties: Some(error_properties),
+        required: if required.is_empty() { None } else { Some(required.iter().map(|r| r.to_string()).collect()) },
+        properties: Some(BTreeMap::from_iter(properties.into_iter().map(|(name, schema)| (name.to_owned(), schema)))),
         ..Schema::default()
-    });
+    }
+}
+
+pub(crate) fn string_schema(description: &str, format: Option<&str>) -> Schema {
     Schema {
-        schema_type: Some("object".to_owned()),
-        properties: Some(error_property),
+        description: Some(description.to_owned()),
+        schema_type: Some("string".to_owned()),
+        format: format.map(|f| f.to_owned()),
         ..Schema::default()
     }
 }
 
-fn build_parameters() -> BTreeMap<String, Parameter> {
+/// The OData v2 error body, with the `innererror` details Exact includes
+fn build_error_schema() -> Schema {
+    let message = object_schema(vec![
+        ("lang", string_schema("Language of the message, e.g. `en-US`", None)),
+        ("value", string_schema("Error cause", None)),
+    ], &["lang", "value"], "Human readable description of the error");
+    // Exact leaves out whatever it has no details for
+    let internal_exception = object_schema(vec![
+        ("message", string_schema("Message of the underlying exception", None)),
+        ("type", string_schema("Type of the underlying exception", None)),
+    ], &[], "The exception causing the error");
+    let inner_error = object_schema(vec![
+        ("message", string_schema("Detailed cause", None)),
+        ("type", string_schema("Type of the server side exception", None)),
+        ("stacktrace", string_schema("Server side stack trace, usually empty", None)),
+        ("internalexception", internal_exception),
+    ], &[], "Details for debugging, not present on every error");
+    let error = object_schema(vec![
+        ("code", string_schema("Error code, often empty", None)),
+        ("message", message),
+        ("innererror", inner_error),
+    ], &["code", "message"], "The error");
+    object_schema(vec![("error", error)], &["error"], "Body of failed requests")
+}
+
+fn build_parameters(options: &SpecOptions, annotations: &mut Annotations) -> BTreeMap<String, Parameter> {
     let mut parameters = BTreeMap::new();
     parameters.insert("Division".to_owned(), Parameter {
         name: "division".to_owned(),
@@ -289,18 +1635,73 @@ fn build_parameters() -> BTreeMap<String, Parameter> {
         ..Parameter::default()
     });
     parameters.insert("order_by".to_owned(), Parameter {
-        name: "$orderBy".to_owned(),
+        name: "$orderby".to_owned(),
         location: "query".to_owned(),
         required: Some(false),
         param_type: Some("string".to_owned()),
-        format: Some("$orderBy".to_owned()),
+        format: Some("$orderby".to_owned()),
         ..Parameter::default()
     });
+    parameters.insert("skip".to_owned(), Parameter {
+        name: "$skip".to_owned(),
+        location: "query".to_owned(),
+        required: Some(false),
+        param_type: Some("integer".to_owned()),
+        format: Some("int32".to_owned()),
+        description: Some("Number of records to skip".to_owned()),
+        ..Parameter::default()
+    });
+    annotations.insert(pointer(&["parameters", "skip"]), "minimum", json!(0));
+    parameters.insert("inlinecount".to_owned(), Parameter {
+        name: "$inlinecount".to_owned(),
+        location: "query".to_owned(),
+        required: Some(false),
+        param_type: Some("string".to_owned()),
+        description: Some("Whether to include the total number of records in `__count`".to_owned()),
+        ..Parameter::default()
+    });
+    annotations.insert(pointer(&["parameters", "inlinecount"]), "enum", json!(["allpages", "none"]));
+    if options.prefer_header {
+        parameters.insert("Prefer".to_owned(), Parameter {
+            name: "Prefer".to_owned(),
+            location: "header".to_owned(),
+            required: Some(false),
+            param_type: Some("string".to_owned()),
+            description: Some("`return=representation` to get the created or updated entity back".to_owned()),
+            ..Parameter::default()
+        });
+        annotations.insert(pointer(&["parameters", "Prefer"]), "enum", json!(["return=representation", "return=minimal"]));
+    }
+    if options.concurrency_headers {
+        parameters.insert("IfMatch".to_owned(), Parameter {
+            name: "If-Match".to_owned(),
+            location: "header".to_owned(),
+            required: Some(false),
+            param_type: Some("string".to_owned()),
+            description: Some("Only change the entity if it still has this `ETag`, fails with 412 otherwise".to_owned()),
+            ..Parameter::default()
+        });
+    }
     parameters
 }
 
-fn build_security_definitions() -> BTreeMap<String, Security> {
+/// Where users grant an app access to their Exact Online administrations
+pub const OAUTH_AUTHORIZATION_URL: &'static str = "https://start.exactonline.nl/api/oauth2/auth";
+/// Where apps exchange authorization codes and refresh tokens for access tokens
+pub const OAUTH_TOKEN_URL: &'static str = "https://start.exactonline.nl/api/oauth2/token";
+
+fn build_security_definitions(endpoints: &[&EndpointDetails]) -> BTreeMap<String, Security> {
     let mut security_definitions = BTreeMap::new();
+    // The docs only name the scopes, a description repeating the name would tell nothing
+    let scopes = BTreeMap::from_iter(endpoints.iter()
+        .flat_map(|e| e.scopes.iter())
+        .map(|s| (s.clone(), String::new())));
+    security_definitions.insert("oauth2".to_owned(), Security::Oauth2 {
+        flow: Flow::AccessCode,
+        authorization_url: OAUTH_AUTHORIZATION_URL.to_owned(),
+        token_url: Some(OAUTH_TOKEN_URL.to_owned()),
+        scopes: scopes,
+    });
     security_definitions.insert("ApiKey".to_owned(), Security::ApiKey {
         name: "Authorization".to_owned(),
         location: "header".to_owned(),
@@ -308,41 +1709,1433 @@ fn build_security_definitions() -> BTreeMap<String, Security> {
     security_definitions
 }
 
+/// OAuth2 is how Exact Online is called, passing a bearer token as ApiKey is the alternative
+/// for clients that obtain tokens themselves
 fn build_security_requirements() -> Vec<BTreeMap<String, Vec<String>>> {
-    let mut requirement = BTreeMap::new();
-    requirement.insert("ApiKey".to_owned(), Vec::default());
-    vec![requirement]
+    ["oauth2", "ApiKey"].iter().map(|scheme| {
+        let mut requirement = BTreeMap::new();
+        requirement.insert(scheme.to_string(), Vec::default());
+        requirement
+    }).collect()
 }
 
 pub fn build_spec(endpoints: Vec<EndpointDetails>) -> Result<Spec> {
-    Ok(Spec {
-        swagger: "2.0".to_owned(),
-        info: Info {
-            title: Some("Exact Online REST API".to_owned()),
-            description: Some("Autogenerated using exact-openapi-gen".to_owned()),
-            terms_of_service: None,
-            contact: Some(Contact {
-                name: Some("Daan de Graaf".to_owned()),
-                url: Some("https://github.com/wildarch".to_owned()),
-                email: Some("daandegraaf9@gmail.com".to_owned()),
-            }),
-            license: Some(License {
-                name: Some("MIT".to_owned()),
-                url: None,
-            }),
-            version: Some(String::from(env!("CARGO_PKG_VERSION"))),
+    build_spec_with(endpoints, &SpecOptions::default())
+}
+
+pub fn build_spec_with(endpoints: Vec<EndpointDetails>, options: &SpecOptions) -> Result<Spec> {
+    let endpoints: Vec<&EndpointDetails> = endpoints.iter().collect();
+    build(&endpoints, options).map(|(spec, _)| spec)
+}
+
+/// Customization of the built `Spec`, applied before it is serialized. Closures taking
+/// `&mut Spec` are hooks too.
+pub trait SpecHook {
+    fn apply(&self, spec: &mut Spec);
+}
+
+impl<F: Fn(&mut Spec)> SpecHook for F {
+    fn apply(&self, spec: &mut Spec) {
+        self(spec)
+    }
+}
+
+/// Builds the spec as JSON, including the annotations that `Spec` has no fields for,
+/// in the version selected by the options. The spec is in the canonical form `canonicalize`
+/// describes, so unchanged endpoints give an unchanged spec.
+pub fn build_document(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Result<Value> {
+    let (spec, annotations) = build(endpoints, options)?;
+    let mut document = serde_json::to_value(&spec)?;
+    annotations.apply(&mut document)?;
+    finish_document(document, options)
+}
+
+/// Like `build_document`, letting `hook` rename, prune or annotate the `Spec` before it is
+/// serialized. Annotations of parameters follow them when the hook reorders the parameters,
+/// annotations of parts the hook removed are dropped.
+pub fn build_document_with_hook(endpoints: &[&EndpointDetails], options: &SpecOptions, hook: &SpecHook) -> Result<Value> {
+    let (mut spec, mut annotations) = build(endpoints, options)?;
+    let built = serde_json::to_value(&spec)?;
+    hook.apply(&mut spec);
+    let mut document = serde_json::to_value(&spec)?;
+    annotations.retarget(|pointer| follow_parameter(pointer, &built, &document));
+    annotations.retain_targets(&document);
+    annotations.apply(&mut document)?;
+    finish_document(document, options)
+}
+
+/// Where the parameter `pointer` addresses in `built` ended up in `hooked`, or `None` when
+/// it was removed. Other pointers are kept as they are.
+fn follow_parameter(pointer: &str, built: &Value, hooked: &Value) -> Option<String> {
+    let (parameters, index) = match pointer.rfind("/parameters/") {
+        Some(at) if pointer.starts_with("/paths/") => (&pointer[..at + "/parameters".len()], &pointer[at + "/parameters/".len()..]),
+        _ => return Some(pointer.to_owned()),
+    };
+    if index.parse::<usize>().is_err() {
+        return Some(pointer.to_owned());
+    }
+    let identity = |parameter: &Value| (parameter.get("$ref").cloned(), parameter.get("in").cloned(), parameter.get("name").cloned());
+    let parameter = match built.pointer(pointer) {
+        Some(parameter) => identity(parameter),
+        None => return Some(pointer.to_owned()),
+    };
+    hooked.pointer(parameters)
+        .and_then(|p| p.as_array())
+        .and_then(|p| p.iter().position(|candidate| identity(candidate) == parameter))
+        .map(|index| format!("{}/{}", parameters, index))
+}
+
+/// Builds a spec per service area, like `Manufacturing` or `CRM`, each holding only the paths
+/// and definitions of the endpoints in that area. Keyed by the service as scraped, the webhooks
+/// end up in the spec of the `Webhooks` service.
+pub fn build_service_documents(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Result<BTreeMap<String, Value>> {
+    let mut services: BTreeMap<String, Vec<&EndpointDetails>> = BTreeMap::new();
+    for endpoint in endpoints.iter().cloned().filter(|e| options.includes_endpoint(e)) {
+        services.entry(service_tag(endpoint)).or_insert_with(Vec::new).push(endpoint);
+    }
+    let webhooks = service_tag(&subscription_endpoint(&[]));
+    if options.include_webhooks {
+        services.entry(webhooks.clone()).or_insert_with(Vec::new);
+    }
+    services.into_iter().map(|(service, endpoints)| {
+        let service_options = SpecOptions {
+            info: service_info(&service, options),
+            include_webhooks: options.include_webhooks && service == webhooks,
+            ..options.clone()
+        };
+        build_document(&endpoints, &service_options).map(|document| (service, document))
+    }).collect()
+}
+
+/// The `info` configured for the service, completed by the general one
+fn service_info(service: &str, options: &SpecOptions) -> InfoOptions {
+    let general = options.info.clone();
+    let specific = options.service_infos.get(service).cloned().unwrap_or_default();
+    let title = match specific.title {
+        Some(title) => title,
+        None => format!("{}: {}", general.title.unwrap_or_else(|| DEFAULT_TITLE.to_owned()), options.renamed(service)),
+    };
+    InfoOptions {
+        title: Some(title),
+        description: specific.description.or(general.description),
+        version: specific.version.or(general.version),
+        contact: specific.contact.or(general.contact),
+        license: specific.license.or(general.license),
+    }
+}
+
+/// Converts the serialized spec to the version selected by the options and post-processes it
+fn finish_document(mut document: Value, options: &SpecOptions) -> Result<Value> {
+    if options.closed_schemas {
+        if let Some(&mut Value::Object(ref mut definitions)) = document.get_mut("definitions") {
+            for definition in definitions.values_mut() {
+                close_schema(definition);
+            }
+        }
+    }
+    let document = match options.spec_version {
+        SpecVersion::Swagger2 => document,
+        SpecVersion::OpenApi3 => {
+            let mut document = to_openapi3(&document);
+            if options.server_variables {
+                let base_path = options.api_base_path.clone().unwrap_or_else(|| "/api/v1".to_owned());
+                use_server_variables(&mut document, &base_path);
+            }
+            document
         },
+    };
+    let document = if options.inline_refs { inline_refs(&document) } else { document };
+    if options.strict_refs {
+        if let Some((reference, locations)) = find_dangling_refs(&document).into_iter().next() {
+            bail!(DanglingRef(reference, locations));
+        }
+    }
+    let mut document = document;
+    canonicalize(&mut document);
+    Ok(document)
+}
+
+/// Forbids properties besides the listed ones on the schema and the schemas it nests. The parts
+/// of composed definitions stay open, as closing them would reject the properties listed by the
+/// other parts.
+fn close_schema(schema: &mut Value) {
+    if let Value::Object(ref mut schema) = *schema {
+        if schema.contains_key("properties") && !schema.contains_key("additionalProperties") {
+            schema.insert("additionalProperties".to_owned(), json!(false));
+        }
+    }
+    close_members(schema);
+}
+
+/// Closes the schemas nested in the schema, not the schema itself
+fn close_members(schema: &mut Value) {
+    let schema = match *schema {
+        Value::Object(ref mut schema) => schema,
+        _ => return,
+    };
+    if let Some(&mut Value::Object(ref mut properties)) = schema.get_mut("properties") {
+        for property in properties.values_mut() {
+            close_schema(property);
+        }
+    }
+    if let Some(items) = schema.get_mut("items") {
+        close_schema(items);
+    }
+    if let Some(&mut Value::Array(ref mut parts)) = schema.get_mut("allOf") {
+        for part in parts {
+            close_members(part);
+        }
+    }
+}
+
+/// The payloads posted to webhook subscribers, per topic
+fn build_webhook_definitions(topics: &[String], options: &SpecOptions, annotations: &mut Annotations) -> BTreeMap<String, Schema> {
+    if !options.include_webhooks {
+        return BTreeMap::new();
+    }
+    BTreeMap::from_iter(topics.iter().map(|topic| {
+        let name = options.definition_name(topic, DefinitionKind::WebhookPayload);
+        let title = definition_title(&name, &options.renamed(topic), DefinitionKind::WebhookPayload);
+        annotations.insert(pointer(&["definitions", &name]), "title", json!(title));
+        (name, build_payload_definition(topic, options))
+    }))
+}
+
+const DEFAULT_TITLE: &'static str = "Exact Online REST API";
+
+/// Describes this generator, unless the options say otherwise
+fn build_info(options: &SpecOptions) -> Info {
+    let overrides = options.info.clone();
+    Info {
+        title: Some(overrides.title.unwrap_or_else(|| DEFAULT_TITLE.to_owned())),
+        description: Some(overrides.description.unwrap_or_else(|| "Autogenerated using exact-openapi-gen".to_owned())),
+        terms_of_service: None,
+        contact: Some(overrides.contact.unwrap_or_else(|| Contact {
+            name: Some("Daan de Graaf".to_owned()),
+            url: Some("https://github.com/wildarch".to_owned()),
+            email: Some("daandegraaf9@gmail.com".to_owned()),
+        })),
+        license: Some(overrides.license.unwrap_or_else(|| License {
+            name: Some("MIT".to_owned()),
+            url: None,
+        })),
+        version: Some(overrides.version.unwrap_or_else(|| String::from(env!("CARGO_PKG_VERSION")))),
+    }
+}
+
+/// Fails on property rules excluding a key, the keyed paths can't do without it
+fn check_property_rules(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Result<()> {
+    for endpoint in endpoints {
+        let excluded = options.property_rules.get(&endpoint.name).map_or(&[][..], |rules| &rules.exclude[..]);
+        if let Some(key) = endpoint.keys().into_iter().find(|k| excluded.contains(&k.name)) {
+            bail!(InvalidOptions(format!("key {} of {} can't be excluded", key.name, endpoint.name)));
+        }
+    }
+    Ok(())
+}
+
+/// Renaming an endpoint onto the name of another one would give both the same definitions
+fn check_renames(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Result<()> {
+    let mut names: BTreeMap<String, &str> = BTreeMap::new();
+    for endpoint in endpoints {
+        let renamed = options.renamed(&endpoint.name);
+        match names.insert(renamed.clone(), &endpoint.name) {
+            Some(other) if other != endpoint.name => {
+                bail!(InvalidOptions(format!("{} and {} would both be named {}", other, endpoint.name, renamed)));
+            },
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+fn build(endpoints: &[&EndpointDetails], options: &SpecOptions) -> Result<(Spec, Annotations)> {
+    options.definition_naming.check()?;
+    check_property_rules(endpoints, options)?;
+    let mut annotations = Annotations::new();
+    let mut included: Vec<&EndpointDetails> = endpoints.iter().cloned().filter(|e| options.includes_endpoint(e)).collect();
+    let topics = options.webhook_topics();
+    let subscriptions = subscription_endpoint(&topics);
+    if options.include_webhooks {
+        if !included.iter().any(|e| e.name == WEBHOOK_SUBSCRIPTIONS) {
+            included.push(&subscriptions);
+        }
+        let webhooks = Map::from_iter(topics.iter().map(|topic| {
+            (topic.clone(), json!({ "$ref": options.definition_ref(topic, DefinitionKind::WebhookPayload) }))
+        }));
+        annotations.insert(String::new(), "x-webhooks", Value::Object(webhooks));
+    }
+    check_renames(&included, options)?;
+    if let Some(conventions) = build_edm_conventions(&included, options) {
+        annotations.insert(String::new(), "x-edm-conventions", conventions);
+    }
+    let spec = Spec {
+        swagger: "2.0".to_owned(),
+        info: build_info(options),
         host: Some("start.exactonline.nl".to_owned()),
         base_path: Some("/".to_owned()),
         schemes: Some(["https".to_owned()].to_vec()),
-        consumes: Some(["application/json".to_owned()].to_vec()),
-        produces: Some(["application/json".to_owned()].to_vec()),
-        tags: None,
-        paths: build_paths(endpoints.iter())?,
-        definitions: Some(build_definitions(endpoints.iter())?),
-        parameters: Some(build_parameters()),
+        consumes: Some(media_types(options)),
+        produces: Some(media_types(options)),
+        tags: Some(build_tags(&included, options)),
+        paths: build_paths(included.iter().cloned(), options, &mut annotations)?,
+        definitions: Some(build_definitions(included.iter().cloned(), options, &mut annotations)?
+            .into_iter().chain(build_webhook_definitions(&topics, options, &mut annotations)).collect()),
+        parameters: Some(build_parameters(options, &mut annotations)),
         responses: None,
-        security_definitions: Some(build_security_definitions()),
+        security_definitions: Some(build_security_definitions(&included)),
         security: Some(build_security_requirements()),
-    })
-}
\ No newline at end of file
+    };
+    Ok((spec, annotations))
+}
+
+#[cfg(test)]
+pub fn sample_endpoint() -> EndpointDetails {
+    use acquisition::Property;
+    use reqwest::Url;
+
+    EndpointDetails {
+        name: "ProjectProjects".to_owned(),
+        uri: "/api/v1/{division}/project/Projects".to_owned(),
+        description: Some("Projects are used to register hours and costs.".to_owned()),
+        doc_url: Url::parse("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=ProjectProjects").unwrap(),
+        requires_division: true,
+        kind: EndpointKind::Standard,
+        service: Some("Project".to_owned()),
+        max_page_size: 60,
+        function_parameters: Vec::new(),
+        singleton: false,
+        method_remarks: BTreeMap::new(),
+        beta: false,
+        deprecated: false,
+        replaced_by: None,
+        required_packages: Vec::new(),
+        scopes: vec!["projects.read".to_owned()],
+        query_options: QueryOption::defaults(EndpointKind::Standard),
+        properties: vec![
+            Property {
+                name: "ID".to_owned(),
+                edm_type: EdmType::Guid,
+                description: Some("Primary key".to_owned()),
+                key: true,
+                methods: vec![Method::Get, Method::Post, Method::Put, Method::Delete],
+                default: None,
+                allowed_values: Vec::new(),
+                nullable: false,
+                mandatory: false,
+                max_length: None,
+                precision: None,
+                scale: None,
+                navigation: false,
+            },
+            Property {
+                name: "Code".to_owned(),
+                edm_type: EdmType::String,
+                description: Some("Code of the project".to_owned()),
+                key: false,
+                methods: vec![Method::Get, Method::Post, Method::Put],
+                default: None,
+                allowed_values: Vec::new(),
+                nullable: true,
+                mandatory: true,
+                max_length: None,
+                precision: None,
+                scale: None,
+                navigation: false,
+            },
+            Property {
+                name: "Created".to_owned(),
+                edm_type: EdmType::DateTime,
+                description: Some("Creation date".to_owned()),
+                key: false,
+                methods: vec![Method::Get],
+                default: None,
+                allowed_values: Vec::new(),
+                nullable: true,
+                mandatory: false,
+                max_length: None,
+                precision: None,
+                scale: None,
+                navigation: false,
+            },
+        ],
+        failed_properties: Vec::new(),
+        methods: vec![Method::Get, Method::Post, Method::Put, Method::Delete],
+        unknown_methods: Vec::new(),
+        examples: BTreeMap::new(),
+    }
+}
+
+#[test]
+fn it_builds_key_segments() {
+    use acquisition::Property;
+
+    let mut endpoint = sample_endpoint();
+    assert_eq!(key_segment(&endpoint), "(guid'{id}')");
+
+    endpoint.properties[0].edm_type = EdmType::Int32;
+    assert_eq!(key_segment(&endpoint), "({id})");
+    let literals = vec![
+        (EdmType::Int64, "({id}L)"),
+        (EdmType::Decimal, "({id}M)"),
+        (EdmType::Double, "({id}d)"),
+        (EdmType::Single, "({id}f)"),
+        (EdmType::Boolean, "({id})"),
+    ];
+    for (edm_type, segment) in literals {
+        endpoint.properties[0].edm_type = edm_type;
+        assert_eq!(key_segment(&endpoint), segment);
+    }
+
+    endpoint.properties.push(Property {
+        name: "Year".to_owned(),
+        edm_type: EdmType::Int16,
+        description: None,
+        key: true,
+        methods: vec![Method::Get],
+        default: None,
+        allowed_values: Vec::new(),
+        nullable: false,
+        mandatory: false,
+        max_length: None,
+        precision: None,
+        scale: None,
+        navigation: false,
+    });
+    endpoint.properties[0].edm_type = EdmType::String;
+    endpoint.properties[0].name = "Code".to_owned();
+    assert_eq!(key_segment(&endpoint), "(Code='{Code}',Year={Year})");
+}
+
+#[test]
+fn it_builds_delete_operations_without_body() {
+    let endpoint = sample_endpoint();
+    let path = "/api/v1/{division}/project/Projects(guid'{id}')";
+    let operation = build_operation(Method::Delete, &endpoint, path, &SpecOptions::default(), &mut Annotations::new())
+        .expect("Delete operation");
+
+    let mut responses = BTreeMap::new();
+    responses.insert("204".to_owned(), Response {
+        description: "Entity deleted".to_owned(),
+        schema: None,
+    });
+    insert_error_responses(&mut responses, &SpecOptions::default());
+    let golden = Operation {
+        responses: responses,
+        parameters: Some(vec![
+            ParameterOrRef::Parameter {
+                name: "id".to_owned(),
+                location: "path".to_owned(),
+                required: Some(true),
+                schema: None,
+                unique_items: None,
+                param_type: Some("string".to_owned()),
+                format: Some("guid".to_owned()),
+                description: Some("ID of the entity to modify/delete".to_owned()),
+            },
+        ]),
+        ..Operation::default()
+    };
+    assert_eq!(operation, golden);
+    assert!(operation.responses.keys().all(|status| status != "200"));
+}
+
+#[test]
+fn it_describes_definitions() {
+    let endpoint = sample_endpoint();
+    let definition = build_definition(Method::Get, &endpoint, "ProjectProjectsResponse", &SpecOptions::default(), &mut Annotations::new());
+    assert_eq!(definition.description, Some("Projects are used to register hours and costs. \
+        Identified by `ID`. Lists return at most 60 records per page.".to_owned()));
+}
+
+#[test]
+fn it_skips_beta_endpoints_unless_included() {
+    let mut endpoint = sample_endpoint();
+    endpoint.beta = true;
+    let mut options = SpecOptions::default();
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    assert!(spec.pointer("/paths/~1api~1v1~1{division}~1project~1Projects").is_none());
+
+    options.include_beta = true;
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    assert_eq!(spec.pointer("/paths/~1api~1v1~1{division}~1project~1Projects/get/x-preview"), Some(&json!(true)));
+}
+
+#[test]
+fn it_generates_unique_operation_ids() {
+    let endpoint = sample_endpoint();
+    let mut duplicate = sample_endpoint();
+    duplicate.uri = "/api/v1/{division}/project/ZProjects".to_owned();
+    let spec = build_document(&[&duplicate, &endpoint], &SpecOptions::default()).expect("Spec");
+    let projects = &spec["paths"]["/api/v1/{division}/project/Projects"];
+    assert_eq!(projects["get"]["operationId"], json!("getProjectProjects"));
+    assert_eq!(projects["post"]["operationId"], json!("createProjectProjects"));
+    assert_eq!(spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"]["put"]["operationId"],
+        json!("updateProjectProjectsById"));
+    assert_eq!(spec["paths"]["/api/v1/{division}/project/ZProjects"]["get"]["operationId"],
+        json!("getProjectProjects2"));
+    // Whichever endpoint was scraped first
+    assert_eq!(build_document(&[&endpoint, &duplicate], &SpecOptions::default()).expect("Spec"), spec);
+}
+
+#[test]
+fn it_tags_operations_by_service() {
+    let endpoint = sample_endpoint();
+    let mut untagged = sample_endpoint();
+    untagged.service = None;
+    untagged.uri = "/api/v1/{division}/crm/Accounts".to_owned();
+    untagged.name = "CRMAccounts".to_owned();
+    let spec = build_document(&[&endpoint, &untagged], &SpecOptions::default()).expect("Spec");
+    assert_eq!(spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["tags"], json!(["Project"]));
+    assert_eq!(spec["paths"]["/api/v1/{division}/crm/Accounts"]["get"]["tags"], json!(["Crm"]));
+    assert_eq!(spec["tags"], json!([
+        { "name": "Crm", "description": "Resources of the Crm service area" },
+        { "name": "Project", "description": "Resources of the Project service area" },
+    ]));
+}
+
+#[test]
+fn it_adds_paging_parameters_to_collections() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let parameters = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"];
+    assert!(parameters.as_array().unwrap().contains(&json!({ "$ref": "#/parameters/skip" })));
+    assert!(parameters.as_array().unwrap().contains(&json!({ "$ref": "#/parameters/inlinecount" })));
+    assert_eq!(spec["parameters"]["order_by"]["name"], json!("$orderby"));
+    assert_eq!(spec["parameters"]["inlinecount"]["enum"], json!(["allpages", "none"]));
+
+    let mut singleton = sample_endpoint();
+    singleton.singleton = true;
+    let spec = build_document(&[&singleton], &SpecOptions::default()).expect("Spec");
+    let parameters = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"];
+    assert!(!parameters.as_array().unwrap().contains(&json!({ "$ref": "#/parameters/skip" })));
+}
+
+#[test]
+fn it_enumerates_expandable_relations() {
+    use acquisition::Property;
+
+    let mut endpoint = sample_endpoint();
+    endpoint.properties.push(Property {
+        name: "Members".to_owned(),
+        edm_type: EdmType::Collection(Box::new(EdmType::Complex("ProjectMembers".to_owned()))),
+        description: None,
+        key: false,
+        methods: vec![Method::Get],
+        default: None,
+        allowed_values: Vec::new(),
+        nullable: true,
+        mandatory: false,
+        max_length: None,
+        precision: None,
+        scale: None,
+        navigation: true,
+    });
+    // Complex types are part of the entity, not related entities
+    endpoint.properties.push(Property {
+        name: "Address".to_owned(),
+        edm_type: EdmType::Complex("Exact.Web.Api.Models.Address".to_owned()),
+        navigation: false,
+        ..endpoint.properties[2].clone()
+    });
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().unwrap().clone();
+    let expand = parameters.iter().find(|p| p["name"] == json!("$expand")).expect("$expand parameter");
+    assert_eq!(expand["items"]["enum"], json!(["Members"]));
+
+    // OData takes `$expand=A,B`, not the parameter repeated
+    let options = SpecOptions { spec_version: SpecVersion::OpenApi3, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().unwrap().clone();
+    let expand = parameters.iter().find(|p| p["name"] == json!("$expand")).expect("$expand parameter");
+    assert_eq!((&expand["style"], &expand["explode"]), (&json!("form"), &json!(false)));
+    assert_eq!(expand["schema"]["items"]["enum"], json!(["Members"]));
+    assert!(expand["schema"].get("collectionFormat").is_none());
+
+    let spec = build_document(&[&sample_endpoint()], &SpecOptions::default()).expect("Spec");
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().unwrap().clone();
+    assert!(parameters.iter().all(|p| p["name"] != json!("$expand")));
+}
+
+#[test]
+fn it_enumerates_selectable_properties() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { typed_select: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().unwrap().clone();
+    let select = parameters.iter().find(|p| p["name"] == json!("$select")).expect("$select parameter");
+    assert_eq!(select["items"]["enum"], json!(["ID", "Code", "Created"]));
+    assert!(!parameters.contains(&json!({ "$ref": "#/parameters/select" })));
+
+    let options = SpecOptions { spec_version: SpecVersion::OpenApi3, ..options };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().unwrap().clone();
+    let select = parameters.iter().find(|p| p["name"] == json!("$select")).expect("$select parameter");
+    assert_eq!((&select["style"], &select["explode"]), (&json!("form"), &json!(false)));
+    assert_eq!(select["schema"]["items"]["enum"], json!(["ID", "Code", "Created"]));
+}
+
+#[test]
+fn it_fetches_single_entities_from_the_keyed_path() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let get = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"]["get"];
+    assert_eq!(get["operationId"], json!("getProjectProjectsById"));
+    assert_eq!(get["responses"]["200"]["schema"]["$ref"], json!("#/definitions/ProjectProjectsSingleResponse"));
+    let single = &spec["definitions"]["ProjectProjectsSingleResponse"];
+    assert_eq!(single["properties"]["d"]["properties"]["Code"]["type"], json!("string"));
+}
+
+#[test]
+fn it_answers_singletons_with_a_single_entity() {
+    let mut endpoint = sample_endpoint();
+    endpoint.singleton = true;
+    endpoint.methods = vec![Method::Get];
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let get = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"];
+    assert_eq!(get["responses"]["200"]["schema"]["$ref"], json!("#/definitions/ProjectProjectsSingleResponse"));
+    assert_eq!(spec["definitions"]["ProjectProjectsSingleResponse"]["required"], json!(["d"]));
+    assert!(spec["definitions"].get("ProjectProjectsResponse").is_none());
+}
+
+#[test]
+fn it_answers_posts_with_the_created_entity() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let post = &spec["paths"]["/api/v1/{division}/project/Projects"]["post"];
+    assert_eq!(post["responses"]["201"]["schema"]["$ref"], json!("#/definitions/ProjectProjectsPostResponse"));
+    let created = &spec["definitions"]["ProjectProjectsPostResponse"];
+    assert_eq!(created["properties"]["d"]["type"], json!("object"));
+    assert!(created["properties"]["d"]["properties"].get("results").is_none());
+}
+
+#[test]
+fn it_declares_oauth2_security() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let oauth2 = &spec["securityDefinitions"]["oauth2"];
+    assert_eq!(oauth2["flow"], json!("accessCode"));
+    assert_eq!(oauth2["tokenUrl"], json!(OAUTH_TOKEN_URL));
+    assert_eq!(oauth2["scopes"], json!({ "projects.read": "" }));
+    assert_eq!(spec["security"], json!([{ "oauth2": [] }, { "ApiKey": [] }]));
+}
+
+#[test]
+fn it_requires_the_documented_scopes() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert_eq!(spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["security"],
+        json!([{ "oauth2": ["projects.read"] }, { "ApiKey": [] }]));
+
+    let mut unscoped = sample_endpoint();
+    unscoped.scopes.clear();
+    let spec = build_document(&[&unscoped], &SpecOptions::default()).expect("Spec");
+    assert!(spec["paths"]["/api/v1/{division}/project/Projects"]["get"].get("security").is_none());
+}
+
+#[test]
+fn it_links_operations_to_the_docs() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert_eq!(spec["paths"]["/api/v1/{division}/project/Projects"]["post"]["externalDocs"]["url"],
+        json!("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name=ProjectProjects"));
+}
+
+#[test]
+fn it_maps_to_standard_formats() {
+    let mut endpoint = sample_endpoint();
+    endpoint.properties[1].edm_type = EdmType::Decimal;
+    let options = SpecOptions { format_mapping: FormatMapping::Standard, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsResponse"]["properties"]["d"]["properties"]["results"]["items"]["properties"];
+    assert_eq!(properties["ID"]["format"], json!("uuid"));
+    assert_eq!(properties["Created"]["format"], json!("date-time"));
+    assert_eq!(properties["Created"]["x-edm-type"], json!("Edm.DateTime"));
+    assert_eq!(properties["Code"]["type"], json!("string"));
+    assert_eq!(properties["Code"]["pattern"], json!(DECIMAL_PATTERN));
+    let delete = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"]["delete"];
+    assert_eq!(delete["parameters"][0]["format"], json!("uuid"));
+}
+
+#[test]
+fn it_constrains_values_to_their_documented_limits() {
+    let mut endpoint = sample_endpoint();
+    endpoint.properties[1].max_length = Some(30);
+    endpoint.properties.push(Property {
+        name: "Budget".to_owned(),
+        edm_type: EdmType::Double,
+        precision: Some(18),
+        scale: Some(2),
+        navigation: false,
+        ..endpoint.properties[1].clone()
+    });
+    endpoint.properties.push(Property {
+        name: "Rate".to_owned(),
+        edm_type: EdmType::Decimal,
+        precision: Some(5),
+        scale: Some(3),
+        navigation: false,
+        ..endpoint.properties[1].clone()
+    });
+    endpoint.properties.push(Property {
+        name: "Priority".to_owned(),
+        edm_type: EdmType::Int16,
+        precision: Some(10),
+        scale: None,
+        navigation: false,
+        ..endpoint.properties[1].clone()
+    });
+    endpoint.properties.push(Property {
+        name: "Level".to_owned(),
+        edm_type: EdmType::Byte,
+        precision: Some(2),
+        scale: None,
+        navigation: false,
+        ..endpoint.properties[1].clone()
+    });
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsPut"]["properties"];
+    assert_eq!(properties["Code"]["maxLength"], json!(30));
+    assert_eq!(properties["Budget"]["maximum"], json!(10000000000000000i64));
+    assert_eq!(properties["Budget"]["minimum"], json!(-10000000000000000i64));
+    assert_eq!(properties["Budget"]["exclusiveMaximum"], json!(true));
+    assert!(properties["Budget"].get("multipleOf").is_none());
+    assert_eq!(properties["Rate"]["pattern"], json!(r"^-?[0-9]{1,2}(\.[0-9]{1,3})?$"));
+    assert!(properties["ID"].get("maxLength").is_none());
+    assert_eq!((&properties["Priority"]["minimum"], &properties["Priority"]["maximum"]), (&json!(-9999999999i64), &json!(9999999999i64)));
+
+    // The range of the type narrows the documented one
+    let options = SpecOptions { format_mapping: FormatMapping::Standard, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsPut"]["properties"];
+    assert_eq!((&properties["Priority"]["minimum"], &properties["Priority"]["maximum"]), (&json!(-32768), &json!(32767)));
+    assert_eq!((&properties["Level"]["minimum"], &properties["Level"]["maximum"]), (&json!(0), &json!(99)));
+    assert!(properties["Level"].get("exclusiveMaximum").is_none());
+}
+
+#[test]
+fn it_constrains_guids() {
+    let mut endpoint = sample_endpoint();
+    endpoint.properties[1].edm_type = EdmType::Collection(Box::new(EdmType::Guid));
+    let options = SpecOptions { guid_pattern: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsPut"]["properties"];
+    assert_eq!(properties["ID"]["format"], json!("uuid"));
+    assert_eq!(properties["ID"]["pattern"], json!(GUID_PATTERN));
+    assert_eq!(properties["Code"]["items"]["format"], json!("uuid"));
+    assert_eq!(properties["Code"]["items"]["pattern"], json!(GUID_PATTERN));
+    let delete = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"]["delete"];
+    assert_eq!(delete["parameters"][0]["format"], json!("uuid"));
+
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert_eq!(spec["definitions"]["ProjectProjectsPut"]["properties"]["ID"]["format"], json!("guid"));
+    assert!(spec["definitions"]["ProjectProjectsPut"]["properties"]["ID"].get("pattern").is_none());
+}
+
+#[test]
+fn it_marks_nullable_properties() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsPut"]["properties"];
+    assert_eq!(properties["Code"]["x-nullable"], json!(true));
+    assert!(properties["ID"].get("x-nullable").is_none());
+
+    let options = SpecOptions { spec_version: SpecVersion::OpenApi3, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    assert_eq!(spec["components"]["schemas"]["ProjectProjectsPut"]["properties"]["Code"]["nullable"], json!(true));
+}
+
+#[test]
+fn it_marks_response_only_properties_read_only() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsSingleResponse"]["properties"]["d"]["properties"];
+    assert_eq!(properties["Created"]["readOnly"], json!(true));
+    assert!(properties["Code"].get("readOnly").is_none());
+}
+
+#[test]
+fn it_composes_definitions_from_a_shared_base() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { compose_definitions: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let definitions = &spec["definitions"];
+    // Created is only returned, so it mustn't be accepted by the definitions of POST and PUT
+    let base = definitions["ProjectProjects"]["properties"].as_object().expect("Base properties");
+    assert_eq!(base.keys().collect::<Vec<_>>(), vec!["Code", "ID"]);
+    let items = &definitions["ProjectProjectsResponse"]["properties"]["d"]["properties"]["results"]["items"]["allOf"];
+    assert_eq!(items[0], json!({ "$ref": "#/definitions/ProjectProjects" }));
+    assert_eq!(items[1]["properties"].as_object().expect("Returned properties").keys().collect::<Vec<_>>(), vec!["Created"]);
+    assert_eq!(items[1]["properties"]["Created"]["readOnly"], json!(true));
+    assert_eq!(definitions["ProjectProjectsPost"]["allOf"], json!([
+        { "$ref": "#/definitions/ProjectProjects" },
+        { "type": "object", "required": ["Code"] },
+    ]));
+    assert_eq!(definitions["ProjectProjectsPut"]["allOf"], json!([{ "$ref": "#/definitions/ProjectProjects" }]));
+    assert!(definitions["ProjectProjectsPut"].get("properties").is_none());
+}
+
+#[test]
+fn it_requires_mandatory_properties_on_create() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert_eq!(spec["definitions"]["ProjectProjectsPost"]["required"], json!(["Code"]));
+    assert!(spec["definitions"]["ProjectProjectsPut"].get("required").is_none());
+}
+
+#[test]
+fn it_enumerates_allowed_values() {
+    use acquisition::AllowedValue;
+
+    let mut endpoint = sample_endpoint();
+    endpoint.properties[1].allowed_values = vec![
+        AllowedValue { value: "A".to_owned(), description: "Active".to_owned() },
+        AllowedValue { value: "B".to_owned(), description: "Blocked".to_owned() },
+    ];
+    endpoint.properties.push(Property {
+        name: "Status".to_owned(),
+        edm_type: EdmType::Int16,
+        description: Some("1 = Open, 2 = Closed".to_owned()),
+        key: false,
+        methods: vec![Method::Get],
+        default: None,
+        allowed_values: vec![
+            AllowedValue { value: "1".to_owned(), description: "Open".to_owned() },
+            AllowedValue { value: "2".to_owned(), description: "Closed".to_owned() },
+        ],
+        nullable: true,
+        mandatory: false,
+        max_length: None,
+        precision: None,
+        scale: None,
+        navigation: false,
+    });
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsSingleResponse"]["properties"]["d"]["properties"];
+    assert_eq!(properties["Code"]["enum"], json!(["A", "B"]));
+    assert_eq!(properties["Status"]["enum"], json!([1, 2]));
+}
+
+#[test]
+fn it_summarizes_operations() {
+    let mut endpoint = sample_endpoint();
+    endpoint.method_remarks.insert("DELETE".to_owned(), "Only projects without hours can be deleted.".to_owned());
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let collection = &spec["paths"]["/api/v1/{division}/project/Projects"];
+    assert_eq!(collection["get"]["summary"], json!("List ProjectProjects"));
+    assert_eq!(collection["post"]["description"],
+        json!("Projects are used to register hours and costs.\n\nRequires `Code`."));
+    let delete = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"]["delete"];
+    assert_eq!(delete["summary"], json!("Delete ProjectProjects by key"));
+    assert_eq!(delete["description"],
+        json!("Projects are used to register hours and costs.\n\nOnly projects without hours can be deleted."));
+}
+
+#[test]
+fn it_marks_deprecated_operations() {
+    let mut endpoint = sample_endpoint();
+    endpoint.deprecated = true;
+    endpoint.replaced_by = Some("ProjectProjectsV2".to_owned());
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let get = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"];
+    assert_eq!(get["deprecated"], json!(true));
+    assert_eq!(get["x-replaced-by"], json!("ProjectProjectsV2"));
+    assert!(spec["paths"]["/api/v1/{division}/project/Projects"]["post"].get("deprecated").is_some());
+}
+
+#[test]
+fn it_flattens_the_envelope() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { flatten_envelope: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let list = &spec["definitions"]["ProjectProjectsResponse"];
+    assert_eq!(list["type"], json!("array"));
+    assert_eq!(list["items"]["properties"]["Created"]["readOnly"], json!(true));
+    let single = &spec["definitions"]["ProjectProjectsSingleResponse"];
+    assert_eq!(single["properties"]["Code"]["type"], json!("string"));
+    assert!(single["properties"].get("d").is_none());
+}
+
+#[test]
+fn it_describes_paging_in_the_envelope() {
+    let mut endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let d = &spec["definitions"]["ProjectProjectsResponse"]["properties"]["d"];
+    assert_eq!(d["properties"]["__next"]["type"], json!("string"));
+    assert_eq!(d["properties"]["__count"]["type"], json!("string"));
+
+    endpoint.query_options.retain(|o| *o != QueryOption::InlineCount);
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let d = &spec["definitions"]["ProjectProjectsResponse"]["properties"]["d"];
+    assert!(d["properties"].get("__count").is_none());
+}
+
+#[test]
+fn it_describes_odata_errors() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let error = &spec["definitions"]["Error"]["properties"]["error"];
+    assert_eq!(error["required"], json!(["code", "message"]));
+    assert_eq!(error["properties"]["message"]["required"], json!(["lang", "value"]));
+    assert_eq!(error["properties"]["innererror"]["properties"]["internalexception"]["properties"]["type"]["type"],
+        json!("string"));
+}
+
+#[test]
+fn it_documents_extra_error_statuses() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let responses = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["responses"];
+    for status in &["403", "429", "503"] {
+        assert_eq!(responses[*status]["schema"]["$ref"], json!("#/definitions/Error"));
+    }
+
+    let options = SpecOptions { error_statuses: Some(vec![400, 429]), ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let responses = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["responses"];
+    assert!(responses.get("429").is_some());
+    assert!(responses.get("403").is_none());
+    assert_eq!(responses["400"]["description"], json!("Bad request (syntax invalid)"));
+}
+
+#[test]
+fn it_documents_rate_limit_headers() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let responses = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["responses"];
+    assert_eq!(responses["200"]["headers"]["X-RateLimit-Remaining"]["type"], json!("integer"));
+    assert!(responses["429"]["headers"].get("X-RateLimit-Minutely-Reset").is_some());
+    assert!(responses["400"].get("headers").is_none());
+}
+
+#[test]
+fn it_adds_a_default_response() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    for (_, path) in spec["paths"].as_object().unwrap() {
+        for (_, operation) in path.as_object().unwrap().iter().filter(|&(key, _)| key != "parameters") {
+            assert_eq!(operation["responses"]["default"]["schema"]["$ref"], json!("#/definitions/Error"));
+        }
+    }
+}
+
+#[test]
+fn it_shares_the_division_parameter_per_path() {
+    let endpoint = sample_endpoint();
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let path = &spec["paths"]["/api/v1/{division}/project/Projects"];
+    assert_eq!(path["parameters"], json!([{ "$ref": "#/parameters/Division" }]));
+    assert!(!path["get"]["parameters"].as_array().unwrap().contains(&json!({ "$ref": "#/parameters/Division" })));
+}
+
+#[test]
+fn it_overrides_info() {
+    use options::InfoOptions;
+
+    let endpoint = sample_endpoint();
+    let options = SpecOptions {
+        info: InfoOptions {
+            title: Some("Exact Online for Acme".to_owned()),
+            contact: Some(Contact { name: Some("Integrations team".to_owned()), url: None, email: None }),
+            ..InfoOptions::default()
+        },
+        ..SpecOptions::default()
+    };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    assert_eq!(spec["info"]["title"], json!("Exact Online for Acme"));
+    assert_eq!(spec["info"]["contact"], json!({ "name": "Integrations team" }));
+    assert_eq!(spec["info"]["license"]["name"], json!("MIT"));
+}
+
+#[test]
+fn it_adds_exact_extensions() {
+    let mut endpoint = sample_endpoint();
+    endpoint.required_packages = vec!["Project".to_owned()];
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let get = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"];
+    assert_eq!(get["x-exact-service"], json!("Project"));
+    assert_eq!(get["x-exact-scope"], json!(["projects.read"]));
+    assert_eq!(get["x-exact-package"], json!(["Project"]));
+    assert_eq!(spec["definitions"]["ProjectProjectsPut"]["properties"]["ID"]["x-exact-key"], json!(true));
+}
+
+#[test]
+fn it_models_webhooks() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions {
+        include_webhooks: true,
+        webhook_topics: vec!["Accounts".to_owned()],
+        ..SpecOptions::default()
+    };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let subscriptions = &spec["paths"]["/api/v1/{division}/webhooks/WebhookSubscriptions"];
+    assert!(subscriptions.get("post").is_some());
+    assert_eq!(spec["definitions"]["WebhooksWebhookSubscriptionsPost"]["properties"]["Topic"]["enum"], json!(["Accounts"]));
+    assert_eq!(spec["x-webhooks"]["Accounts"]["$ref"], json!("#/definitions/AccountsWebhookPayload"));
+    let content = &spec["definitions"]["AccountsWebhookPayload"]["properties"]["Content"];
+    assert_eq!(content["properties"]["Topic"]["enum"], json!(["Accounts"]));
+    // Keys are typed like the keys of the entities they name
+    let id = &spec["definitions"]["ProjectProjectsPut"]["properties"]["ID"];
+    assert_eq!(content["properties"]["Key"]["format"], id["format"]);
+    assert_eq!(content["properties"]["ClientId"]["format"], id["format"]);
+
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert!(spec.get("x-webhooks").is_none());
+    assert!(spec["definitions"].get("AccountsWebhookPayload").is_none());
+}
+
+#[test]
+fn it_adds_count_paths() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { include_count_paths: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let count = &spec["paths"]["/api/v1/{division}/project/Projects/$count"]["get"];
+    assert_eq!(count["operationId"], json!("countProjectProjects"));
+    assert_eq!(count["responses"]["200"]["schema"]["type"], json!("integer"));
+    assert_eq!(count["parameters"], json!([{ "$ref": "#/parameters/filter" }]));
+
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert!(spec["paths"].get("/api/v1/{division}/project/Projects/$count").is_none());
+}
+
+#[test]
+fn it_adds_concurrency_headers() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { concurrency_headers: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let keyed = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"];
+    for method in &["put", "delete"] {
+        assert!(keyed[*method]["parameters"].as_array().unwrap().contains(&json!({ "$ref": "#/parameters/IfMatch" })));
+    }
+    assert_eq!(spec["parameters"]["IfMatch"]["in"], json!("header"));
+    assert_eq!(keyed["get"]["responses"]["200"]["headers"]["ETag"]["type"], json!("string"));
+
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert!(spec["parameters"].get("IfMatch").is_none());
+}
+
+#[test]
+fn it_adds_the_prefer_header() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { prefer_header: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let post = &spec["paths"]["/api/v1/{division}/project/Projects"]["post"];
+    assert!(post["parameters"].as_array().unwrap().contains(&json!({ "$ref": "#/parameters/Prefer" })));
+    assert_eq!(spec["parameters"]["Prefer"]["enum"], json!(["return=representation", "return=minimal"]));
+}
+
+#[test]
+fn it_supports_binary_payloads() {
+    let mut endpoint = sample_endpoint();
+    endpoint.properties[1].edm_type = EdmType::Binary;
+    let options = SpecOptions { binary_format: BinaryFormat::Base64, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    // A binary column doesn't make the entity a file
+    assert!(spec["paths"].get("/api/v1/{division}/project/Projects(guid'{id}')/$value").is_none());
+    assert_eq!(spec["definitions"]["ProjectProjectsPost"]["properties"]["Code"]["format"], json!("byte"));
+
+    let options = SpecOptions { file_endpoints: vec!["ProjectProjects".to_owned()], ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let collection = &spec["paths"]["/api/v1/{division}/project/Projects"];
+    assert!(collection["post"].get("consumes").is_none());
+    assert!(collection["get"].get("produces").is_none());
+    assert_eq!(spec["definitions"]["ProjectProjectsPost"]["properties"]["Code"]["format"], json!("edm-binary"));
+    assert_eq!(spec["x-edm-conventions"], json!({ "Edm.Binary": { "type": "string", "format": "edm-binary" } }));
+    let value = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')/$value"];
+    assert_eq!(value["get"]["operationId"], json!("downloadProjectProjects"));
+    assert_eq!(value["get"]["produces"], json!(["application/octet-stream"]));
+    assert_eq!(value["get"]["responses"]["200"]["schema"], json!({ "type": "file" }));
+    assert_eq!(value["put"]["consumes"], json!(["application/octet-stream"]));
+    let body = value["put"]["parameters"].as_array().expect("Parameters").iter()
+        .find(|p| p["in"] == json!("body")).cloned().expect("Body");
+    assert_eq!(body["schema"], json!({ "type": "string", "format": "binary" }));
+    assert_eq!(::validation::validate_spec(&spec), Vec::new());
+}
+
+#[test]
+fn it_optionally_includes_atom() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions { include_atom: true, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    assert_eq!(spec["produces"], json!(["application/json", "application/atom+xml"]));
+    assert_eq!(spec["consumes"], json!(["application/json", "application/atom+xml"]));
+
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    assert_eq!(spec["produces"], json!(["application/json"]));
+}
+
+#[test]
+fn it_generates_resolvable_refs() {
+    let endpoint = sample_endpoint();
+    let options = SpecOptions {
+        strict_refs: true,
+        include_count_paths: true,
+        include_webhooks: true,
+        compose_definitions: true,
+        typed_select: true,
+        ..SpecOptions::default()
+    };
+    build_document(&[&endpoint], &options).expect("Spec without dangling refs");
+    let options = SpecOptions { spec_version: SpecVersion::OpenApi3, ..options };
+    build_document(&[&endpoint], &options).expect("Spec without dangling refs");
+}
+
+#[test]
+fn it_generates_valid_specs() {
+    let mut singleton = sample_endpoint();
+    singleton.singleton = true;
+    singleton.uri = "/api/v1/current/Me".to_owned();
+    singleton.name = "SystemSystemMe".to_owned();
+    singleton.requires_division = false;
+    singleton.methods = vec![Method::Get];
+    let endpoints = [&sample_endpoint(), &singleton];
+    let options = SpecOptions {
+        include_count_paths: true,
+        include_webhooks: true,
+        compose_definitions: true,
+        typed_select: true,
+        concurrency_headers: true,
+        prefer_header: true,
+        ..SpecOptions::default()
+    };
+    let openapi3 = SpecOptions { spec_version: SpecVersion::OpenApi3, ..options.clone() };
+    for options in vec![SpecOptions::default(), options, openapi3] {
+        let spec = build_document(&endpoints, &options).expect("Spec");
+        assert_eq!(::validation::validate_spec(&spec), Vec::new());
+        assert_eq!(::lint::lint_spec(&spec, &::lint::LintOptions::default()), Vec::new());
+    }
+}
+
+#[test]
+fn it_applies_hooks_before_serializing() {
+    let endpoint = sample_endpoint();
+    let hook = |spec: &mut Spec| {
+        spec.paths.remove("/api/v1/{division}/project/Projects(guid'{id}')");
+        spec.info.title = Some("Projects".to_owned());
+    };
+    let spec = build_document_with_hook(&[&endpoint], &SpecOptions::default(), &hook).expect("Spec");
+    assert_eq!(spec["info"]["title"], json!("Projects"));
+    assert!(spec["paths"].get("/api/v1/{division}/project/Projects(guid'{id}')").is_none());
+    assert!(spec["paths"].get("/api/v1/{division}/project/Projects").is_some());
+
+    // Annotations of parameters follow them when the hook removes the ones before
+    let hook = |spec: &mut Spec| {
+        let get = spec.paths.get_mut("/api/v1/{division}/project/Projects").and_then(|p| p.get.as_mut()).expect("GET");
+        get.parameters.as_mut().expect("Parameters").remove(0);
+    };
+    let options = SpecOptions { typed_select: true, ..SpecOptions::default() };
+    let spec = build_document_with_hook(&[&endpoint], &options, &hook).expect("Spec");
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().expect("Parameters");
+    assert!(!parameters.contains(&json!({ "$ref": "#/parameters/filter" })));
+    let select = parameters.iter().find(|p| p["name"] == json!("$select")).expect("$select");
+    assert_eq!(select["collectionFormat"], json!("csv"));
+    assert_eq!(select["items"]["enum"], json!(["ID", "Code", "Created"]));
+    let top = parameters.iter().find(|p| p["name"] == json!("$top")).expect("$top");
+    assert_eq!(top["maximum"], json!(60));
+    assert!(parameters.iter().filter(|p| p.get("collectionFormat").is_some()).count() == 1);
+}
+
+#[test]
+fn it_maps_types_with_the_configured_mapper() {
+    #[derive(Debug)]
+    struct NumericDecimals;
+    impl TypeMapper for NumericDecimals {
+        fn map_type(&self, edm: &EdmType, options: &SpecOptions) -> OpenApiType {
+            match *edm {
+                EdmType::DateTime => OpenApiType::new("string", Some("date-time")),
+                EdmType::Decimal => OpenApiType::new("number", None),
+                EdmType::Int16 => OpenApiType::new("integer", None),
+                _ => DefaultTypeMapper.map_type(edm, options),
+            }
+        }
+    }
+    let mut endpoint = sample_endpoint();
+    endpoint.properties.push(Property { name: "Budget".to_owned(), edm_type: EdmType::Decimal, ..endpoint.properties[2].clone() });
+    endpoint.properties.push(Property { name: "Priority".to_owned(), edm_type: EdmType::Int16, ..endpoint.properties[2].clone() });
+    let options = SpecOptions { type_mapper: Some(::std::sync::Arc::new(NumericDecimals)), ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsSingleResponse"]["properties"]["d"]["properties"];
+    assert_eq!(properties["Created"]["format"], json!("date-time"));
+    assert_eq!(properties["ID"]["format"], json!("guid"));
+    // Only the types the endpoints use are documented, none of the default oddities here
+    assert!(spec.get("x-edm-conventions").is_none());
+    // The annotations follow the mapped types rather than the format mapping
+    assert_eq!(properties["Created"]["x-edm-type"], json!("Edm.DateTime"));
+    assert_eq!(properties["Budget"]["x-edm-type"], json!("Edm.Decimal"));
+    assert_eq!((&properties["Priority"]["minimum"], &properties["Priority"]["maximum"]), (&json!(-32768), &json!(32767)));
+    assert!(properties["ID"].get("x-edm-type").is_none());
+
+    let options = SpecOptions { format_mapping: FormatMapping::Standard, guid_pattern: true, ..options };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsSingleResponse"]["properties"]["d"]["properties"];
+    assert!(properties["Budget"].get("pattern").is_none());
+    assert_eq!(properties["ID"]["pattern"], json!(GUID_PATTERN));
+    let documented: Vec<&String> = spec["x-edm-conventions"].as_object().expect("Conventions").keys().collect();
+    assert_eq!(documented, vec!["Edm.DateTime", "Edm.Decimal", "Edm.Guid", "Edm.Int16"]);
+}
+
+#[test]
+fn it_names_definitions_and_refs_consistently() {
+    let mut options = SpecOptions { strict_refs: true, include_webhooks: true, ..SpecOptions::default() };
+    options.definition_naming.prefix = "Exact".to_owned();
+    options.definition_naming.casing = ::options::NameCasing::Snake;
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec without dangling refs");
+    assert!(spec["definitions"].get("exact_project_projects_response").is_some());
+    assert!(spec["definitions"].get("exact_error").is_some());
+    assert_eq!(spec["paths"]["/api/v1/{division}/project/Projects"]["post"]["parameters"][0]["schema"]["$ref"],
+        json!("#/definitions/exact_project_projects_post"));
+}
+
+#[test]
+fn it_renames_endpoints_and_tags() {
+    let mut options = SpecOptions { strict_refs: true, ..SpecOptions::default() };
+    options.renames.insert("ProjectProjects".to_owned(), "Projects".to_owned());
+    options.renames.insert("Project".to_owned(), "Projects and hours".to_owned());
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec without dangling refs");
+    let get = &spec["paths"]["/api/v1/{division}/project/Projects"]["get"];
+    assert_eq!(get["operationId"], json!("getProjects"));
+    assert_eq!(get["summary"], json!("List Projects"));
+    assert_eq!(get["tags"], json!(["Projects and hours"]));
+    assert_eq!(get["responses"]["200"]["schema"]["$ref"], json!("#/definitions/ProjectsResponse"));
+    assert_eq!(spec["tags"][0]["name"], json!("Projects and hours"));
+    assert!(spec["definitions"].get("ProjectProjectsResponse").is_none());
+
+    let mut accounts = sample_endpoint();
+    accounts.name = "CRMAccounts".to_owned();
+    accounts.uri = "/api/v1/{division}/crm/Accounts".to_owned();
+    options.renames.insert("CRMAccounts".to_owned(), "Projects".to_owned());
+    match build_document(&[&sample_endpoint(), &accounts], &options) {
+        Err(Error(InvalidOptions(_), _)) => {},
+        other => panic!("Expected colliding renames to fail, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_applies_property_rules() {
+    let mut options = SpecOptions { strict_refs: true, ..SpecOptions::default() };
+    let mut rules = ::options::PropertyRules::default();
+    rules.exclude = vec!["Created".to_owned()];
+    rules.rename.insert("Code".to_owned(), "ProjectNumber".to_owned());
+    options.property_rules.insert("ProjectProjects".to_owned(), rules);
+    options.typed_select = true;
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec without dangling refs");
+    let post = &spec["definitions"]["ProjectProjectsPost"];
+    assert_eq!(post["required"], json!(["ProjectNumber"]));
+    assert_eq!(post["properties"]["ProjectNumber"]["x-exact-name"], json!("Code"));
+    assert!(post["properties"].get("Code").is_none());
+    let entity = &spec["definitions"]["ProjectProjectsResponse"]["properties"]["d"]["properties"]["results"]["items"];
+    let properties: Vec<&String> = entity["properties"].as_object().expect("Properties").keys().collect();
+    assert_eq!(properties, vec!["ID", "ProjectNumber"]);
+    // The API only knows the documented names
+    let parameters = spec["paths"]["/api/v1/{division}/project/Projects"]["get"]["parameters"].as_array().expect("Parameters");
+    let select = parameters.iter().find(|p| p["name"] == json!("$select")).expect("$select");
+    assert_eq!(select["items"]["enum"], json!(["ID", "Code"]));
+
+    // Keys can't be excluded
+    options.property_rules.get_mut("ProjectProjects").expect("Rules").exclude.push("ID".to_owned());
+    match build_document(&[&sample_endpoint()], &options) {
+        Err(Error(InvalidOptions(_), _)) => {},
+        other => panic!("Expected invalid options, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_builds_a_spec_per_service() {
+    let mut payroll = sample_endpoint();
+    payroll.name = "PayrollEmploymentContracts".to_owned();
+    payroll.uri = "/api/v1/{division}/payroll/EmploymentContracts".to_owned();
+    payroll.service = Some("Payroll".to_owned());
+    let mut options = SpecOptions { strict_refs: true, ..SpecOptions::default() };
+    options.service_infos.insert("Payroll".to_owned(), InfoOptions {
+        title: Some("Payroll API".to_owned()),
+        version: Some("2".to_owned()),
+        ..InfoOptions::default()
+    });
+    let documents = build_service_documents(&[&sample_endpoint(), &payroll], &options).expect("Specs without dangling refs");
+    let services: Vec<&String> = documents.keys().collect();
+    assert_eq!(services, vec!["Payroll", "Project"]);
+
+    let project = &documents["Project"];
+    assert_eq!(project["info"]["title"], json!("Exact Online REST API: Project"));
+    assert!(project["paths"].get("/api/v1/{division}/project/Projects").is_some());
+    assert!(project["paths"].get("/api/v1/{division}/payroll/EmploymentContracts").is_none());
+    assert!(project["definitions"].get("PayrollEmploymentContractsResponse").is_none());
+
+    let payroll = &documents["Payroll"];
+    assert_eq!(payroll["info"]["title"], json!("Payroll API"));
+    assert_eq!(payroll["info"]["version"], json!("2"));
+    assert!(payroll["definitions"].get("PayrollEmploymentContractsResponse").is_some());
+    assert!(payroll["definitions"].get("ProjectProjectsResponse").is_none());
+}
+
+#[test]
+fn it_attaches_examples() {
+    let mut endpoint = sample_endpoint();
+    endpoint.examples.insert("GET".to_owned(), json!({ "d": { "results": [{ "Code": "P1" }, { "Code": "P2" }] } }));
+    endpoint.examples.insert("POST".to_owned(), json!({ "Code": "P3" }));
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let path = &spec["paths"]["/api/v1/{division}/project/Projects"];
+    assert_eq!(path["get"]["responses"]["200"]["examples"]["application/json"],
+        json!({ "d": { "results": [{ "Code": "P1" }, { "Code": "P2" }] } }));
+    assert_eq!(path["post"]["parameters"][0]["x-examples"]["application/json"], json!({ "Code": "P3" }));
+    // Single entities are shown with the first entity of the page
+    assert_eq!(path["post"]["responses"]["201"]["examples"]["application/json"], json!({ "d": { "Code": "P1" } }));
+    let keyed = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"];
+    assert_eq!(keyed["get"]["responses"]["200"]["examples"]["application/json"], json!({ "d": { "Code": "P1" } }));
+
+    let options = SpecOptions { flatten_envelope: true, spec_version: SpecVersion::OpenApi3, ..SpecOptions::default() };
+    let spec = build_document(&[&endpoint], &options).expect("Spec");
+    let path = &spec["paths"]["/api/v1/{division}/project/Projects"];
+    assert_eq!(path["get"]["responses"]["200"]["content"]["application/json"]["example"],
+        json!([{ "Code": "P1" }, { "Code": "P2" }]));
+    assert_eq!(path["post"]["requestBody"]["content"]["application/json"]["example"], json!({ "Code": "P3" }));
+    assert_eq!(path["post"]["responses"]["201"]["content"]["application/json"]["example"], json!({ "Code": "P1" }));
+}
+
+#[test]
+fn it_titles_and_describes_every_definition() {
+    let options = SpecOptions { include_webhooks: true, compose_definitions: true, ..SpecOptions::default() };
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec");
+    let definitions = spec["definitions"].as_object().expect("Definitions");
+    for (name, definition) in definitions {
+        assert!(definition.get("title").is_some(), "{} has no title", name);
+        assert!(definition.get("description").is_some(), "{} has no description", name);
+    }
+    assert_eq!(definitions["ProjectProjects"]["title"], json!("ProjectProjects"));
+    assert_eq!(definitions["ProjectProjectsResponse"]["title"], json!("ProjectProjectsResponse"));
+    assert_eq!(definitions["ProjectProjectsPost"]["title"], json!("ProjectProjectsPost"));
+    assert_eq!(definitions["AccountsWebhookPayload"]["title"], json!("AccountsWebhookPayload"));
+    for (name, definition) in definitions {
+        assert!(!definition["title"].as_str().unwrap_or_default().contains(' '), "{} has a title with spaces", name);
+    }
+}
+
+#[test]
+fn it_closes_schemas() {
+    let options = SpecOptions { closed_schemas: true, ..SpecOptions::default() };
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec");
+    let response = &spec["definitions"]["ProjectProjectsResponse"];
+    assert_eq!(response["additionalProperties"], json!(false));
+    assert_eq!(response["properties"]["d"]["additionalProperties"], json!(false));
+    assert_eq!(response["properties"]["d"]["properties"]["results"]["items"]["additionalProperties"], json!(false));
+    assert_eq!(spec["definitions"]["ProjectProjectsPost"]["additionalProperties"], json!(false));
+
+    let options = SpecOptions { closed_schemas: true, compose_definitions: true, ..SpecOptions::default() };
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec");
+    assert_eq!(spec["definitions"]["ProjectProjects"]["additionalProperties"], json!(false));
+    let parts = spec["definitions"]["ProjectProjectsPost"]["allOf"].as_array().expect("Composed definition");
+    assert!(parts.iter().all(|part| part.get("additionalProperties").is_none()));
+
+    // What Exact answers passes, including the OData metadata and paging
+    let spec = build_document(&[&sample_endpoint()], &SpecOptions { closed_schemas: true, ..SpecOptions::default() }).expect("Spec");
+    let page = json!({ "d": {
+        "results": [{
+            "__metadata": {
+                "uri": "https://start.exactonline.nl/api/v1/1/project/Projects(guid'2a6e6e1b-0ee8-4a3b-9a1e-1e7a1a1a1a1a')",
+                "type": "Exact.Web.Api.Models.Project"
+            },
+            "ID": "2a6e6e1b-0ee8-4a3b-9a1e-1e7a1a1a1a1a",
+            "Code": "P-001",
+            "Created": "/Date(1500000000000)/"
+        }],
+        "__next": "https://start.exactonline.nl/api/v1/1/project/Projects?$skiptoken=guid'2a6e6e1b-0ee8-4a3b-9a1e-1e7a1a1a1a1a'"
+    } });
+    assert_eq!(unknown_members(&spec, &spec["definitions"]["ProjectProjectsResponse"], &page), Vec::<String>::new());
+    let mut unknown = page.clone();
+    unknown["d"]["results"][0]["Budget"] = json!(100);
+    assert_eq!(unknown_members(&spec, &spec["definitions"]["ProjectProjectsResponse"], &unknown), vec!["Budget"]);
+}
+
+/// Members of `value` that the closed schemas it should follow don't allow
+#[cfg(test)]
+fn unknown_members(spec: &Value, schema: &Value, value: &Value) -> Vec<String> {
+    if let Some(reference) = schema.get("$ref").and_then(|r| r.as_str()) {
+        let target = spec.pointer(&reference[1..]).expect("Resolvable reference");
+        return unknown_members(spec, target, value);
+    }
+    let mut unknown = Vec::new();
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    if let (Some(properties), Some(members)) = (properties, value.as_object()) {
+        for (name, member) in members {
+            match properties.get(name) {
+                Some(property) => unknown.extend(unknown_members(spec, property, member)),
+                None if schema.get("additionalProperties") == Some(&json!(false)) => unknown.push(name.clone()),
+                None => {},
+            }
+        }
+    }
+    if let (Some(items), Some(elements)) = (schema.get("items"), value.as_array()) {
+        for element in elements {
+            unknown.extend(unknown_members(spec, items, element));
+        }
+    }
+    unknown
+}
+
+#[test]
+fn it_emits_documented_defaults() {
+    use acquisition::AllowedValue;
+
+    let mut endpoint = sample_endpoint();
+    endpoint.properties[1].default = Some("P-001".to_owned());
+    endpoint.properties[2].default = Some("today".to_owned());
+    let template = endpoint.properties[1].clone();
+    endpoint.properties.push(Property {
+        name: "Status".to_owned(),
+        edm_type: EdmType::Int16,
+        default: Some("Open".to_owned()),
+        allowed_values: vec![
+            AllowedValue { value: "1".to_owned(), description: "Open".to_owned() },
+            AllowedValue { value: "2".to_owned(), description: "Closed".to_owned() },
+        ],
+        ..template.clone()
+    });
+    endpoint.properties.push(Property {
+        name: "Blocked".to_owned(),
+        edm_type: EdmType::Boolean,
+        default: Some("False".to_owned()),
+        ..template.clone()
+    });
+    endpoint.properties.push(Property {
+        name: "Hours".to_owned(),
+        edm_type: EdmType::Double,
+        default: Some("none".to_owned()),
+        ..template
+    });
+    let spec = build_document(&[&endpoint], &SpecOptions::default()).expect("Spec");
+    let properties = &spec["definitions"]["ProjectProjectsResponse"]["properties"]["d"]["properties"]["results"]["items"]["properties"];
+    assert_eq!(properties["Code"]["default"], json!("P-001"));
+    assert_eq!(properties["Status"]["default"], json!(1));
+    assert_eq!(properties["Blocked"]["default"], json!(false));
+    // Defaults that aren't values of the type are prose
+    assert!(properties["Created"].get("default").is_none());
+    assert!(properties["Hours"].get("default").is_none());
+}
+
+#[test]
+fn it_overrides_media_types_per_operation() {
+    let mut options = SpecOptions::default();
+    let mut overrides = BTreeMap::new();
+    overrides.insert("GET".to_owned(), ::options::MediaTypes {
+        produces: Some(vec!["application/pdf".to_owned()]),
+        ..::options::MediaTypes::default()
+    });
+    options.media_types.insert("ProjectProjects".to_owned(), overrides);
+    options.include_count_paths = true;
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec");
+    let paths = &spec["paths"];
+    assert_eq!(paths["/api/v1/{division}/project/Projects"]["get"]["produces"], json!(["application/pdf"]));
+    assert_eq!(paths["/api/v1/{division}/project/Projects(guid'{id}')"]["get"]["produces"], json!(["application/pdf"]));
+    assert!(paths["/api/v1/{division}/project/Projects"]["post"].get("produces").is_none());
+    assert_eq!(paths["/api/v1/{division}/project/Projects/$count"]["get"]["produces"], json!(["text/plain"]));
+    let get = &paths["/api/v1/{division}/project/Projects(guid'{id}')"]["get"];
+    assert_eq!(get["responses"]["200"]["schema"], json!({ "type": "file" }));
+    assert_eq!(get["responses"]["500"]["schema"]["$ref"], json!("#/definitions/Error"));
+    assert_eq!(paths["/api/v1/{division}/project/Projects"]["post"]["responses"]["201"]["schema"]["$ref"],
+        json!("#/definitions/ProjectProjectsPostResponse"));
+
+    options.spec_version = SpecVersion::OpenApi3;
+    let spec = build_document(&[&sample_endpoint()], &options).expect("Spec");
+    let content = &spec["paths"]["/api/v1/{division}/project/Projects(guid'{id}')"]["get"]["responses"]["200"]["content"];
+    assert_eq!(content["application/pdf"]["schema"], json!({ "type": "string", "format": "binary" }));
+}
@@ -0,0 +1,324 @@
+//! Validation of a generated spec against the official JSON Schema of its version, and the rules of
+//! the specification such a schema can't express
+
+use annotations::pointer;
+use json_schema::JsonSchema;
+
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The official schemas, the Swagger 2.0 one refers to the JSON Schema meta-schema
+const SWAGGER_2_SCHEMA: &'static str = include_str!("schemas/swagger-2.0.json");
+const OPENAPI_3_SCHEMA: &'static str = include_str!("schemas/openapi-3.0.json");
+const DRAFT_4_SCHEMA: &'static str = include_str!("schemas/json-schema-draft-04.json");
+
+/// The methods a path item can have operations for
+pub(crate) const OPERATION_METHODS: &'static [&'static str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+/// A rule of the specification the document breaks, `location` points at the offending part
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Collects the violations as the document is walked
+struct Validator<'a> {
+    spec: &'a Value,
+    /// Whether the document is OpenAPI 3.0 rather than Swagger 2.0
+    openapi3: bool,
+    operation_ids: BTreeMap<String, String>,
+    violations: Vec<Violation>,
+}
+
+/// Checks a Swagger 2.0 or OpenAPI 3.0 document against the official JSON Schema of its version,
+/// then the rules its JSON Schema can't express, like path templates matching the path parameters,
+/// operation ids being unique and `$ref`s pointing at something
+pub fn validate_spec(spec: &Value) -> Vec<Violation> {
+    let openapi3 = spec.get("openapi").is_some();
+    let schema = if openapi3 {
+        JsonSchema::new(OPENAPI_3_SCHEMA, &[])
+    } else {
+        JsonSchema::new(SWAGGER_2_SCHEMA, &[DRAFT_4_SCHEMA])
+    };
+    let mut violations = schema.expect("Valid embedded schema").validate(spec);
+    let mut validator = Validator { spec: spec, openapi3: openapi3, operation_ids: BTreeMap::new(), violations: Vec::new() };
+    validator.validate_root();
+    violations.extend(validator.violations);
+    violations
+}
+
+impl<'a> Validator<'a> {
+    fn violation<S: AsRef<str>>(&mut self, location: &[S], message: String) {
+        self.violations.push(Violation { location: pointer(location), message: message });
+    }
+
+    /// What `value` refers to when it is a local reference, the value itself otherwise
+    fn resolve(&self, value: &'a Value) -> Option<&'a Value> {
+        match value.get("$ref").and_then(|r| r.as_str()) {
+            Some(target) if target.starts_with('#') => self.spec.pointer(&target[1..]),
+            Some(_) => None,
+            None => Some(value),
+        }
+    }
+
+    /// Where the document keeps the named objects of `kind`, like `["definitions"]` for schemas
+    fn components(&self, kind: &str) -> Vec<&'static str> {
+        match (self.openapi3, kind) {
+            (true, "schemas") => vec!["components", "schemas"],
+            (true, _) => vec!["components", "securitySchemes"],
+            (false, "schemas") => vec!["definitions"],
+            (false, _) => vec!["securityDefinitions"],
+        }
+    }
+
+    fn validate_root(&mut self) {
+        let spec = self.spec;
+        if let Some(paths) = spec.get("paths").and_then(|p| p.as_object()) {
+            for (path, item) in paths.iter().filter(|&(path, _)| !path.starts_with("x-")) {
+                self.validate_path_item(path, item);
+            }
+        }
+        let location: Vec<String> = self.components("schemas").iter().map(|s| s.to_string()).collect();
+        if let Some(definitions) = spec.pointer(&pointer(&location)).and_then(|d| d.as_object()) {
+            for (name, schema) in definitions {
+                let mut location = location.clone();
+                location.push(name.clone());
+                self.validate_schema(&location, schema);
+            }
+        }
+    }
+
+    fn validate_path_item(&mut self, path: &str, item: &'a Value) {
+        let shared = self.parameters(&["paths", path, "parameters"], item.get("parameters"));
+        for method in OPERATION_METHODS {
+            if let Some(operation) = item.get(*method) {
+                self.validate_operation(path, method, operation, &shared);
+            }
+        }
+    }
+
+    /// The parameters by name and location, after checking each of them
+    fn parameters(&mut self, location: &[&str], parameters: Option<&'a Value>) -> BTreeMap<(String, String), &'a Value> {
+        let mut found = BTreeMap::new();
+        let parameters = match parameters {
+            Some(&Value::Array(ref parameters)) => parameters,
+            _ => return found,
+        };
+        for (i, parameter) in parameters.iter().enumerate() {
+            let index = i.to_string();
+            let mut location = location.to_vec();
+            location.push(&index);
+            let parameter = match self.resolve(parameter) {
+                Some(parameter) => parameter,
+                None => {
+                    self.violation(&location, "refers to a missing parameter".to_owned());
+                    continue;
+                },
+            };
+            let name = parameter.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_owned();
+            let place = parameter.get("in").and_then(|n| n.as_str()).unwrap_or_default().to_owned();
+            // Body parameters and those of OpenAPI 3.0 have a schema, others are typed themselves
+            match parameter.get("schema") {
+                Some(schema) => {
+                    let mut location: Vec<String> = location.iter().map(|s| s.to_string()).collect();
+                    location.push("schema".to_owned());
+                    self.validate_schema(&location, schema);
+                },
+                None if parameter.get("type") == Some(&json!("array")) && parameter.get("items").is_none() =>
+                    self.violation(&location, "is an array without items".to_owned()),
+                None => {},
+            }
+            if found.insert((name.clone(), place.clone()), parameter).is_some() {
+                self.violation(&location, format!("repeats {} parameter '{}'", place, name));
+            }
+        }
+        found
+    }
+
+    fn validate_operation(&mut self, path: &str, method: &str, operation: &'a Value,
+            shared: &BTreeMap<(String, String), &'a Value>) {
+        let location = ["paths", path, method];
+        if let Some(id) = operation.get("operationId").and_then(|i| i.as_str()) {
+            let here = pointer(&location);
+            if let Some(first) = self.operation_ids.insert(id.to_owned(), here.clone()) {
+                self.violation(&location, format!("repeats operationId '{}' of {}", id, first));
+            }
+        }
+
+        let mut parameters = shared.clone();
+        let own = self.parameters(&["paths", path, method, "parameters"], operation.get("parameters"));
+        parameters.extend(own);
+        let places: Vec<&str> = parameters.keys().map(|&(_, ref place)| place.as_str()).collect();
+        if places.iter().filter(|&&p| p == "body").count() > 1 {
+            self.violation(&location, "has more than one body parameter".to_owned());
+        }
+        if places.contains(&"body") && places.contains(&"formData") {
+            self.violation(&location, "has both body and formData parameters".to_owned());
+        }
+        let declared: BTreeSet<&str> = parameters.keys()
+            .filter(|&&(_, ref place)| place == "path")
+            .map(|&(ref name, _)| name.as_str())
+            .collect();
+        let templated = template_names(path);
+        for name in templated.difference(&declared) {
+            self.violation(&location, format!("has no path parameter for '{{{}}}'", name));
+        }
+        for name in declared.difference(&templated) {
+            self.violation(&location, format!("has path parameter '{}', which the path doesn't contain", name));
+        }
+
+        if let Some(responses) = operation.get("responses").and_then(|r| r.as_object()) {
+            self.validate_responses(&location, responses);
+        }
+        if let Some(requirements) = operation.get("security").and_then(|s| s.as_array()) {
+            for requirement in requirements.iter().filter_map(|r| r.as_object()) {
+                for scheme in requirement.keys() {
+                    let mut scheme_location: Vec<&str> = self.components("securitySchemes");
+                    scheme_location.push(scheme);
+                    if self.spec.pointer(&pointer(&scheme_location)).is_none() {
+                        self.violation(&location, format!("requires undefined security scheme '{}'", scheme));
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_responses(&mut self, location: &[&str], responses: &'a Map<String, Value>) {
+        for (status, response) in responses.iter().filter(|&(status, _)| !status.starts_with("x-")) {
+            let mut location: Vec<String> = location.iter().map(|s| s.to_string()).collect();
+            location.push("responses".to_owned());
+            location.push(status.clone());
+            let response = match self.resolve(response) {
+                Some(response) => response,
+                None => {
+                    self.violation(&location, "refers to a missing response".to_owned());
+                    continue;
+                },
+            };
+            if let Some(schema) = response.get("schema") {
+                let mut location = location.clone();
+                location.push("schema".to_owned());
+                self.validate_schema(&location, schema);
+            }
+            // OpenAPI 3.0 has a schema per media type
+            for (media_type, content) in response.get("content").and_then(|c| c.as_object()).into_iter().flatten() {
+                if let Some(schema) = content.get("schema") {
+                    let mut location = location.clone();
+                    location.extend(vec!["content".to_owned(), media_type.clone(), "schema".to_owned()]);
+                    self.validate_schema(&location, schema);
+                }
+            }
+        }
+    }
+
+    fn validate_schema(&mut self, location: &[String], schema: &'a Value) {
+        if let Some(target) = schema.get("$ref").and_then(|r| r.as_str()) {
+            if self.resolve(schema).is_none() {
+                self.violation(location, format!("refers to missing '{}'", target));
+            }
+            return;
+        }
+        let nested = |member: &str| {
+            let mut location = location.to_vec();
+            location.push(member.to_owned());
+            location
+        };
+        if schema.get("type") == Some(&json!("array")) {
+            match schema.get("items") {
+                Some(items) => self.validate_schema(&nested("items"), items),
+                None => self.violation(location, "is an array without items".to_owned()),
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+            for (name, property) in properties {
+                let mut location = nested("properties");
+                location.push(name.clone());
+                self.validate_schema(&location, property);
+            }
+        }
+        if let Some(composed) = schema.get("allOf").and_then(|a| a.as_array()) {
+            for (i, part) in composed.iter().enumerate() {
+                let mut location = nested("allOf");
+                location.push(i.to_string());
+                self.validate_schema(&location, part);
+            }
+        }
+    }
+}
+
+/// Names of the `{templated}` segments of the path
+fn template_names(path: &str) -> BTreeSet<&str> {
+    path.split('{').skip(1).filter_map(|part| part.find('}').map(|end| &part[..end])).collect()
+}
+
+#[test]
+fn it_validates_specs() {
+    let spec = json!({
+        "swagger": "2.0",
+        "info": { "title": "API", "version": "1" },
+        "paths": {
+            "/a/{division}": {
+                "parameters": [{ "$ref": "#/parameters/Division" }],
+                "get": { "operationId": "getA", "responses": { "200": { "description": "OK" } } }
+            },
+            "/b/{id}": {
+                "get": {
+                    "operationId": "getA",
+                    "parameters": [{ "name": "id", "in": "path", "type": "string" }, { "name": "q", "in": "query" }],
+                    "responses": { "OK": { "schema": { "type": "array" } } }
+                },
+                "post": { "operationId": "postB", "responses": {} }
+            }
+        },
+        "parameters": { "Division": { "name": "division", "in": "path", "type": "integer", "required": true } }
+    });
+    let messages: Vec<String> = validate_spec(&spec).iter().map(|v| v.to_string()).collect();
+    assert_eq!(messages, vec![
+        "/paths/~1b~1{id}/get/parameters/0: is missing required member 'required'",
+        "/paths/~1b~1{id}/get/parameters/1: is missing required member 'type'",
+        "/paths/~1b~1{id}/get/responses/OK: is not allowed",
+        "/paths/~1b~1{id}/post/responses: matches the schema in not",
+        "/paths/~1b~1{id}/post/responses: must have at least 1 members",
+        "/paths/~1b~1{id}/get: repeats operationId 'getA' of /paths/~1a~1{division}/get",
+        "/paths/~1b~1{id}/get/responses/OK/schema: is an array without items",
+        "/paths/~1b~1{id}/post: has no path parameter for '{id}'",
+    ]);
+}
+
+#[test]
+fn it_validates_openapi3_specs() {
+    let spec = json!({
+        "openapi": "3.0.0",
+        "info": { "title": "API", "version": "1" },
+        "paths": {
+            "/a/{id}": {
+                "get": {
+                    "parameters": [{ "name": "id", "in": "path", "schema": { "type": "array" } }],
+                    "responses": {
+                        "200": {
+                            "description": "OK",
+                            "content": { "application/json": { "schema": { "$ref": "#/components/schemas/A" } } }
+                        }
+                    },
+                    "security": [{ "oauth2": [] }]
+                }
+            },
+            "/b": { "get": { "responses": { "200": { "description": "OK" } } } }
+        },
+        "components": { "securitySchemes": { "oauth2": { "type": "http", "scheme": "bearer" } } }
+    });
+    let messages: Vec<String> = validate_spec(&spec).iter().map(|v| v.to_string()).collect();
+    assert_eq!(messages, vec![
+        "/paths/~1a~1{id}/get/parameters/0: is missing required member 'required'",
+        "/paths/~1a~1{id}/get/parameters/0/schema: is an array without items",
+        "/paths/~1a~1{id}/get/responses/200/content/application~1json/schema: refers to missing '#/components/schemas/A'",
+    ]);
+}
@@ -0,0 +1,138 @@
+//! Webhook subscriptions and the payloads Exact posts to their callback urls
+
+use acquisition::{AllowedValue, EdmType, EndpointDetails, EndpointKind, Property, QueryOption};
+use openapi::Schema;
+use options::SpecOptions;
+use transform::{edm_schema, object_schema, string_schema};
+
+use reqwest::{Method, Url};
+use std::collections::BTreeMap;
+
+/// Name of the subscriptions resource, as the overview would name it
+pub const WEBHOOK_SUBSCRIPTIONS: &'static str = "WebhooksWebhookSubscriptions";
+
+/// The topics Exact documents for webhooks, used unless the options list others
+pub const WEBHOOK_TOPICS: &'static [&'static str] = &[
+    "Accounts", "BankAccounts", "Contacts", "CostTransactions", "Documents", "FinancialTransactions",
+    "GLAccounts", "Items", "ProjectWBS", "PurchaseOrders", "Quotations", "SalesInvoices", "SalesOrders",
+    "StockPositions", "TimeCostTransactions",
+];
+
+/// Members of the change Exact always posts
+const PAYLOAD_CONTENT: &'static [&'static str] = &[
+    "Topic", "ClientId", "Division", "Action", "Key", "ExactOnlineEndpoint", "EventCreatedOn",
+];
+
+/// Actions a webhook reports for the entity with the given key
+const WEBHOOK_ACTIONS: &'static [&'static str] = &["Create", "Update", "Delete"];
+
+fn property(name: &str, edm_type: EdmType, description: &str, methods: &[Method]) -> Property {
+    Property {
+        name: name.to_owned(),
+        edm_type: edm_type,
+        description: Some(description.to_owned()),
+        key: false,
+        methods: methods.to_vec(),
+        default: None,
+        allowed_values: Vec::new(),
+        nullable: true,
+        mandatory: false,
+        max_length: None,
+        precision: None,
+        scale: None,
+        navigation: false,
+    }
+}
+
+/// The `WebhookSubscriptions` resource, for when the scraped docs don't list it
+pub fn subscription_endpoint(topics: &[String]) -> EndpointDetails {
+    let read = [Method::Get];
+    let write = [Method::Get, Method::Post];
+    let mut id = property("ID", EdmType::Guid, "Primary key", &[Method::Get, Method::Delete]);
+    id.key = true;
+    id.nullable = false;
+    let mut callback = property("CallbackURL", EdmType::String,
+        "Url Exact posts the payloads to, must be https", &write);
+    callback.mandatory = true;
+    let mut topic = property("Topic", EdmType::String, "The resource to receive changes of", &write);
+    topic.mandatory = true;
+    topic.allowed_values = topics.iter()
+        .map(|t| AllowedValue { value: t.clone(), description: format!("Changes of {}", t) })
+        .collect();
+    let mut methods = BTreeMap::new();
+    methods.insert("POST".to_owned(), "The callback url must answer a validation request before the \
+        subscription is created.".to_owned());
+    EndpointDetails {
+        name: WEBHOOK_SUBSCRIPTIONS.to_owned(),
+        uri: "/api/v1/{division}/webhooks/WebhookSubscriptions".to_owned(),
+        description: Some("Subscriptions to changes of a topic, which Exact posts to the callback url.".to_owned()),
+        doc_url: Url::parse(&format!("https://start.exactonline.nl/docs/HlpRestAPIResourcesDetails.aspx?name={}",
+            WEBHOOK_SUBSCRIPTIONS)).expect("Valid url"),
+        requires_division: true,
+        kind: EndpointKind::Standard,
+        service: Some("Webhooks".to_owned()),
+        max_page_size: EndpointKind::Standard.default_page_size(),
+        function_parameters: Vec::new(),
+        singleton: false,
+        method_remarks: methods,
+        beta: false,
+        deprecated: false,
+        replaced_by: None,
+        required_packages: Vec::new(),
+        scopes: Vec::new(),
+        query_options: QueryOption::defaults(EndpointKind::Standard),
+        properties: vec![
+            id,
+            callback,
+            property("ClientID", EdmType::Guid, "The app the subscription belongs to", &read),
+            property("Created", EdmType::DateTime, "Creation date", &read),
+            property("Creator", EdmType::Guid, "User ID of the creator", &read),
+            property("CreatorFullName", EdmType::String, "Name of the creator", &read),
+            property("Description", EdmType::String, "Description of the subscription", &read),
+            property("Division", EdmType::Int32, "Division the subscription applies to", &read),
+            topic,
+            property("UserID", EdmType::Guid, "The user the changes are reported for", &read),
+        ],
+        failed_properties: Vec::new(),
+        methods: vec![Method::Get, Method::Post, Method::Delete],
+        unknown_methods: Vec::new(),
+        examples: BTreeMap::new(),
+    }
+}
+
+/// The body Exact posts to the callback url when an entity of the topic changes.
+/// It only names the entity, which is fetched from `ExactOnlineEndpoint`.
+pub fn build_payload_definition(topic: &str, options: &SpecOptions) -> Schema {
+    let content = object_schema(vec![
+        ("Topic", Schema {
+            enum_values: Some(vec![topic.to_owned()]),
+            ..string_schema("The topic subscribed to", None)
+        }),
+        ("ClientId", Schema {
+            description: Some("The app the subscription belongs to".to_owned()),
+            ..edm_schema(&EdmType::Guid, options)
+        }),
+        ("Division", Schema {
+            description: Some("Division of the changed entity".to_owned()),
+            ..edm_schema(&EdmType::Int32, options)
+        }),
+        ("Action", Schema {
+            enum_values: Some(WEBHOOK_ACTIONS.iter().map(|a| a.to_string()).collect()),
+            ..string_schema("What happened to the entity", None)
+        }),
+        ("Key", Schema {
+            description: Some("Key of the changed entity".to_owned()),
+            ..edm_schema(&EdmType::Guid, options)
+        }),
+        ("ExactOnlineEndpoint", string_schema("Url of the changed entity", None)),
+        ("EventCreatedOn", Schema {
+            description: Some("When the change happened".to_owned()),
+            ..edm_schema(&EdmType::DateTime, options)
+        }),
+    ], PAYLOAD_CONTENT, "The change");
+    object_schema(vec![
+        ("Content", content),
+        ("HashCode", string_schema("HMAC-SHA256 of `Content` keyed with the webhook secret of the app, \
+            to verify the sender", None)),
+    ], &["Content", "HashCode"], &format!("Notification of a change of {}", topic))
+}